@@ -83,6 +83,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     fs_extra::copy_items(&source_paths, &out_dir, &copy_options)?;
 
+    generate_keywords(
+        &out_dir.join("src/postgres/include/parser/kwlist.h"),
+        &out_dir.join("keywords.rs"),
+    )?;
+
     // Compile the C library.
     let mut build = cc::Build::new();
 
@@ -130,6 +135,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut bindgen_builder = bindgen::Builder::default()
         .header(out_header_path.to_str().ok_or("Invalid header path")?)
         // Allowlist only the functions we need
+        .allowlist_function("pg_query_parse")
         .allowlist_function("pg_query_parse_protobuf")
         .allowlist_function("pg_query_scan")
         .allowlist_function("pg_query_deparse_protobuf")
@@ -137,6 +143,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .allowlist_function("pg_query_fingerprint")
         .allowlist_function("pg_query_split_with_parser")
         .allowlist_function("pg_query_split_with_scanner")
+        .allowlist_function("pg_query_free_parse_result")
         .allowlist_function("pg_query_free_protobuf_parse_result")
         .allowlist_function("pg_query_free_scan_result")
         .allowlist_function("pg_query_free_deparse_result")
@@ -144,6 +151,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .allowlist_function("pg_query_free_fingerprint_result")
         .allowlist_function("pg_query_free_split_result")
         // Allowlist the types used by these functions
+        .allowlist_type("PgQueryParseResult")
         .allowlist_type("PgQueryProtobufParseResult")
         .allowlist_type("PgQueryScanResult")
         .allowlist_type("PgQueryError")
@@ -202,6 +210,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         if !bindings_content.contains("extern \"C\"") {
             bindings_content.push_str("\nextern \"C\" {\n");
             bindings_content.push_str("    pub fn pg_query_scan(input: *const ::std::os::raw::c_char) -> PgQueryScanResult;\n");
+            bindings_content.push_str("    pub fn pg_query_parse(input: *const ::std::os::raw::c_char) -> PgQueryParseResult;\n");
             bindings_content.push_str("    pub fn pg_query_parse_protobuf(input: *const ::std::os::raw::c_char) -> PgQueryProtobufParseResult;\n");
             bindings_content.push_str("    pub fn pg_query_deparse_protobuf(protobuf: PgQueryProtobuf) -> PgQueryDeparseResult;\n");
             bindings_content.push_str("    pub fn pg_query_normalize(input: *const ::std::os::raw::c_char) -> PgQueryNormalizeResult;\n");
@@ -210,6 +219,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             bindings_content.push_str("    pub fn pg_query_split_with_scanner(input: *const ::std::os::raw::c_char) -> PgQuerySplitResult;\n");
             bindings_content
                 .push_str("    pub fn pg_query_free_scan_result(result: PgQueryScanResult);\n");
+            bindings_content
+                .push_str("    pub fn pg_query_free_parse_result(result: PgQueryParseResult);\n");
             bindings_content.push_str("    pub fn pg_query_free_protobuf_parse_result(result: PgQueryProtobufParseResult);\n");
             bindings_content.push_str(
                 "    pub fn pg_query_free_deparse_result(result: PgQueryDeparseResult);\n",
@@ -253,3 +264,40 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Generates the keyword table from the `PG_KEYWORD` entries in the grammar's kwlist.h, e.g.
+/// `PG_KEYWORD("abort", ABORT_P, UNRESERVED_KEYWORD, BARE_LABEL)`
+fn generate_keywords(
+    kwlist_path: &std::path::Path,
+    out_path: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let kwlist = std::fs::read_to_string(kwlist_path)?;
+    let mut table = String::from("&[\n");
+    for line in kwlist.lines() {
+        let Some(args) = line
+            .strip_prefix("PG_KEYWORD(")
+            .and_then(|rest| rest.split_once(')'))
+            .map(|(args, _)| args)
+        else {
+            continue;
+        };
+        let args: Vec<_> = args.split(',').map(str::trim).collect();
+        let [name, _, category, label] = args[..] else {
+            return Err(format!("Unexpected keyword definition: {line}").into());
+        };
+        let kind = match category {
+            "UNRESERVED_KEYWORD" => "UnreservedKeyword",
+            "COL_NAME_KEYWORD" => "ColNameKeyword",
+            "TYPE_FUNC_NAME_KEYWORD" => "TypeFuncNameKeyword",
+            "RESERVED_KEYWORD" => "ReservedKeyword",
+            _ => return Err(format!("Unexpected keyword category: {category}").into()),
+        };
+        table.push_str(&format!(
+            "    Keyword {{ name: {name}, kind: KeywordKind::{kind}, bare_label: {} }},\n",
+            label == "BARE_LABEL"
+        ));
+    }
+    table.push(']');
+    std::fs::write(out_path, table)?;
+    Ok(())
+}
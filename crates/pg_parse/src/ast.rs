@@ -0,0 +1,44 @@
+//! The types that make up a parse tree.
+//!
+//! These are generated from libpg_query's `pg_query.proto`, which mirrors the node structs of
+//! Postgres. Most of them come from `src/include/nodes/parsenodes.h` (statements and raw
+//! grammar output such as [`SelectStmt`], [`ColumnRef`] or [`AExpr`]) and
+//! `src/include/nodes/primnodes.h` (shared expression nodes such as [`RangeVar`],
+//! [`BoolExpr`] or [`NullTest`]). The mapping is mechanical:
+//!
+//! - Each node struct becomes a struct of the same name in Pascal case, e.g. `A_Expr` is
+//!   [`AExpr`] and `CreateStmt` is [`CreateStmt`]. Field names are converted to snake case, and
+//!   fields that are Rust keywords get an `r#` prefix (`r#type`).
+//! - Fields of type `Node *` become `Option<Box<Node>>`, and `List *` fields become
+//!   `Vec<Node>`. [`Node`] wraps the [`node::Node`] enum, which [`NodeEnum`](crate::NodeEnum)
+//!   re-exports under a more convenient name.
+//! - C enums such as `A_Expr_Kind` become Rust enums ([`AExprKind`]). The structs store them as
+//!   `i32`; prost generates accessors such as [`AExpr::kind()`] that convert them, and
+//!   `TryFrom<i32>` converts them by hand.
+//! - Unions are modelled as `oneof` modules named after their struct, e.g. the value of
+//!   [`AConst`] is an [`a_const::Val`].
+//! - `location` fields hold byte offsets into the parsed query, or -1 if unknown.
+//!
+//! The results of [`scan`](crate::scan) ([`ScanResult`], [`ScanToken`], [`Token`] and
+//! [`KeywordKind`]) are defined here too.
+//!
+//! # Example
+//!
+//! ```rust
+//! use pg_parse::ast::{AExprKind, SelectStmt};
+//! use pg_parse::NodeEnum;
+//!
+//! fn where_operator(stmt: &SelectStmt) -> Option<AExprKind> {
+//!     match stmt.where_clause.as_ref()?.node.as_ref()? {
+//!         NodeEnum::AExpr(expr) => Some(expr.kind()),
+//!         _ => None,
+//!     }
+//! }
+//!
+//! let result = pg_parse::parse("SELECT * FROM t WHERE a = 1").unwrap();
+//! let NodeEnum::SelectStmt(stmt) = result.stmts()[0] else {
+//!     unreachable!()
+//! };
+//! assert_eq!(where_operator(stmt), Some(AExprKind::AexprOp));
+//! ```
+pub use crate::protobuf::*;
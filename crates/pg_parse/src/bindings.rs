@@ -63,6 +63,24 @@ const _: () = {
 };
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
+pub struct PgQueryParseResult {
+    pub parse_tree: *mut ::std::os::raw::c_char,
+    pub stderr_buffer: *mut ::std::os::raw::c_char,
+    pub error: *mut PgQueryError,
+}
+#[allow(clippy::unnecessary_operation, clippy::identity_op)]
+const _: () = {
+    ["Size of PgQueryParseResult"][::std::mem::size_of::<PgQueryParseResult>() - 24usize];
+    ["Alignment of PgQueryParseResult"][::std::mem::align_of::<PgQueryParseResult>() - 8usize];
+    ["Offset of field: PgQueryParseResult::parse_tree"]
+        [::std::mem::offset_of!(PgQueryParseResult, parse_tree) - 0usize];
+    ["Offset of field: PgQueryParseResult::stderr_buffer"]
+        [::std::mem::offset_of!(PgQueryParseResult, stderr_buffer) - 8usize];
+    ["Offset of field: PgQueryParseResult::error"]
+        [::std::mem::offset_of!(PgQueryParseResult, error) - 16usize];
+};
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
 pub struct PgQueryProtobufParseResult {
     pub parse_tree: PgQueryProtobuf,
     pub stderr_buffer: *mut ::std::os::raw::c_char,
@@ -177,6 +195,9 @@ unsafe extern "C" {
 unsafe extern "C" {
     pub fn pg_query_scan(input: *const ::std::os::raw::c_char) -> PgQueryScanResult;
 }
+unsafe extern "C" {
+    pub fn pg_query_parse(input: *const ::std::os::raw::c_char) -> PgQueryParseResult;
+}
 unsafe extern "C" {
     pub fn pg_query_parse_protobuf(
         input: *const ::std::os::raw::c_char,
@@ -200,6 +221,9 @@ unsafe extern "C" {
 unsafe extern "C" {
     pub fn pg_query_free_scan_result(result: PgQueryScanResult);
 }
+unsafe extern "C" {
+    pub fn pg_query_free_parse_result(result: PgQueryParseResult);
+}
 unsafe extern "C" {
     pub fn pg_query_free_split_result(result: PgQuerySplitResult);
 }
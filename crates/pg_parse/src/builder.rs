@@ -0,0 +1,903 @@
+//! Helpers for constructing statements without spelling out every protobuf struct.
+//!
+//! Expressions are plain [`NodeEnum`]s, so they can be mixed with nodes taken from a parsed
+//! query. Statements are assembled with builders that turn into a [`NodeEnum`] through
+//! `build()` or `Into<NodeEnum>`. All built nodes have unknown locations.
+//!
+//! # Example
+//!
+//! ```rust
+//! use pg_parse::builder::*;
+//!
+//! let stmt = select()
+//!     .columns([col("a"), alias(col("u.b"), "c")])
+//!     .from(table("public", "users").alias("u"))
+//!     .filter(eq(col("id"), param(1)))
+//!     .build();
+//!
+//! assert_eq!(
+//!     stmt.deparse().unwrap(),
+//!     "SELECT a, u.b AS c FROM public.users u WHERE id = $1"
+//! );
+//! ```
+use crate::protobuf::{self, a_const, Node};
+use crate::{Error, NodeEnum, Result};
+
+fn node(node: NodeEnum) -> Node {
+    Node { node: Some(node) }
+}
+
+fn boxed(n: NodeEnum) -> Option<Box<Node>> {
+    Some(Box::new(node(n)))
+}
+
+fn string(value: &str) -> Node {
+    node(NodeEnum::String(protobuf::String {
+        sval: value.to_string(),
+    }))
+}
+
+fn names(name: &str) -> Vec<Node> {
+    name.split('.').map(string).collect()
+}
+
+fn constant(val: a_const::Val) -> NodeEnum {
+    NodeEnum::AConst(protobuf::AConst {
+        isnull: false,
+        location: -1,
+        val: Some(val),
+    })
+}
+
+/// A column reference. Dots separate the table from the column name, and `*` selects all
+/// columns, e.g. `col("u.id")` or `col("u.*")`.
+pub fn col(name: &str) -> NodeEnum {
+    let fields = name
+        .split('.')
+        .map(|part| match part {
+            "*" => node(NodeEnum::AStar(protobuf::AStar {})),
+            part => string(part),
+        })
+        .collect();
+    NodeEnum::ColumnRef(protobuf::ColumnRef {
+        fields,
+        location: -1,
+    })
+}
+
+/// Selects all columns, i.e. `*`.
+pub fn star() -> NodeEnum {
+    col("*")
+}
+
+/// A positional parameter like `$1`.
+pub fn param(number: i32) -> NodeEnum {
+    NodeEnum::ParamRef(protobuf::ParamRef {
+        number,
+        location: -1,
+    })
+}
+
+/// An integer constant. Values outside the range of `i32` are stored as numeric constants, like
+/// the parser does.
+pub fn int(value: i64) -> NodeEnum {
+    match i32::try_from(value) {
+        Ok(ival) => constant(a_const::Val::Ival(protobuf::Integer { ival })),
+        Err(_) => constant(a_const::Val::Fval(protobuf::Float {
+            fval: value.to_string(),
+        })),
+    }
+}
+
+/// A numeric constant. Infinities and NaN have no numeric literal, so they become casts such
+/// as `'NaN'::float8`.
+pub fn float(value: f64) -> NodeEnum {
+    if value.is_finite() {
+        return constant(a_const::Val::Fval(protobuf::Float {
+            fval: value.to_string(),
+        }));
+    }
+    let special = match value {
+        f64::INFINITY => "Infinity",
+        f64::NEG_INFINITY => "-Infinity",
+        _ => "NaN",
+    };
+    NodeEnum::TypeCast(Box::new(protobuf::TypeCast {
+        arg: boxed(text(special)),
+        type_name: Some(protobuf::TypeName {
+            names: names("float8"),
+            typemod: -1,
+            location: -1,
+            ..Default::default()
+        }),
+        location: -1,
+    }))
+}
+
+/// A string constant.
+pub fn text(value: &str) -> NodeEnum {
+    constant(a_const::Val::Sval(protobuf::String {
+        sval: value.to_string(),
+    }))
+}
+
+/// A boolean constant.
+pub fn boolean(value: bool) -> NodeEnum {
+    constant(a_const::Val::Boolval(protobuf::Boolean { boolval: value }))
+}
+
+/// The `NULL` constant.
+pub fn null() -> NodeEnum {
+    NodeEnum::AConst(protobuf::AConst {
+        isnull: true,
+        location: -1,
+        val: None,
+    })
+}
+
+/// A binary operator expression, e.g. `op("||", col("a"), text("b"))`.
+pub fn op(operator: &str, left: NodeEnum, right: NodeEnum) -> NodeEnum {
+    NodeEnum::AExpr(Box::new(protobuf::AExpr {
+        kind: protobuf::AExprKind::AexprOp as i32,
+        name: vec![string(operator)],
+        lexpr: boxed(left),
+        rexpr: boxed(right),
+        location: -1,
+    }))
+}
+
+/// `left = right`
+pub fn eq(left: NodeEnum, right: NodeEnum) -> NodeEnum {
+    op("=", left, right)
+}
+
+/// `left <> right`
+pub fn neq(left: NodeEnum, right: NodeEnum) -> NodeEnum {
+    op("<>", left, right)
+}
+
+/// `left < right`
+pub fn lt(left: NodeEnum, right: NodeEnum) -> NodeEnum {
+    op("<", left, right)
+}
+
+/// `left <= right`
+pub fn lte(left: NodeEnum, right: NodeEnum) -> NodeEnum {
+    op("<=", left, right)
+}
+
+/// `left > right`
+pub fn gt(left: NodeEnum, right: NodeEnum) -> NodeEnum {
+    op(">", left, right)
+}
+
+/// `left >= right`
+pub fn gte(left: NodeEnum, right: NodeEnum) -> NodeEnum {
+    op(">=", left, right)
+}
+
+/// `expr IN (values...)`
+pub fn in_list(expr: NodeEnum, values: impl IntoIterator<Item = NodeEnum>) -> NodeEnum {
+    NodeEnum::AExpr(Box::new(protobuf::AExpr {
+        kind: protobuf::AExprKind::AexprIn as i32,
+        name: vec![string("=")],
+        lexpr: boxed(expr),
+        rexpr: boxed(NodeEnum::List(protobuf::List {
+            items: values.into_iter().map(node).collect(),
+        })),
+        location: -1,
+    }))
+}
+
+fn bool_expr(op: protobuf::BoolExprType, args: impl IntoIterator<Item = NodeEnum>) -> NodeEnum {
+    let mut flattened = Vec::new();
+    for arg in args {
+        match arg {
+            // Nested expressions of the same operator are merged, like the parser does
+            NodeEnum::BoolExpr(e)
+                if e.boolop == op as i32 && op != protobuf::BoolExprType::NotExpr =>
+            {
+                flattened.extend(e.args)
+            }
+            arg => flattened.push(node(arg)),
+        }
+    }
+
+    if flattened.len() == 1 && op != protobuf::BoolExprType::NotExpr {
+        return flattened.remove(0).node.unwrap();
+    }
+
+    NodeEnum::BoolExpr(Box::new(protobuf::BoolExpr {
+        xpr: None,
+        boolop: op as i32,
+        args: flattened,
+        location: -1,
+    }))
+}
+
+/// Combines the conditions with `AND`. A single condition is returned as is.
+pub fn and(conditions: impl IntoIterator<Item = NodeEnum>) -> NodeEnum {
+    bool_expr(protobuf::BoolExprType::AndExpr, conditions)
+}
+
+/// Combines the conditions with `OR`. A single condition is returned as is.
+pub fn or(conditions: impl IntoIterator<Item = NodeEnum>) -> NodeEnum {
+    bool_expr(protobuf::BoolExprType::OrExpr, conditions)
+}
+
+/// `NOT condition`
+pub fn not(condition: NodeEnum) -> NodeEnum {
+    bool_expr(protobuf::BoolExprType::NotExpr, [condition])
+}
+
+fn null_test(expr: NodeEnum, test: protobuf::NullTestType) -> NodeEnum {
+    NodeEnum::NullTest(Box::new(protobuf::NullTest {
+        xpr: None,
+        arg: boxed(expr),
+        nulltesttype: test as i32,
+        argisrow: false,
+        location: -1,
+    }))
+}
+
+/// `expr IS NULL`
+pub fn is_null(expr: NodeEnum) -> NodeEnum {
+    null_test(expr, protobuf::NullTestType::IsNull)
+}
+
+/// `expr IS NOT NULL`
+pub fn is_not_null(expr: NodeEnum) -> NodeEnum {
+    null_test(expr, protobuf::NullTestType::IsNotNull)
+}
+
+/// A function call. Dots separate the schema from the function name, e.g.
+/// `func("pg_catalog.lower", [col("name")])`.
+pub fn func(name: &str, args: impl IntoIterator<Item = NodeEnum>) -> NodeEnum {
+    NodeEnum::FuncCall(Box::new(protobuf::FuncCall {
+        funcname: names(name),
+        args: args.into_iter().map(node).collect(),
+        funcformat: protobuf::CoercionForm::CoerceExplicitCall as i32,
+        location: -1,
+        ..Default::default()
+    }))
+}
+
+/// Names a node: select list entries get an output name (`expr AS name`), while tables,
+/// subqueries and joins get an alias.
+pub fn alias(node: impl Into<NodeEnum>, name: &str) -> NodeEnum {
+    let alias = Some(protobuf::Alias {
+        aliasname: name.to_string(),
+        colnames: Vec::new(),
+    });
+    match node.into() {
+        NodeEnum::RangeVar(mut r) => {
+            r.alias = alias;
+            NodeEnum::RangeVar(r)
+        }
+        NodeEnum::RangeSubselect(mut r) => {
+            r.alias = alias;
+            NodeEnum::RangeSubselect(r)
+        }
+        NodeEnum::JoinExpr(mut j) => {
+            j.alias = alias;
+            NodeEnum::JoinExpr(j)
+        }
+        NodeEnum::ResTarget(mut t) => {
+            t.name = name.to_string();
+            NodeEnum::ResTarget(t)
+        }
+        expr => NodeEnum::ResTarget(Box::new(protobuf::ResTarget {
+            name: name.to_string(),
+            indirection: Vec::new(),
+            val: boxed(expr),
+            location: -1,
+        })),
+    }
+}
+
+/// A subquery in the `FROM` clause. Postgres requires subqueries to have an alias.
+pub fn subquery(query: impl Into<NodeEnum>, alias_name: &str) -> NodeEnum {
+    let subquery = NodeEnum::RangeSubselect(Box::new(protobuf::RangeSubselect {
+        lateral: false,
+        subquery: boxed(query.into()),
+        alias: None,
+    }));
+    alias(subquery, alias_name)
+}
+
+/// A table reference, see [`table`].
+#[derive(Debug, Clone)]
+pub struct Table(protobuf::RangeVar);
+
+impl Table {
+    /// Gives the table an alias, as in `FROM users u`.
+    pub fn alias(mut self, name: &str) -> Self {
+        self.0.alias = Some(protobuf::Alias {
+            aliasname: name.to_string(),
+            colnames: Vec::new(),
+        });
+        self
+    }
+}
+
+impl From<Table> for NodeEnum {
+    fn from(table: Table) -> Self {
+        NodeEnum::RangeVar(table.0)
+    }
+}
+
+/// A table qualified with its schema, e.g. `public.users`.
+pub fn table(schema: &str, name: &str) -> Table {
+    Table(protobuf::RangeVar {
+        catalogname: String::new(),
+        schemaname: schema.to_string(),
+        relname: name.to_string(),
+        inh: true,
+        relpersistence: "p".to_string(),
+        alias: None,
+        location: -1,
+    })
+}
+
+/// A table that is looked up in the search path.
+pub fn unqualified_table(name: &str) -> Table {
+    table("", name)
+}
+
+/// The kinds of joins [`SelectBuilder::join`] can add.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    Full,
+}
+
+/// Starts a `SELECT` statement.
+pub fn select() -> SelectBuilder {
+    SelectBuilder {
+        stmt: protobuf::SelectStmt {
+            limit_option: protobuf::LimitOption::Default as i32,
+            op: protobuf::SetOperation::SetopNone as i32,
+            ..Default::default()
+        },
+    }
+}
+
+/// Builds a `SELECT` statement, see [`select`].
+#[derive(Debug, Clone)]
+pub struct SelectBuilder {
+    stmt: protobuf::SelectStmt,
+}
+
+impl SelectBuilder {
+    /// Adds entries to the select list. Use [`alias`] to name an entry.
+    pub fn columns(mut self, columns: impl IntoIterator<Item = NodeEnum>) -> Self {
+        for column in columns {
+            let target = match column {
+                NodeEnum::ResTarget(t) => NodeEnum::ResTarget(t),
+                expr => NodeEnum::ResTarget(Box::new(protobuf::ResTarget {
+                    name: String::new(),
+                    indirection: Vec::new(),
+                    val: boxed(expr),
+                    location: -1,
+                })),
+            };
+            self.stmt.target_list.push(node(target));
+        }
+        self
+    }
+
+    /// Makes this a `SELECT DISTINCT`.
+    pub fn distinct(mut self) -> Self {
+        // The parser represents a plain DISTINCT as a list with a single empty node
+        self.stmt.distinct_clause = vec![Node { node: None }];
+        self
+    }
+
+    /// Adds a table, subquery or join to the `FROM` clause.
+    pub fn from(mut self, item: impl Into<NodeEnum>) -> Self {
+        self.stmt.from_clause.push(node(item.into()));
+        self
+    }
+
+    /// Joins the last item of the `FROM` clause with `item`.
+    ///
+    /// Returns an error if the `FROM` clause is empty, as there is nothing to join `item` with.
+    /// Add the first item with [`from`](Self::from).
+    pub fn join(mut self, kind: JoinKind, item: impl Into<NodeEnum>, on: NodeEnum) -> Result<Self> {
+        let left =
+            self.stmt.from_clause.pop().ok_or_else(|| {
+                Error::Build("join requires an item in the FROM clause".to_string())
+            })?;
+        let jointype = match kind {
+            JoinKind::Inner => protobuf::JoinType::JoinInner,
+            JoinKind::Left => protobuf::JoinType::JoinLeft,
+            JoinKind::Right => protobuf::JoinType::JoinRight,
+            JoinKind::Full => protobuf::JoinType::JoinFull,
+        };
+        let join = NodeEnum::JoinExpr(Box::new(protobuf::JoinExpr {
+            jointype: jointype as i32,
+            larg: Some(Box::new(left)),
+            rarg: boxed(item.into()),
+            quals: boxed(on),
+            ..Default::default()
+        }));
+        Ok(self.from(join))
+    }
+
+    /// Adds a condition to the `WHERE` clause. Multiple conditions are combined with `AND`.
+    pub fn filter(mut self, condition: NodeEnum) -> Self {
+        self.stmt.where_clause = Some(Box::new(node(match self.stmt.where_clause.take() {
+            Some(existing) => and([existing.node.unwrap(), condition]),
+            None => condition,
+        })));
+        self
+    }
+
+    /// Adds expressions to the `GROUP BY` clause.
+    pub fn group_by(mut self, exprs: impl IntoIterator<Item = NodeEnum>) -> Self {
+        self.stmt.group_clause.extend(exprs.into_iter().map(node));
+        self
+    }
+
+    /// Adds a condition to the `HAVING` clause. Multiple conditions are combined with `AND`.
+    pub fn having(mut self, condition: NodeEnum) -> Self {
+        self.stmt.having_clause = Some(Box::new(node(match self.stmt.having_clause.take() {
+            Some(existing) => and([existing.node.unwrap(), condition]),
+            None => condition,
+        })));
+        self
+    }
+
+    fn sort(mut self, expr: NodeEnum, dir: protobuf::SortByDir) -> Self {
+        let sort = NodeEnum::SortBy(Box::new(protobuf::SortBy {
+            node: boxed(expr),
+            sortby_dir: dir as i32,
+            sortby_nulls: protobuf::SortByNulls::SortbyNullsDefault as i32,
+            use_op: Vec::new(),
+            location: -1,
+        }));
+        self.stmt.sort_clause.push(node(sort));
+        self
+    }
+
+    /// Adds an expression to the `ORDER BY` clause, sorting in ascending order.
+    pub fn order_by(self, expr: NodeEnum) -> Self {
+        self.sort(expr, protobuf::SortByDir::SortbyDefault)
+    }
+
+    /// Adds an expression to the `ORDER BY` clause, sorting in descending order.
+    pub fn order_by_desc(self, expr: NodeEnum) -> Self {
+        self.sort(expr, protobuf::SortByDir::SortbyDesc)
+    }
+
+    /// Sets the `LIMIT`.
+    pub fn limit(mut self, count: NodeEnum) -> Self {
+        self.stmt.limit_count = boxed(count);
+        self.stmt.limit_option = protobuf::LimitOption::Count as i32;
+        self
+    }
+
+    /// Sets the `OFFSET`.
+    pub fn offset(mut self, offset: NodeEnum) -> Self {
+        self.stmt.limit_offset = boxed(offset);
+        self.stmt.limit_option = protobuf::LimitOption::Count as i32;
+        self
+    }
+
+    pub fn build(self) -> NodeEnum {
+        self.into()
+    }
+}
+
+impl From<SelectBuilder> for NodeEnum {
+    fn from(builder: SelectBuilder) -> Self {
+        NodeEnum::SelectStmt(Box::new(builder.stmt))
+    }
+}
+
+fn constraint(contype: protobuf::ConstrType) -> protobuf::Constraint {
+    protobuf::Constraint {
+        contype: contype as i32,
+        location: -1,
+        ..Default::default()
+    }
+}
+
+/// A column definition for [`CreateTableBuilder::column`]. `type_name` may be qualified with
+/// a schema, e.g. `pg_catalog.int8`.
+pub fn column(name: &str, type_name: &str) -> ColumnBuilder {
+    ColumnBuilder {
+        def: protobuf::ColumnDef {
+            colname: name.to_string(),
+            type_name: Some(protobuf::TypeName {
+                names: names(type_name),
+                typemod: -1,
+                location: -1,
+                ..Default::default()
+            }),
+            is_local: true,
+            location: -1,
+            ..Default::default()
+        },
+    }
+}
+
+/// Builds a column definition, see [`column`].
+#[derive(Debug, Clone)]
+pub struct ColumnBuilder {
+    def: protobuf::ColumnDef,
+}
+
+impl ColumnBuilder {
+    fn constraint(mut self, constraint: protobuf::Constraint) -> Self {
+        self.def
+            .constraints
+            .push(node(NodeEnum::Constraint(Box::new(constraint))));
+        self
+    }
+
+    /// Sets type modifiers, as in `varchar(255)` or `numeric(10, 2)`.
+    pub fn type_mods(mut self, mods: impl IntoIterator<Item = NodeEnum>) -> Self {
+        if let Some(type_name) = self.def.type_name.as_mut() {
+            type_name.typmods = mods.into_iter().map(node).collect();
+        }
+        self
+    }
+
+    /// Makes this an array column, as in `text[]`.
+    pub fn array(mut self) -> Self {
+        if let Some(type_name) = self.def.type_name.as_mut() {
+            type_name
+                .array_bounds
+                .push(node(NodeEnum::Integer(protobuf::Integer { ival: -1 })));
+        }
+        self
+    }
+
+    pub fn not_null(self) -> Self {
+        self.constraint(constraint(protobuf::ConstrType::ConstrNotnull))
+    }
+
+    pub fn primary_key(self) -> Self {
+        self.constraint(constraint(protobuf::ConstrType::ConstrPrimary))
+    }
+
+    pub fn unique(self) -> Self {
+        self.constraint(constraint(protobuf::ConstrType::ConstrUnique))
+    }
+
+    pub fn default(self, value: NodeEnum) -> Self {
+        self.constraint(protobuf::Constraint {
+            raw_expr: boxed(value),
+            ..constraint(protobuf::ConstrType::ConstrDefault)
+        })
+    }
+
+    /// Adds a foreign key referencing `column` of `table`.
+    pub fn references(self, table: Table, column: &str) -> Self {
+        self.constraint(protobuf::Constraint {
+            pktable: Some(table.0),
+            pk_attrs: vec![string(column)],
+            fk_matchtype: "s".to_string(),
+            fk_upd_action: "a".to_string(),
+            fk_del_action: "a".to_string(),
+            initially_valid: true,
+            ..constraint(protobuf::ConstrType::ConstrForeign)
+        })
+    }
+}
+
+impl From<ColumnBuilder> for NodeEnum {
+    fn from(builder: ColumnBuilder) -> Self {
+        NodeEnum::ColumnDef(Box::new(builder.def))
+    }
+}
+
+/// Starts a `CREATE TABLE` statement.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::builder::*;
+///
+/// let stmt = create_table(table("public", "users"))
+///     .column(column("id", "int8").primary_key())
+///     .column(column("name", "text").not_null().default(text("")))
+///     .if_not_exists()
+///     .build();
+///
+/// assert_eq!(
+///     stmt.deparse().unwrap(),
+///     "CREATE TABLE IF NOT EXISTS public.users (id int8 PRIMARY KEY, name text NOT NULL DEFAULT '')"
+/// );
+/// ```
+pub fn create_table(table: Table) -> CreateTableBuilder {
+    CreateTableBuilder {
+        stmt: protobuf::CreateStmt {
+            relation: Some(table.0),
+            oncommit: protobuf::OnCommitAction::OncommitNoop as i32,
+            ..Default::default()
+        },
+    }
+}
+
+/// Builds a `CREATE TABLE` statement, see [`create_table`].
+#[derive(Debug, Clone)]
+pub struct CreateTableBuilder {
+    stmt: protobuf::CreateStmt,
+}
+
+impl CreateTableBuilder {
+    pub fn column(mut self, column: ColumnBuilder) -> Self {
+        self.stmt.table_elts.push(node(column.into()));
+        self
+    }
+
+    fn table_constraint(mut self, contype: protobuf::ConstrType, columns: &[&str]) -> Self {
+        let constraint = protobuf::Constraint {
+            keys: columns.iter().map(|c| string(c)).collect(),
+            ..constraint(contype)
+        };
+        self.stmt
+            .table_elts
+            .push(node(NodeEnum::Constraint(Box::new(constraint))));
+        self
+    }
+
+    /// Adds a `PRIMARY KEY` constraint spanning multiple columns.
+    pub fn primary_key(self, columns: &[&str]) -> Self {
+        self.table_constraint(protobuf::ConstrType::ConstrPrimary, columns)
+    }
+
+    /// Adds a `UNIQUE` constraint spanning multiple columns.
+    pub fn unique(self, columns: &[&str]) -> Self {
+        self.table_constraint(protobuf::ConstrType::ConstrUnique, columns)
+    }
+
+    pub fn if_not_exists(mut self) -> Self {
+        self.stmt.if_not_exists = true;
+        self
+    }
+
+    pub fn build(self) -> NodeEnum {
+        self.into()
+    }
+}
+
+impl From<CreateTableBuilder> for NodeEnum {
+    fn from(builder: CreateTableBuilder) -> Self {
+        NodeEnum::CreateStmt(builder.stmt)
+    }
+}
+
+/// Starts a `CREATE INDEX` statement. Pass an empty name to let Postgres choose one.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::builder::*;
+///
+/// let stmt = create_index("users_email_idx", table("public", "users"))
+///     .column("email")
+///     .unique()
+///     .concurrently()
+///     .build();
+///
+/// assert_eq!(
+///     stmt.deparse().unwrap(),
+///     "CREATE UNIQUE INDEX CONCURRENTLY users_email_idx ON public.users USING btree (email)"
+/// );
+/// ```
+pub fn create_index(name: &str, table: Table) -> CreateIndexBuilder {
+    CreateIndexBuilder {
+        stmt: protobuf::IndexStmt {
+            idxname: name.to_string(),
+            relation: Some(table.0),
+            access_method: "btree".to_string(),
+            ..Default::default()
+        },
+    }
+}
+
+/// Builds a `CREATE INDEX` statement, see [`create_index`].
+#[derive(Debug, Clone)]
+pub struct CreateIndexBuilder {
+    stmt: protobuf::IndexStmt,
+}
+
+impl CreateIndexBuilder {
+    fn param(mut self, name: &str, expr: Option<NodeEnum>, dir: protobuf::SortByDir) -> Self {
+        let elem = protobuf::IndexElem {
+            name: name.to_string(),
+            expr: expr.and_then(boxed),
+            ordering: dir as i32,
+            nulls_ordering: protobuf::SortByNulls::SortbyNullsDefault as i32,
+            ..Default::default()
+        };
+        self.stmt
+            .index_params
+            .push(node(NodeEnum::IndexElem(Box::new(elem))));
+        self
+    }
+
+    /// Adds a column to the index.
+    pub fn column(self, name: &str) -> Self {
+        self.param(name, None, protobuf::SortByDir::SortbyDefault)
+    }
+
+    /// Adds a column to the index, sorted in descending order.
+    pub fn column_desc(self, name: &str) -> Self {
+        self.param(name, None, protobuf::SortByDir::SortbyDesc)
+    }
+
+    /// Adds an expression to the index, e.g. `lower(email)`.
+    pub fn expression(self, expr: NodeEnum) -> Self {
+        self.param("", Some(expr), protobuf::SortByDir::SortbyDefault)
+    }
+
+    /// Sets the index access method. Defaults to `btree`.
+    pub fn using(mut self, method: &str) -> Self {
+        self.stmt.access_method = method.to_string();
+        self
+    }
+
+    /// Makes this a partial index.
+    pub fn filter(mut self, condition: NodeEnum) -> Self {
+        self.stmt.where_clause = boxed(condition);
+        self
+    }
+
+    pub fn unique(mut self) -> Self {
+        self.stmt.unique = true;
+        self
+    }
+
+    pub fn concurrently(mut self) -> Self {
+        self.stmt.concurrent = true;
+        self
+    }
+
+    pub fn if_not_exists(mut self) -> Self {
+        self.stmt.if_not_exists = true;
+        self
+    }
+
+    pub fn build(self) -> NodeEnum {
+        self.into()
+    }
+}
+
+impl From<CreateIndexBuilder> for NodeEnum {
+    fn from(builder: CreateIndexBuilder) -> Self {
+        NodeEnum::IndexStmt(Box::new(builder.stmt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, StructuralEq};
+
+    fn assert_builds(built: NodeEnum, query: &str) {
+        let parsed = parse(query).unwrap();
+        assert!(
+            built.structural_eq(parsed.stmts()[0]),
+            "{built:#?}\ndoes not match\n{:#?}",
+            parsed.stmts()[0]
+        );
+        assert_eq!(
+            built.deparse().unwrap(),
+            parsed.deparse().unwrap(),
+            "{query}"
+        );
+    }
+
+    #[test]
+    fn it_builds_selects() {
+        assert_builds(
+            select()
+                .columns([col("a"), alias(col("t.b"), "x"), star()])
+                .from(table("public", "users").alias("u"))
+                .filter(eq(col("id"), param(1)))
+                .filter(is_null(col("b")))
+                .order_by_desc(col("a"))
+                .limit(int(10))
+                .build(),
+            "SELECT a, t.b AS x, * FROM public.users u WHERE id = $1 AND b IS NULL ORDER BY a DESC LIMIT 10",
+        );
+    }
+
+    #[test]
+    fn it_builds_expressions() {
+        assert_builds(
+            select()
+                .columns([func("lower", [col("a")])])
+                .from(unqualified_table("a"))
+                .join(JoinKind::Inner, unqualified_table("b"), boolean(true))
+                .unwrap()
+                .filter(and([
+                    not(col("x")),
+                    in_list(col("y"), [int(1), int(2)]),
+                    eq(col("z"), float(1.5)),
+                    eq(col("w"), null()),
+                ]))
+                .build(),
+            "SELECT lower(a) FROM a JOIN b ON true WHERE NOT x AND y IN (1, 2) AND z = 1.5 AND w = NULL",
+        );
+        assert_builds(
+            select()
+                .columns([int(1 << 40), text("a")])
+                .filter(or([gt(col("a"), int(1)), lte(col("b"), int(2))]))
+                .build(),
+            "SELECT 1099511627776, 'a' WHERE a > 1 OR b <= 2",
+        );
+        assert_builds(
+            select()
+                .columns([float(f64::NAN), float(f64::INFINITY), float(-0.5)])
+                .build(),
+            "SELECT 'NaN'::float8, 'Infinity'::float8, -0.5",
+        );
+    }
+
+    #[test]
+    fn it_rejects_joins_without_from() {
+        let result = select().join(JoinKind::Inner, unqualified_table("t"), boolean(true));
+        assert!(matches!(result, Err(Error::Build(_))));
+    }
+
+    #[test]
+    fn it_builds_subqueries() {
+        assert_builds(
+            select()
+                .columns([col("s.a")])
+                .from(subquery(select().columns([col("a")]).from(unqualified_table("t")), "s"))
+                .group_by([col("s.a")])
+                .having(gt(func("count", [col("s.a")]), int(1)))
+                .offset(int(5))
+                .distinct()
+                .build(),
+            "SELECT DISTINCT s.a FROM (SELECT a FROM t) s GROUP BY s.a HAVING count(s.a) > 1 OFFSET 5",
+        );
+    }
+
+    #[test]
+    fn it_builds_create_table() {
+        assert_builds(
+            create_table(table("public", "users"))
+                .column(column("id", "int8").not_null().primary_key())
+                .column(column("name", "text").default(text("x")).unique())
+                .column(column("org", "int8").references(unqualified_table("orgs"), "id"))
+                .column(column("code", "pg_catalog.varchar").type_mods([int(10)]))
+                .column(column("tags", "text").array())
+                .primary_key(&["id", "name"])
+                .if_not_exists()
+                .build(),
+            "CREATE TABLE IF NOT EXISTS public.users (id int8 NOT NULL PRIMARY KEY, name text DEFAULT 'x' UNIQUE, org int8 REFERENCES orgs (id), code varchar(10), tags text[], PRIMARY KEY (id, name))",
+        );
+    }
+
+    #[test]
+    fn it_builds_create_index() {
+        assert_builds(
+            create_index("idx", table("public", "users"))
+                .column("a")
+                .column_desc("b")
+                .expression(func("lower", [col("c")]))
+                .filter(gt(col("a"), int(1)))
+                .unique()
+                .concurrently()
+                .if_not_exists()
+                .build(),
+            "CREATE UNIQUE INDEX CONCURRENTLY IF NOT EXISTS idx ON public.users USING btree (a, b DESC, lower(c)) WHERE a > 1",
+        );
+        assert_builds(
+            create_index("", unqualified_table("t"))
+                .using("gin")
+                .column("a")
+                .build(),
+            "CREATE INDEX ON t USING gin (a)",
+        );
+    }
+}
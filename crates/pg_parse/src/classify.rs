@@ -0,0 +1,566 @@
+use crate::protobuf::{self, DefElem, FuncCall, TransactionStmtKind};
+use crate::{NamePart, NodeEnum, NodeRef, ParseResult, QualifiedName};
+
+/// What a statement does, as far as can be told from its syntax.
+///
+/// The classes follow the `log_statement` levels of Postgres: `Ddl` and `Dml` are what it logs
+/// as `ddl` and `mod`, with `TRUNCATE` and `COPY FROM` counting as DML. `EXPLAIN` is classified
+/// as read-only, unless it runs the statement with `ANALYZE`, in which case it gets the class
+/// of the explained statement. `COPY (query) TO` gets the class of its query.
+///
+/// See [`ParseResult::classify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatementClass {
+    /// A query that does not modify data, e.g. `SELECT`, `VALUES` or `COPY t TO STDOUT`.
+    ///
+    /// `calls_functions` is set if the query calls functions that may modify data or state,
+    /// e.g. `SELECT create_order(1)` or `SELECT nextval('s')`. Only the built-in functions
+    /// that compute values, such as `lower` or `count`, are known to be safe, and functions in
+    /// `FROM` always set the flag.
+    ReadOnly { calls_functions: bool },
+    /// A `SELECT` with a locking clause such as `FOR UPDATE`, which takes row locks.
+    LockingSelect,
+    /// A query whose `WITH` clause contains `INSERT`, `UPDATE`, `DELETE` or `MERGE`.
+    DataModifyingCte,
+    /// `INSERT`, `UPDATE`, `DELETE`, `MERGE`, `TRUNCATE` or `COPY FROM`.
+    Dml,
+    /// A statement that creates, alters or drops objects or privileges, including
+    /// `SELECT INTO` and `CREATE TABLE AS`.
+    Ddl,
+    /// `BEGIN`, `COMMIT`, `ROLLBACK` and savepoints.
+    TransactionControl,
+    /// `PREPARE TRANSACTION`, `COMMIT PREPARED` and `ROLLBACK PREPARED`, which write the state
+    /// of prepared transactions.
+    TwoPhaseCommit,
+    /// `SET`, `RESET`, `SHOW`, `SET CONSTRAINTS` and `DISCARD`.
+    Session,
+    /// Any other statement, e.g. `VACUUM`, `LOCK`, `CALL`, `DO`, `EXECUTE` or `NOTIFY`.
+    Utility,
+}
+
+impl StatementClass {
+    /// Classifies a single statement, see [`ParseResult::classify`].
+    pub fn of(stmt: &NodeEnum) -> StatementClass {
+        classify_node(stmt.to_ref())
+    }
+
+    /// Returns whether the statement can be run on a read-only hot standby replica.
+    ///
+    /// This is the case for read-only queries that do not call functions other than the
+    /// built-in ones that compute values, transaction control and session statements. Two-phase
+    /// commit fails on a standby. Utility statements are assumed to write, since they include
+    /// `CALL`, `DO` and `EXECUTE`, whose effect is not known.
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            StatementClass::ReadOnly {
+                calls_functions: false
+            } | StatementClass::TransactionControl
+                | StatementClass::Session
+        )
+    }
+}
+
+impl ParseResult {
+    /// Classifies each statement, e.g. to decide whether it can be sent to a replica.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use pg_parse::{parse, StatementClass};
+    ///
+    /// let result = parse(
+    ///     "BEGIN; SELECT * FROM t FOR UPDATE; EXPLAIN ANALYZE DELETE FROM t; SELECT * FROM f()",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(
+    ///     result.classify(),
+    ///     [
+    ///         StatementClass::TransactionControl,
+    ///         StatementClass::LockingSelect,
+    ///         StatementClass::Dml,
+    ///         StatementClass::ReadOnly { calls_functions: true },
+    ///     ]
+    /// );
+    /// assert!(!result.classify().iter().all(|c| c.is_read_only()));
+    /// ```
+    pub fn classify(&self) -> Vec<StatementClass> {
+        self.stmts().into_iter().map(StatementClass::of).collect()
+    }
+}
+
+fn classify_node(node: NodeRef<'_>) -> StatementClass {
+    match node {
+        NodeRef::SelectStmt(s) => {
+            if s.into_clause.is_some() {
+                StatementClass::Ddl
+            } else {
+                classify_query(node)
+            }
+        }
+        NodeRef::InsertStmt(_)
+        | NodeRef::UpdateStmt(_)
+        | NodeRef::DeleteStmt(_)
+        | NodeRef::MergeStmt(_)
+        | NodeRef::TruncateStmt(_) => StatementClass::Dml,
+        NodeRef::CopyStmt(s) => {
+            if s.is_from {
+                StatementClass::Dml
+            } else if s.is_program || !s.filename.is_empty() {
+                // Writes a file on the server
+                StatementClass::Utility
+            } else if let Some(query) = s.query.as_ref().and_then(|q| q.node.as_ref()) {
+                classify_node(query.to_ref())
+            } else {
+                StatementClass::ReadOnly {
+                    calls_functions: false,
+                }
+            }
+        }
+        NodeRef::ExplainStmt(s) => match s.query.as_ref().and_then(|q| q.node.as_ref()) {
+            Some(query) if explain_analyze(&s.options) => classify_node(query.to_ref()),
+            _ => StatementClass::ReadOnly {
+                calls_functions: false,
+            },
+        },
+        NodeRef::TransactionStmt(s) => match s.kind() {
+            TransactionStmtKind::TransStmtPrepare
+            | TransactionStmtKind::TransStmtCommitPrepared
+            | TransactionStmtKind::TransStmtRollbackPrepared => StatementClass::TwoPhaseCommit,
+            _ => StatementClass::TransactionControl,
+        },
+        NodeRef::VariableSetStmt(_)
+        | NodeRef::VariableShowStmt(_)
+        | NodeRef::ConstraintsSetStmt(_)
+        | NodeRef::DiscardStmt(_) => StatementClass::Session,
+        NodeRef::CreateTableAsStmt(_)
+        | NodeRef::CreateSchemaStmt(_)
+        | NodeRef::CreateStmt(_)
+        | NodeRef::CreateForeignTableStmt(_)
+        | NodeRef::CreateTableSpaceStmt(_)
+        | NodeRef::DropTableSpaceStmt(_)
+        | NodeRef::AlterTableSpaceOptionsStmt(_)
+        | NodeRef::CreateExtensionStmt(_)
+        | NodeRef::AlterExtensionStmt(_)
+        | NodeRef::AlterExtensionContentsStmt(_)
+        | NodeRef::CreateFdwStmt(_)
+        | NodeRef::AlterFdwStmt(_)
+        | NodeRef::CreateForeignServerStmt(_)
+        | NodeRef::AlterForeignServerStmt(_)
+        | NodeRef::CreateUserMappingStmt(_)
+        | NodeRef::AlterUserMappingStmt(_)
+        | NodeRef::DropUserMappingStmt(_)
+        | NodeRef::ImportForeignSchemaStmt(_)
+        | NodeRef::DropStmt(_)
+        | NodeRef::CommentStmt(_)
+        | NodeRef::SecLabelStmt(_)
+        | NodeRef::RenameStmt(_)
+        | NodeRef::AlterObjectDependsStmt(_)
+        | NodeRef::AlterObjectSchemaStmt(_)
+        | NodeRef::AlterOwnerStmt(_)
+        | NodeRef::AlterOperatorStmt(_)
+        | NodeRef::AlterTypeStmt(_)
+        | NodeRef::AlterTableMoveAllStmt(_)
+        | NodeRef::AlterTableStmt(_)
+        | NodeRef::AlterDomainStmt(_)
+        | NodeRef::GrantStmt(_)
+        | NodeRef::GrantRoleStmt(_)
+        | NodeRef::AlterDefaultPrivilegesStmt(_)
+        | NodeRef::DefineStmt(_)
+        | NodeRef::CompositeTypeStmt(_)
+        | NodeRef::CreateEnumStmt(_)
+        | NodeRef::CreateRangeStmt(_)
+        | NodeRef::AlterEnumStmt(_)
+        | NodeRef::ViewStmt(_)
+        | NodeRef::CreateFunctionStmt(_)
+        | NodeRef::AlterFunctionStmt(_)
+        | NodeRef::IndexStmt(_)
+        | NodeRef::RuleStmt(_)
+        | NodeRef::CreateSeqStmt(_)
+        | NodeRef::AlterSeqStmt(_)
+        | NodeRef::CreatedbStmt(_)
+        | NodeRef::AlterDatabaseStmt(_)
+        | NodeRef::AlterDatabaseRefreshCollStmt(_)
+        | NodeRef::AlterDatabaseSetStmt(_)
+        | NodeRef::DropdbStmt(_)
+        | NodeRef::AlterSystemStmt(_)
+        | NodeRef::CreateTrigStmt(_)
+        | NodeRef::CreateEventTrigStmt(_)
+        | NodeRef::AlterEventTrigStmt(_)
+        | NodeRef::CreatePlangStmt(_)
+        | NodeRef::CreateDomainStmt(_)
+        | NodeRef::CreateRoleStmt(_)
+        | NodeRef::AlterRoleStmt(_)
+        | NodeRef::AlterRoleSetStmt(_)
+        | NodeRef::DropRoleStmt(_)
+        | NodeRef::DropOwnedStmt(_)
+        | NodeRef::ReassignOwnedStmt(_)
+        | NodeRef::CreateConversionStmt(_)
+        | NodeRef::CreateCastStmt(_)
+        | NodeRef::CreateOpClassStmt(_)
+        | NodeRef::CreateOpFamilyStmt(_)
+        | NodeRef::CreateTransformStmt(_)
+        | NodeRef::AlterOpFamilyStmt(_)
+        | NodeRef::AlterTsdictionaryStmt(_)
+        | NodeRef::AlterTsconfigurationStmt(_)
+        | NodeRef::CreateAmStmt(_)
+        | NodeRef::CreatePublicationStmt(_)
+        | NodeRef::AlterPublicationStmt(_)
+        | NodeRef::CreateSubscriptionStmt(_)
+        | NodeRef::AlterSubscriptionStmt(_)
+        | NodeRef::DropSubscriptionStmt(_)
+        | NodeRef::CreateStatsStmt(_)
+        | NodeRef::AlterStatsStmt(_)
+        | NodeRef::AlterCollationStmt(_)
+        | NodeRef::CreatePolicyStmt(_)
+        | NodeRef::AlterPolicyStmt(_) => StatementClass::Ddl,
+        _ => StatementClass::Utility,
+    }
+}
+
+/// Classifies a query by the CTEs, locking clauses and functions it contains at any depth
+fn classify_query(query: NodeRef<'_>) -> StatementClass {
+    let nodes = query.nodes();
+    let modifies = nodes.iter().any(|n| match n {
+        NodeRef::CommonTableExpr(cte) => cte.ctequery.as_ref().is_some_and(|q| {
+            matches!(
+                q.node,
+                Some(
+                    NodeEnum::InsertStmt(_)
+                        | NodeEnum::UpdateStmt(_)
+                        | NodeEnum::DeleteStmt(_)
+                        | NodeEnum::MergeStmt(_)
+                )
+            )
+        }),
+        _ => false,
+    });
+    if modifies {
+        return StatementClass::DataModifyingCte;
+    }
+    if nodes.iter().any(|n| matches!(n, NodeRef::LockingClause(_))) {
+        return StatementClass::LockingSelect;
+    }
+    StatementClass::ReadOnly {
+        calls_functions: nodes.iter().any(|n| match n {
+            NodeRef::RangeFunction(_) => true,
+            NodeRef::FuncCall(call) => !computes_value(call),
+            _ => false,
+        }),
+    }
+}
+
+/// Built-in functions that only compute a value from their arguments and the data they read,
+/// sorted for binary search. Functions that write or change state, such as `nextval`,
+/// `set_config` or `pg_advisory_lock`, must not be listed.
+const VALUE_FUNCTIONS: &[&str] = &[
+    "abs",
+    "age",
+    "array_agg",
+    "array_length",
+    "array_position",
+    "array_to_string",
+    "avg",
+    "bool_and",
+    "bool_or",
+    "btrim",
+    "cardinality",
+    "ceil",
+    "ceiling",
+    "char_length",
+    "concat",
+    "concat_ws",
+    "count",
+    "cume_dist",
+    "date_part",
+    "date_trunc",
+    "dense_rank",
+    "every",
+    "extract",
+    "first_value",
+    "floor",
+    "format",
+    "initcap",
+    "json_agg",
+    "json_build_array",
+    "json_build_object",
+    "json_object_agg",
+    "jsonb_agg",
+    "jsonb_build_array",
+    "jsonb_build_object",
+    "jsonb_object_agg",
+    "jsonb_set",
+    "lag",
+    "last_value",
+    "lead",
+    "left",
+    "length",
+    "lower",
+    "lpad",
+    "ltrim",
+    "make_date",
+    "make_interval",
+    "max",
+    "md5",
+    "min",
+    "mod",
+    "now",
+    "nth_value",
+    "ntile",
+    "percent_rank",
+    "percentile_cont",
+    "percentile_disc",
+    "position",
+    "power",
+    "rank",
+    "regexp_replace",
+    "replace",
+    "right",
+    "round",
+    "row_number",
+    "rpad",
+    "rtrim",
+    "sqrt",
+    "string_agg",
+    "string_to_array",
+    "strpos",
+    "substr",
+    "substring",
+    "sum",
+    "timezone",
+    "to_char",
+    "to_date",
+    "to_json",
+    "to_jsonb",
+    "to_number",
+    "to_timestamp",
+    "trim",
+    "trunc",
+    "upper",
+];
+
+/// Returns whether `call` is a built-in function that only computes a value
+fn computes_value(call: &FuncCall) -> bool {
+    let name = QualifiedName::from_nodes(&call.funcname).unwrap_or_default();
+    let name = match name.0.as_slice() {
+        [NamePart::Ident(name)] => name,
+        [NamePart::Ident(schema), NamePart::Ident(name)] if schema == "pg_catalog" => name,
+        _ => return false,
+    };
+    VALUE_FUNCTIONS.binary_search(&name.as_str()).is_ok()
+}
+
+/// Returns whether `EXPLAIN` options include `ANALYZE`, read like Postgres's `defGetBoolean`
+fn explain_analyze(options: &[protobuf::Node]) -> bool {
+    options
+        .iter()
+        .filter_map(|o| match &o.node {
+            Some(NodeEnum::DefElem(d)) if d.defname == "analyze" => Some(d),
+            _ => None,
+        })
+        .next_back()
+        .is_some_and(|d| def_elem_is_true(d))
+}
+
+/// Reads a boolean option, e.g. `ANALYZE` in `EXPLAIN (ANALYZE off)`
+pub(crate) fn def_elem_is_true(def: &DefElem) -> bool {
+    match def.arg.as_ref().and_then(|a| a.node.as_ref()) {
+        None => true,
+        Some(NodeEnum::Integer(i)) => i.ival != 0,
+        Some(NodeEnum::Boolean(b)) => b.boolval,
+        Some(NodeEnum::String(s)) => !matches!(
+            s.sval.to_ascii_lowercase().as_str(),
+            "f" | "false" | "n" | "no" | "off" | "0"
+        ),
+        Some(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn classify(query: &str) -> StatementClass {
+        let result = parse(query).unwrap();
+        assert_eq!(result.stmts().len(), 1, "{query}");
+        result.classify()[0]
+    }
+
+    const READ_ONLY: StatementClass = StatementClass::ReadOnly {
+        calls_functions: false,
+    };
+
+    #[test]
+    fn it_classifies_queries() {
+        assert_eq!(
+            classify("SELECT * FROM t WHERE a IN (SELECT b FROM u)"),
+            READ_ONLY
+        );
+        assert_eq!(classify("VALUES (1), (2)"), READ_ONLY);
+        assert_eq!(classify("WITH x AS (SELECT 1) SELECT * FROM x"), READ_ONLY);
+        assert_eq!(classify("SELECT lower(a) FROM t"), READ_ONLY);
+        assert_eq!(
+            classify("SELECT count(*), pg_catalog.max(a) FROM t GROUP BY b"),
+            READ_ONLY
+        );
+        assert_eq!(
+            classify("SELECT * FROM t, LATERAL generate_series(1, t.n)"),
+            StatementClass::ReadOnly {
+                calls_functions: true
+            }
+        );
+        assert_eq!(
+            classify("SELECT * FROM t FOR UPDATE SKIP LOCKED"),
+            StatementClass::LockingSelect
+        );
+        assert_eq!(
+            classify("SELECT * FROM (SELECT * FROM t FOR SHARE) s"),
+            StatementClass::LockingSelect
+        );
+        assert_eq!(
+            classify("WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d"),
+            StatementClass::DataModifyingCte
+        );
+        assert_eq!(
+            classify("SELECT * FROM (WITH i AS (INSERT INTO t VALUES (1) RETURNING *) SELECT * FROM i) s"),
+            StatementClass::DataModifyingCte
+        );
+        assert_eq!(classify("SELECT * INTO new_t FROM t"), StatementClass::Ddl);
+    }
+
+    #[test]
+    fn it_flags_function_calls() {
+        for query in [
+            "SELECT nextval('s')",
+            "SELECT pg_advisory_lock(1)",
+            "SELECT set_config('a', 'b', false)",
+            "SELECT create_order(1)",
+            "SELECT * FROM t WHERE id = app.next_id()",
+            "SELECT * FROM t WHERE a IN (SELECT audit(b) FROM u)",
+            "SELECT lower(a) FROM t ORDER BY public.lower(a)",
+        ] {
+            assert_eq!(
+                classify(query),
+                StatementClass::ReadOnly {
+                    calls_functions: true
+                },
+                "{query}"
+            );
+            assert!(!classify(query).is_read_only(), "{query}");
+        }
+    }
+
+    #[test]
+    fn it_classifies_writes() {
+        assert_eq!(
+            classify("INSERT INTO t SELECT * FROM u"),
+            StatementClass::Dml
+        );
+        assert_eq!(classify("UPDATE t SET a = 1"), StatementClass::Dml);
+        assert_eq!(
+            classify("WITH x AS (SELECT 1) DELETE FROM t USING x"),
+            StatementClass::Dml
+        );
+        assert_eq!(
+            classify("MERGE INTO t USING u ON t.id = u.id WHEN MATCHED THEN DELETE"),
+            StatementClass::Dml
+        );
+        assert_eq!(classify("TRUNCATE t"), StatementClass::Dml);
+        assert_eq!(classify("COPY t FROM STDIN"), StatementClass::Dml);
+        assert_eq!(classify("COPY t TO STDOUT"), READ_ONLY);
+        assert_eq!(
+            classify("COPY (DELETE FROM t RETURNING *) TO STDOUT"),
+            StatementClass::Dml
+        );
+        assert_eq!(classify("COPY t TO '/tmp/t.csv'"), StatementClass::Utility);
+
+        assert_eq!(classify("CREATE TABLE t (a int)"), StatementClass::Ddl);
+        assert_eq!(
+            classify("ALTER TABLE t ADD COLUMN b int"),
+            StatementClass::Ddl
+        );
+        assert_eq!(classify("CREATE INDEX ON t (a)"), StatementClass::Ddl);
+        assert_eq!(classify("DROP VIEW v"), StatementClass::Ddl);
+        assert_eq!(classify("GRANT SELECT ON t TO r"), StatementClass::Ddl);
+        assert_eq!(classify("CREATE TABLE n AS SELECT 1"), StatementClass::Ddl);
+    }
+
+    #[test]
+    fn it_classifies_explain() {
+        assert_eq!(classify("EXPLAIN DELETE FROM t"), READ_ONLY);
+        assert_eq!(
+            classify("EXPLAIN ANALYZE DELETE FROM t"),
+            StatementClass::Dml
+        );
+        assert_eq!(
+            classify("EXPLAIN (ANALYZE, BUFFERS) UPDATE t SET a = 1"),
+            StatementClass::Dml
+        );
+        assert_eq!(
+            classify("EXPLAIN (ANALYZE off) UPDATE t SET a = 1"),
+            READ_ONLY
+        );
+        assert_eq!(
+            classify("EXPLAIN (ANALYZE 1) SELECT * FROM t FOR UPDATE"),
+            StatementClass::LockingSelect
+        );
+        assert_eq!(
+            classify("EXPLAIN ANALYZE SELECT * FROM f()"),
+            StatementClass::ReadOnly {
+                calls_functions: true
+            }
+        );
+    }
+
+    #[test]
+    fn it_classifies_other_statements() {
+        for query in [
+            "BEGIN",
+            "START TRANSACTION READ ONLY",
+            "COMMIT",
+            "ROLLBACK TO SAVEPOINT s",
+        ] {
+            assert_eq!(
+                classify(query),
+                StatementClass::TransactionControl,
+                "{query}"
+            );
+        }
+        for query in [
+            "PREPARE TRANSACTION 'x'",
+            "COMMIT PREPARED 'x'",
+            "ROLLBACK PREPARED 'x'",
+        ] {
+            assert_eq!(classify(query), StatementClass::TwoPhaseCommit, "{query}");
+            assert!(!classify(query).is_read_only(), "{query}");
+        }
+        for query in [
+            "SET search_path = app",
+            "RESET ALL",
+            "SHOW work_mem",
+            "DISCARD ALL",
+            "SET CONSTRAINTS ALL DEFERRED",
+        ] {
+            assert_eq!(classify(query), StatementClass::Session, "{query}");
+        }
+        for query in [
+            "VACUUM t",
+            "LOCK t",
+            "CALL p()",
+            "DO $$ BEGIN END $$",
+            "NOTIFY c",
+        ] {
+            assert_eq!(classify(query), StatementClass::Utility, "{query}");
+        }
+
+        assert!(READ_ONLY.is_read_only());
+        assert!(StatementClass::Session.is_read_only());
+        assert!(!StatementClass::ReadOnly {
+            calls_functions: true
+        }
+        .is_read_only());
+        assert!(!StatementClass::LockingSelect.is_read_only());
+        assert!(!StatementClass::Utility.is_read_only());
+    }
+}
@@ -0,0 +1,83 @@
+//! Conversions between the node structs, [`NodeEnum`] and [`Node`](protobuf::Node).
+//!
+//! Every struct that can appear in a [`NodeEnum`] converts into it with `From`, taking care of
+//! boxing the variants that need it, and can be taken back out with `TryFrom`, which returns the
+//! node unchanged if it is of another type. `as_*` and `is_*` accessors exist on [`NodeEnum`],
+//! [`NodeRef`] and [`Node`](protobuf::Node).
+//!
+//! # Example
+//!
+//! ```rust
+//! use pg_parse::ast::{RangeVar, SelectStmt};
+//! use pg_parse::NodeEnum;
+//!
+//! let result = pg_parse::parse("SELECT * FROM users").unwrap();
+//! let stmt = result.stmts()[0];
+//! assert!(stmt.is_select_stmt());
+//!
+//! let table = stmt.as_select_stmt().unwrap().from_clause[0].as_range_var().unwrap();
+//! assert_eq!(table.relname, "users");
+//!
+//! let node = NodeEnum::from(RangeVar {
+//!     relname: "accounts".to_string(),
+//!     ..table.clone()
+//! });
+//! assert!(SelectStmt::try_from(node).is_err());
+//! ```
+use crate::protobuf;
+
+pg_parse_macros::convert_codegen!();
+
+impl From<NodeEnum> for protobuf::Node {
+    fn from(node: NodeEnum) -> Self {
+        protobuf::Node { node: Some(node) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{AConst, ColumnRef, Node, SelectStmt};
+    use crate::{parse, NodeEnum};
+
+    #[test]
+    fn it_round_trips_boxed_and_unboxed_variants() {
+        let result = parse("SELECT a FROM t WHERE b = 1").unwrap();
+
+        // `SelectStmt` is boxed inside `NodeEnum`, `ColumnRef` is not
+        let select = result.stmts()[0].as_select_stmt().unwrap().clone();
+        let node = NodeEnum::from(select.clone());
+        assert!(matches!(node, NodeEnum::SelectStmt(_)));
+        assert_eq!(SelectStmt::try_from(node).unwrap(), select);
+
+        let target = select.target_list[0].as_res_target().unwrap();
+        let column = target
+            .val
+            .as_ref()
+            .unwrap()
+            .as_column_ref()
+            .unwrap()
+            .clone();
+        let node = Node::from(column.clone());
+        assert!(node.is_column_ref());
+        assert_eq!(ColumnRef::try_from(node.node.unwrap()).unwrap(), column);
+    }
+
+    #[test]
+    fn it_returns_the_node_when_the_type_does_not_match() {
+        let result = parse("SELECT 1").unwrap();
+        let node = result.stmts()[0].clone();
+        assert_eq!(AConst::try_from(node.clone()), Err(node));
+    }
+
+    #[test]
+    fn it_provides_accessors_on_node_refs() {
+        let result = parse("SELECT a FROM t").unwrap();
+        let nodes = result.stmts()[0].nodes();
+        let tables: Vec<_> = nodes.iter().filter_map(|n| n.as_range_var()).collect();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].relname, "t");
+        assert!(nodes.iter().any(|n| n.is_column_ref()));
+        assert!(!nodes.iter().any(|n| n.is_insert_stmt()));
+        assert_eq!(Node { node: None }.as_select_stmt(), None);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::iter_ref::push_children;
+use crate::{NodeRef, ParseResult};
+
+/// A cursor for navigating the nodes of a [`ParseResult`], similar to tree-sitter's
+/// `TreeCursor`.
+///
+/// The top level of the tree are the statements of the parse result, so moving to the next
+/// sibling of a statement moves to the next statement. Children are ordered by their field
+/// declaration order, just like the iterators.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::{parse, NodeRef};
+///
+/// let result = parse("SELECT a FROM users WHERE id = 1").unwrap();
+/// let mut cursor = result.cursor();
+///
+/// let node = cursor.node_at_offset(15).unwrap();
+/// assert!(matches!(node, NodeRef::RangeVar(r) if r.relname == "users"));
+///
+/// assert!(cursor.goto_parent());
+/// assert!(matches!(cursor.node(), Some(NodeRef::SelectStmt(_))));
+/// ```
+pub struct TreeCursor<'a> {
+    result: &'a ParseResult,
+    // The path from a statement to the current node. Each level holds the current node and its
+    // siblings.
+    levels: Vec<Level<'a>>,
+}
+
+struct Level<'a> {
+    siblings: Vec<NodeRef<'a>>,
+    index: usize,
+}
+
+impl<'a> TreeCursor<'a> {
+    /// Creates a cursor positioned at the first statement of `result`.
+    pub fn new(result: &'a ParseResult) -> Self {
+        let mut cursor = Self {
+            result,
+            levels: Vec::new(),
+        };
+        cursor.reset();
+        cursor
+    }
+
+    /// Moves the cursor back to the first statement.
+    pub fn reset(&mut self) {
+        let statements: Vec<_> = self
+            .result
+            .stmts()
+            .into_iter()
+            .map(|s| s.to_ref())
+            .collect();
+        self.levels.clear();
+        if !statements.is_empty() {
+            self.levels.push(Level {
+                siblings: statements,
+                index: 0,
+            });
+        }
+    }
+
+    /// Returns the node the cursor points at, or None if the parse result has no statements.
+    pub fn node(&self) -> Option<NodeRef<'a>> {
+        self.levels.last().map(|l| l.siblings[l.index])
+    }
+
+    /// Returns the number of ancestors of the current node. Statements have a depth of zero.
+    pub fn depth(&self) -> usize {
+        self.levels.len().saturating_sub(1)
+    }
+
+    /// Moves the cursor to the parent of the current node.
+    ///
+    /// Returns false if the cursor points at a statement.
+    pub fn goto_parent(&mut self) -> bool {
+        if self.levels.len() <= 1 {
+            return false;
+        }
+        self.levels.pop();
+        true
+    }
+
+    /// Moves the cursor to the statement containing the current node.
+    pub fn goto_statement(&mut self) {
+        self.levels.truncate(1);
+    }
+
+    /// Moves the cursor to the first child of the current node.
+    ///
+    /// Returns false if the current node has no children.
+    pub fn goto_first_child(&mut self) -> bool {
+        let Some(node) = self.node() else {
+            return false;
+        };
+
+        let mut children = Vec::new();
+        push_children(&node, &mut children);
+        if children.is_empty() {
+            return false;
+        }
+
+        self.levels.push(Level {
+            siblings: children,
+            index: 0,
+        });
+        true
+    }
+
+    /// Moves the cursor to the next sibling of the current node.
+    ///
+    /// Returns false if the current node is the last child of its parent.
+    pub fn goto_next_sibling(&mut self) -> bool {
+        match self.levels.last_mut() {
+            Some(level) if level.index + 1 < level.siblings.len() => {
+                level.index += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the cursor to the previous sibling of the current node.
+    ///
+    /// Returns false if the current node is the first child of its parent.
+    pub fn goto_previous_sibling(&mut self) -> bool {
+        match self.levels.last_mut() {
+            Some(level) if level.index > 0 => {
+                level.index -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the cursor to the innermost node at the given byte offset of the source text and
+    /// returns it.
+    ///
+    /// Nodes only record where they start, so this is the deepest node with a location that
+    /// starts at or before `offset` and comes last in source order within the statement
+    /// containing `offset`. Returns None and leaves the cursor where it was if no statement
+    /// contains the offset.
+    pub fn node_at_offset(&mut self, offset: usize) -> Option<NodeRef<'a>> {
+        let offset = i32::try_from(offset).ok()?;
+
+        // The index into `stmts()`, which skips raw statements without a node
+        let statement = self
+            .result
+            .protobuf
+            .stmts
+            .iter()
+            .filter(|s| s.stmt.as_ref().is_some_and(|s| s.node.is_some()))
+            .position(|s| {
+                s.stmt_location <= offset
+                    && (s.stmt_len == 0 || offset < s.stmt_location + s.stmt_len)
+            })?;
+
+        self.reset();
+        self.levels[0].index = statement;
+
+        // Search the statement for the best match, remembering the child indices leading to it
+        let mut best: (i32, Vec<usize>) = (-1, Vec::new());
+        let mut stack = vec![(self.levels[0].siblings[statement], Vec::new())];
+        while let Some((node, path)) = stack.pop() {
+            if let Some(location) = node.location() {
+                if location <= offset && location >= best.0 {
+                    best = (location, path.clone());
+                }
+            }
+
+            let mut children = Vec::new();
+            push_children(&node, &mut children);
+            for (i, child) in children.into_iter().enumerate().rev() {
+                let mut child_path = path.clone();
+                child_path.push(i);
+                stack.push((child, child_path));
+            }
+        }
+
+        for index in best.1 {
+            self.goto_first_child();
+            if let Some(level) = self.levels.last_mut() {
+                level.index = index;
+            }
+        }
+
+        self.node()
+    }
+}
+
+impl ParseResult {
+    /// Creates a [`TreeCursor`] positioned at the first statement.
+    pub fn cursor(&self) -> TreeCursor<'_> {
+        TreeCursor::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, NodeRef};
+
+    #[test]
+    fn it_navigates_the_tree() {
+        let result = parse("SELECT a, b FROM t").unwrap();
+        let mut cursor = result.cursor();
+
+        assert!(matches!(cursor.node(), Some(NodeRef::SelectStmt(_))));
+        assert!(!cursor.goto_parent());
+        assert!(!cursor.goto_next_sibling());
+
+        assert!(cursor.goto_first_child());
+        assert_eq!(cursor.depth(), 1);
+        assert!(matches!(cursor.node(), Some(NodeRef::ResTarget(t)) if t.location == 7));
+        assert!(!cursor.goto_previous_sibling());
+        assert!(cursor.goto_next_sibling());
+        assert!(matches!(cursor.node(), Some(NodeRef::ResTarget(t)) if t.location == 10));
+        assert!(cursor.goto_next_sibling());
+        assert!(matches!(cursor.node(), Some(NodeRef::RangeVar(_))));
+        assert!(!cursor.goto_next_sibling());
+        assert!(!cursor.goto_first_child());
+
+        assert!(cursor.goto_previous_sibling());
+        assert!(cursor.goto_first_child());
+        assert!(cursor.goto_first_child());
+        assert!(matches!(cursor.node(), Some(NodeRef::String(s)) if s.sval == "b"));
+        assert_eq!(cursor.depth(), 3);
+
+        cursor.goto_statement();
+        assert!(matches!(cursor.node(), Some(NodeRef::SelectStmt(_))));
+    }
+
+    #[test]
+    fn it_moves_between_statements() {
+        let result = parse("SELECT 1; UPDATE t SET a = 1").unwrap();
+        let mut cursor = result.cursor();
+
+        assert!(matches!(cursor.node(), Some(NodeRef::SelectStmt(_))));
+        assert!(cursor.goto_next_sibling());
+        assert!(matches!(cursor.node(), Some(NodeRef::UpdateStmt(_))));
+        assert!(!cursor.goto_next_sibling());
+    }
+
+    #[test]
+    fn it_finds_the_node_at_an_offset() {
+        let query = "SELECT a FROM t; DELETE FROM users WHERE id = 1";
+        let result = parse(query).unwrap();
+        let mut cursor = result.cursor();
+
+        let offset = query.find("users").unwrap() + 2;
+        let node = cursor.node_at_offset(offset);
+        assert!(matches!(node, Some(NodeRef::RangeVar(r)) if r.relname == "users"));
+        assert!(cursor.goto_parent());
+        assert!(matches!(cursor.node(), Some(NodeRef::DeleteStmt(_))));
+
+        let offset = query.find("id").unwrap();
+        let node = cursor.node_at_offset(offset);
+        assert!(matches!(node, Some(NodeRef::ColumnRef(_))));
+        assert!(cursor.goto_parent());
+        assert!(matches!(cursor.node(), Some(NodeRef::AExpr(_))));
+
+        let node = cursor.node_at_offset(query.len() - 1);
+        assert!(matches!(node, Some(NodeRef::AConst(_))));
+
+        let node = cursor.node_at_offset(7);
+        assert!(matches!(node, Some(NodeRef::ColumnRef(_))));
+        assert_eq!(cursor.depth(), 2);
+    }
+
+    #[test]
+    fn it_handles_empty_results() {
+        let result = parse("").unwrap();
+        let mut cursor = result.cursor();
+        assert!(cursor.node().is_none());
+        assert!(!cursor.goto_first_child());
+        assert!(!cursor.goto_next_sibling());
+        assert!(cursor.node_at_offset(0).is_none());
+    }
+}
@@ -1,9 +1,11 @@
 use std::ffi::CStr;
+use std::fmt;
 use std::os::raw::c_char;
 
 use crate::bindings::*;
 use crate::error::*;
 use crate::protobuf;
+use crate::{AstPath, FieldValue, NodeEnum, NodeKind, NodeRef, PathSegment};
 
 use prost::Message;
 
@@ -28,9 +30,85 @@ use prost::Message;
 /// assert_eq!(select.deparse().unwrap(), "SELECT name FROM contacts");
 /// ```
 ///
-/// Note that this function will panic if called on a node not defined in `deparseStmt`
+/// Nodes other than statements are deparsed on their own, see [`NodeRef::deparse`].
+///
+/// Failures are reported as [`Error::Deparse`] with the statement that could not be deparsed.
+/// Nodes that only occur in analyzed queries, such as `Var` or `TargetEntry`, are rejected
+/// before the tree is handed to the deparser.
 pub fn deparse(protobuf: &protobuf::ParseResult) -> Result<String> {
-    let buffer = protobuf.encode_to_vec();
+    check_statements(protobuf)?;
+    deparse_raw(protobuf).map_err(|message| statement_error(protobuf, message))
+}
+
+/// Converts a syntax tree encoded with protobuf back into a string.
+///
+/// This takes the output of [`parse_to_protobuf_bytes`](crate::parse_to_protobuf_bytes) or of
+/// the pg_query bindings for other languages.
+///
+/// The bytes are still decoded in full, so that they can be checked for unsupported nodes like
+/// in [`deparse`] before they reach the deparser, which does not handle malformed input. Only
+/// the encoding step is saved: the deparser gets the bytes as they are.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::{deparse_protobuf_bytes, parse_to_protobuf_bytes};
+///
+/// let bytes = parse_to_protobuf_bytes("select a from t where b = 1").unwrap();
+/// assert_eq!(deparse_protobuf_bytes(&bytes).unwrap(), "SELECT a FROM t WHERE b = 1");
+/// ```
+pub fn deparse_protobuf_bytes(bytes: &[u8]) -> Result<String> {
+    let protobuf = protobuf::ParseResult::decode(bytes)?;
+    check_statements(&protobuf)?;
+    deparse_bytes(bytes).map_err(|message| statement_error(&protobuf, message))
+}
+
+/// Returns an error for the first statement that is empty or contains nodes the deparser does not
+/// support
+fn check_statements(protobuf: &protobuf::ParseResult) -> Result<()> {
+    let mut path = Vec::new();
+    for (index, raw_stmt) in protobuf.stmts.iter().enumerate() {
+        path.extend([PathSegment::Field("stmts"), PathSegment::Index(index)]);
+        match raw_stmt.stmt.as_ref().and_then(|s| s.node.as_ref()) {
+            Some(stmt) => check_supported(stmt.to_ref(), &mut path)?,
+            None => return Err(deparse_error(None, path, "the statement is empty")),
+        }
+        path.clear();
+    }
+    Ok(())
+}
+
+/// Turns a message of the C deparser into an error for the statement it failed on
+fn statement_error(protobuf: &protobuf::ParseResult, message: String) -> Error {
+    // With several statements, deparse them one by one to find the one that failed
+    let index = protobuf
+        .stmts
+        .iter()
+        .position(|raw_stmt| {
+            protobuf.stmts.len() > 1
+                && deparse_raw(&protobuf::ParseResult {
+                    version: protobuf.version,
+                    stmts: vec![raw_stmt.clone()],
+                })
+                .is_err()
+        })
+        .unwrap_or(0);
+    let kind = protobuf
+        .stmts
+        .get(index)
+        .and_then(|s| s.stmt.as_ref())
+        .and_then(|s| s.node.as_ref())
+        .map(|n| n.kind());
+    let path = vec![PathSegment::Field("stmts"), PathSegment::Index(index)];
+    deparse_error(kind, path, message)
+}
+
+/// Calls the C deparser, returning its error message on failure
+fn deparse_raw(protobuf: &protobuf::ParseResult) -> core::result::Result<String, String> {
+    deparse_bytes(&protobuf.encode_to_vec())
+}
+
+fn deparse_bytes(buffer: &[u8]) -> core::result::Result<String, String> {
     let len = buffer.len();
     let data = buffer.as_ptr() as *const c_char as *mut c_char;
     let protobuf = PgQueryProtobuf { data, len };
@@ -40,7 +118,7 @@ pub fn deparse(protobuf: &protobuf::ParseResult) -> Result<String> {
         let message = unsafe { CStr::from_ptr((*result.error).message) }
             .to_string_lossy()
             .to_string();
-        Err(Error::Parse(message))
+        Err(message)
     } else {
         let query = unsafe { CStr::from_ptr(result.query) }
             .to_string_lossy()
@@ -52,9 +130,270 @@ pub fn deparse(protobuf: &protobuf::ParseResult) -> Result<String> {
     deparse_result
 }
 
+/// Describes why a node could not be deparsed.
+///
+/// Nodes the deparser does not support are reported with their own kind and path. When the C
+/// deparser itself fails, its message cannot be traced back to a node, so the error only points
+/// at the failing statement `stmts[i]` and carries the kind of that statement rather than of the
+/// subtree that caused the failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeparseError {
+    /// The kind of the node that could not be deparsed, or None for an empty node.
+    pub kind: Option<NodeKind>,
+    /// The path from the deparsed node or parse result to the node that could not be deparsed.
+    pub path: AstPath,
+    pub message: String,
+}
+
+impl fmt::Display for DeparseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Some(kind) => write!(f, "{kind}")?,
+            None => write!(f, "empty node")?,
+        }
+        if !self.path.0.is_empty() {
+            write!(f, " at {}", self.path)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+fn deparse_error(
+    kind: Option<NodeKind>,
+    path: Vec<PathSegment>,
+    message: impl Into<String>,
+) -> Error {
+    Error::Deparse(DeparseError {
+        kind,
+        path: AstPath(path),
+        message: message.into(),
+    })
+}
+
+/// Returns an error for the first node below and including `node` that the deparser does not
+/// support
+fn check_supported(node: NodeRef<'_>, path: &mut Vec<PathSegment>) -> Result<()> {
+    let kind = node.kind();
+    if is_analyzed(kind) {
+        return Err(deparse_error(
+            Some(kind),
+            path.clone(),
+            "nodes of analyzed queries cannot be deparsed",
+        ));
+    }
+
+    for (field, value) in node.fields() {
+        path.push(PathSegment::Field(field));
+        check_supported_value(&value, path)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+/// Returns whether nodes of `kind` only appear in analyzed queries, which the deparser does not
+/// support. The arms for nodes added in later Postgres versions follow the grammar that the
+/// macros crate selects: Postgres 15 when its feature is enabled, then 16, otherwise 17.
+fn is_analyzed(kind: NodeKind) -> bool {
+    use NodeKind::*;
+
+    match kind {
+        TableFunc | Var | Param | Aggref | WindowFunc | SubscriptingRef | FuncExpr | OpExpr
+        | DistinctExpr | NullIfExpr | ScalarArrayOpExpr | SubPlan | AlternativeSubPlan
+        | FieldSelect | FieldStore | RelabelType | CoerceViaIo | ArrayCoerceExpr
+        | ConvertRowtypeExpr | CollateExpr | CaseTestExpr | ArrayExpr | RowCompareExpr
+        | MergeAction | CoerceToDomain | CoerceToDomainValue | NextValueExpr | InferenceElem
+        | TargetEntry | RangeTblRef | FromExpr | OnConflictExpr | Query | RangeTblEntry
+        | RangeTblFunction | TableSampleClause | WithCheckOption | SortGroupClause
+        | WindowClause | RowMarkClause => true,
+        #[cfg(not(feature = "postgres-15"))]
+        JsonConstructorExpr | RtepermissionInfo => true,
+        #[cfg(not(any(feature = "postgres-15", feature = "postgres-16")))]
+        WindowFuncRunCondition
+        | JsonExpr
+        | JsonTablePath
+        | JsonTablePathScan
+        | JsonTableSiblingJoin => true,
+        _ => false,
+    }
+}
+
+fn check_supported_value(value: &FieldValue<'_>, path: &mut Vec<PathSegment>) -> Result<()> {
+    match value {
+        FieldValue::Node(node) => check_supported(*node, path),
+        FieldValue::List(values) => {
+            for (index, value) in values.iter().enumerate() {
+                path.push(PathSegment::Index(index));
+                check_supported_value(value, path)?;
+                path.pop();
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Deparses a single node. Statements are deparsed as they are, fragments such as expressions
+/// are wrapped in a statement whose surrounding text is removed again.
+pub(crate) fn deparse_node(node: NodeRef<'_>) -> Result<String> {
+    let kind = node.kind();
+    check_supported(node, &mut Vec::new())?;
+
+    let (stmt, prefix, suffix) = match Fragment::of(kind) {
+        Some(fragment) => fragment.wrap(node.to_enum()),
+        None if kind.name().ends_with("Stmt") => (node.to_enum(), "", ""),
+        None => {
+            return Err(deparse_error(
+                Some(kind),
+                Vec::new(),
+                "the node cannot be deparsed on its own",
+            ))
+        }
+    };
+
+    let sql = deparse_raw(&protobuf::ParseResult {
+        version: PG_VERSION_NUM as i32,
+        stmts: vec![protobuf::RawStmt {
+            stmt: Some(Box::new(protobuf::Node { node: Some(stmt) })),
+            stmt_location: 0,
+            stmt_len: 0,
+        }],
+    })
+    .map_err(|message| deparse_error(Some(kind), Vec::new(), message))?;
+
+    sql.strip_prefix(prefix)
+        .and_then(|s| s.strip_suffix(suffix))
+        .map(str::to_string)
+        .ok_or_else(|| {
+            deparse_error(
+                Some(kind),
+                Vec::new(),
+                format!("unexpected deparser output: {sql}"),
+            )
+        })
+}
+
+/// The kinds of nodes that can be deparsed by placing them in a `SELECT`
+#[derive(Clone, Copy)]
+enum Fragment {
+    Expression,
+    TypeName,
+    Target,
+    SortBy,
+    WindowDef,
+    FromItem,
+    CommonTableExpr,
+}
+
+impl Fragment {
+    /// Arms for nodes added after Postgres 15 follow the grammar that the macros crate selects
+    fn of(kind: NodeKind) -> Option<Fragment> {
+        use NodeKind::*;
+
+        Some(match kind {
+            ColumnRef | AConst | ParamRef | AIndirection | CaseExpr | SubLink | AArrayExpr
+            | RowExpr | GroupingFunc | FuncCall | AExpr | BoolExpr | NullTest | BooleanTest
+            | TypeCast | CollateClause | CoalesceExpr | MinMaxExpr | SqlvalueFunction | XmlExpr
+            | XmlSerialize => Fragment::Expression,
+            #[cfg(not(feature = "postgres-15"))]
+            JsonIsPredicate
+            | JsonObjectConstructor
+            | JsonArrayConstructor
+            | JsonArrayQueryConstructor
+            | JsonObjectAgg
+            | JsonArrayAgg => Fragment::Expression,
+            #[cfg(not(any(feature = "postgres-15", feature = "postgres-16")))]
+            JsonParseExpr | JsonScalarExpr | JsonSerializeExpr | JsonFuncExpr
+            | MergeSupportFunc => Fragment::Expression,
+            TypeName => Fragment::TypeName,
+            ResTarget => Fragment::Target,
+            SortBy => Fragment::SortBy,
+            WindowDef => Fragment::WindowDef,
+            RangeVar | JoinExpr | RangeSubselect | RangeFunction | RangeTableSample
+            | RangeTableFunc => Fragment::FromItem,
+            #[cfg(not(any(feature = "postgres-15", feature = "postgres-16")))]
+            JsonTable => Fragment::FromItem,
+            CommonTableExpr => Fragment::CommonTableExpr,
+            _ => return None,
+        })
+    }
+
+    /// Returns a statement containing `node` and the text around it in the deparsed statement
+    fn wrap(self, node: NodeEnum) -> (NodeEnum, &'static str, &'static str) {
+        let mut select = protobuf::SelectStmt {
+            limit_option: protobuf::LimitOption::Default as i32,
+            op: protobuf::SetOperation::SetopNone as i32,
+            ..Default::default()
+        };
+        let target = |val: NodeEnum| {
+            protobuf::Node::from(protobuf::ResTarget {
+                val: Some(Box::new(val.into())),
+                location: -1,
+                ..Default::default()
+            })
+        };
+
+        let (prefix, suffix) = match (self, node) {
+            (Fragment::Expression, node) => {
+                select.target_list.push(target(node));
+                ("SELECT ", "")
+            }
+            (Fragment::TypeName, NodeEnum::TypeName(type_name)) => {
+                let null = protobuf::AConst {
+                    isnull: true,
+                    location: -1,
+                    val: None,
+                };
+                select
+                    .target_list
+                    .push(target(NodeEnum::from(protobuf::TypeCast {
+                        arg: Some(Box::new(null.into())),
+                        type_name: Some(type_name),
+                        location: -1,
+                    })));
+                ("SELECT NULL::", "")
+            }
+            (Fragment::Target, node) => {
+                select.target_list.push(node.into());
+                ("SELECT ", "")
+            }
+            (Fragment::SortBy, node) => {
+                select.sort_clause.push(node.into());
+                ("SELECT ORDER BY ", "")
+            }
+            (Fragment::WindowDef, NodeEnum::WindowDef(mut def)) if def.name.is_empty() => {
+                def.name = "w".to_string();
+                select.window_clause.push(NodeEnum::WindowDef(def).into());
+                ("SELECT WINDOW w AS ", "")
+            }
+            (Fragment::WindowDef, node) => {
+                select.window_clause.push(node.into());
+                ("SELECT WINDOW ", "")
+            }
+            (Fragment::FromItem, node) => {
+                select.from_clause.push(node.into());
+                ("SELECT FROM ", "")
+            }
+            (Fragment::CommonTableExpr, node) => {
+                select.with_clause = Some(protobuf::WithClause {
+                    ctes: vec![node.into()],
+                    recursive: false,
+                    location: -1,
+                });
+                ("WITH ", " SELECT")
+            }
+            (Fragment::TypeName, _) => unreachable!("only TypeName nodes are type names"),
+        };
+
+        (NodeEnum::SelectStmt(Box::new(select)), prefix, suffix)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::parse;
+    use crate::{
+        ast, deparse_protobuf_bytes, parse, parse_to_protobuf_bytes, Error, NodeEnum, NodeKind,
+        ParseResult,
+    };
 
     fn assert_deparse(input: &str, output: &str) {
         let result = parse(input).unwrap();
@@ -90,4 +429,171 @@ mod tests {
         let query = "SELECT DISTINCT ON (a) a, b FROM c";
         assert_deparse(query, query);
     }
+
+    fn fragments(query: &str, kind: NodeKind) -> Vec<String> {
+        let result = parse(query).unwrap();
+        result.stmts()[0]
+            .iter_with_order(crate::TraversalOrder::PreOrder)
+            .filter(|n| n.kind() == kind)
+            .map(|n| n.deparse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn it_deparses_expressions() {
+        assert_eq!(
+            fragments(
+                "SELECT * FROM t WHERE a = $1 AND lower(b) LIKE 'x%' OR c IS NULL",
+                NodeKind::AExpr
+            ),
+            ["a = $1", "lower(b) LIKE 'x%'"]
+        );
+        assert_eq!(
+            fragments("SELECT a.b, count(*) FROM a", NodeKind::ColumnRef),
+            ["a.b"]
+        );
+        assert_eq!(
+            fragments("SELECT 1 WHERE a IN (SELECT b FROM c)", NodeKind::SubLink),
+            ["a IN (SELECT b FROM c)"]
+        );
+        assert_eq!(
+            fragments("SELECT CASE WHEN a THEN 1 END", NodeKind::CaseExpr),
+            ["CASE WHEN a THEN 1 END"]
+        );
+    }
+
+    #[test]
+    fn it_deparses_type_names() {
+        assert_eq!(
+            fragments(
+                "CREATE TABLE t (a int, b numeric(10, 2), c text[], d timestamptz)",
+                NodeKind::TypeName
+            ),
+            ["int", "numeric(10, 2)", "text[]", "timestamptz"]
+        );
+    }
+
+    #[test]
+    fn it_deparses_clauses() {
+        assert_eq!(
+            fragments("SELECT a AS x, b FROM t", NodeKind::ResTarget),
+            ["a AS x", "b"]
+        );
+        assert_eq!(
+            fragments(
+                "SELECT a FROM t ORDER BY a DESC NULLS LAST",
+                NodeKind::SortBy
+            ),
+            ["a DESC NULLS LAST"]
+        );
+        assert_eq!(
+            fragments(
+                "SELECT sum(a) OVER (PARTITION BY b ORDER BY c) FROM t",
+                NodeKind::WindowDef
+            ),
+            ["(PARTITION BY b ORDER BY c)"]
+        );
+        assert_eq!(
+            fragments(
+                "SELECT 1 FROM t WINDOW w1 AS (ORDER BY a)",
+                NodeKind::WindowDef
+            ),
+            ["w1 AS (ORDER BY a)"]
+        );
+        assert_eq!(
+            fragments("SELECT 1 FROM a JOIN b USING (id)", NodeKind::JoinExpr),
+            ["a JOIN b USING (id)"]
+        );
+        assert_eq!(
+            fragments(
+                "WITH x AS (SELECT 1) SELECT * FROM x",
+                NodeKind::CommonTableExpr
+            ),
+            ["x AS (SELECT 1)"]
+        );
+    }
+
+    #[test]
+    fn it_rejects_nodes_that_cannot_stand_alone() {
+        let result = parse("SELECT a FROM t AS x").unwrap();
+        let alias = result.stmts()[0]
+            .iter()
+            .find(|n| n.kind() == NodeKind::Alias)
+            .unwrap();
+        assert_eq!(
+            alias.deparse().unwrap_err().to_string(),
+            "Error deparsing Alias: the node cannot be deparsed on its own"
+        );
+    }
+
+    #[test]
+    fn it_rejects_nodes_of_analyzed_queries() {
+        let mut result = parse("SELECT 1; SELECT a FROM t WHERE b").unwrap();
+        result.stmts_mut()[1].walk_mut(|node, _| {
+            if let NodeEnum::ColumnRef(c) = node {
+                if c.fields[0].as_string().is_some_and(|s| s.sval == "b") {
+                    *node = NodeEnum::from(ast::Var::default());
+                }
+            }
+        });
+
+        let Err(Error::Deparse(error)) = result.deparse() else {
+            panic!("expected a deparse error");
+        };
+        assert_eq!(error.kind, Some(NodeKind::Var));
+        assert_eq!(error.path.to_string(), "stmts[1].where_clause");
+        assert_eq!(
+            error.to_string(),
+            "Var at stmts[1].where_clause: nodes of analyzed queries cannot be deparsed"
+        );
+    }
+
+    #[test]
+    fn it_reports_the_statement_the_deparser_failed_on() {
+        let mut result = parse("SELECT 1; SELECT 2 WHERE a; SELECT 3").unwrap();
+        if let NodeEnum::SelectStmt(select) = result.stmts_mut().remove(1) {
+            // Not an expression, which the deparser reports as an error
+            select.where_clause = Some(Box::new(ast::Node::from(ast::Alias::default())));
+        }
+
+        let Err(Error::Deparse(error)) = result.deparse() else {
+            panic!("expected a deparse error");
+        };
+        assert_eq!(error.kind, Some(NodeKind::SelectStmt));
+        assert_eq!(error.path.to_string(), "stmts[1]");
+    }
+
+    #[test]
+    fn it_deparses_protobuf_bytes() {
+        let query = "SELECT a FROM t WHERE b = $1; UPDATE t SET a = 1";
+        let bytes = parse_to_protobuf_bytes(query).unwrap();
+        assert_eq!(deparse_protobuf_bytes(&bytes).unwrap(), query);
+
+        let result = ParseResult::from_protobuf_bytes(&bytes).unwrap();
+        assert_eq!(result.protobuf, parse(query).unwrap().protobuf);
+        assert_eq!(result.to_protobuf_bytes(), bytes);
+    }
+
+    #[test]
+    fn it_reports_errors_for_protobuf_bytes() {
+        let mut result = parse("SELECT 1; SELECT 2 WHERE a").unwrap();
+        if let NodeEnum::SelectStmt(select) = result.stmts_mut().remove(1) {
+            select.where_clause = Some(Box::new(ast::Node::from(ast::Alias::default())));
+        }
+
+        let Err(Error::Deparse(error)) = deparse_protobuf_bytes(&result.to_protobuf_bytes()) else {
+            panic!("expected a deparse error");
+        };
+        assert_eq!(error.kind, Some(NodeKind::SelectStmt));
+        assert_eq!(error.path.to_string(), "stmts[1]");
+
+        assert!(matches!(
+            deparse_protobuf_bytes(&[0xff]),
+            Err(Error::Decode(_))
+        ));
+        assert!(matches!(
+            ParseResult::from_protobuf_bytes(&[0xff]),
+            Err(Error::Decode(_))
+        ));
+    }
 }
@@ -0,0 +1,419 @@
+use std::fmt;
+
+use crate::{NodeRef, ParseResult, StructuralEq};
+
+pg_parse_macros::diff_codegen!();
+
+/// Compares two parse results and returns the changes needed to turn `a` into `b`.
+///
+/// Differences in location fields are ignored, so reformatting a query produces no changes.
+/// Lists are aligned on their structurally equal elements, so inserting a column into a select
+/// list reports a single added node instead of a change to every following column.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::{ast_diff, parse, ChangeKind, DiffValue};
+///
+/// let a = parse("CREATE VIEW v AS SELECT * FROM t WHERE a = 1").unwrap();
+/// let b = parse("create view v as\n  select * from t where a = 2").unwrap();
+///
+/// let changes = ast_diff(&a, &b);
+/// assert_eq!(changes.len(), 1);
+/// assert_eq!(
+///     changes[0].path.to_string(),
+///     "stmts[0].query.where_clause.rexpr.val.ival"
+/// );
+/// assert!(matches!(
+///     &changes[0].kind,
+///     ChangeKind::Changed { old: DiffValue::Value(old), new: DiffValue::Value(new) }
+///         if old == "1" && new == "2"
+/// ));
+/// ```
+pub fn ast_diff<'a>(a: &'a ParseResult, b: &'a ParseResult) -> Vec<AstChange<'a>> {
+    let mut differ = Differ {
+        path: Vec::new(),
+        spans: (None, None),
+        changes: Vec::new(),
+    };
+
+    differ.list(
+        "stmts",
+        a.stmts().into_iter().map(|s| s.to_ref()).collect(),
+        b.stmts().into_iter().map(|s| s.to_ref()).collect(),
+    );
+
+    differ.changes
+}
+
+/// A single difference between two parse results.
+#[derive(Debug, Clone)]
+pub struct AstChange<'a> {
+    /// Where the change happened. Paths point into the old tree, except for added nodes,
+    /// whose list indices refer to the new tree.
+    pub path: AstPath,
+    pub kind: ChangeKind<'a>,
+    /// The span of the changed node in the old input. For added nodes and changed fields, this
+    /// is the span of the closest enclosing node that has a location.
+    pub old_span: Option<Span>,
+    /// The span of the changed node in the new input. For removed nodes and changed fields,
+    /// this is the span of the closest enclosing node that has a location.
+    pub new_span: Option<Span>,
+}
+
+/// What kind of difference an [`AstChange`] describes.
+#[derive(Debug, Clone)]
+pub enum ChangeKind<'a> {
+    /// A node only present in the new tree.
+    Added(NodeRef<'a>),
+    /// A node only present in the old tree.
+    Removed(NodeRef<'a>),
+    /// A field whose value differs, or a node that was replaced with a node of another type.
+    Changed {
+        old: DiffValue<'a>,
+        new: DiffValue<'a>,
+    },
+}
+
+/// The old or new value of a changed field.
+#[derive(Debug, Clone)]
+pub enum DiffValue<'a> {
+    Node(NodeRef<'a>),
+    /// A scalar value rendered as text. Enum values are rendered by their protobuf name, e.g.
+    /// `AEXPR_OP`.
+    Value(String),
+}
+
+/// A range of byte offsets into the source text.
+///
+/// Nodes only record the offset at which they start, so `last` is the start of the last token
+/// located within the node rather than the end of the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub last: usize,
+}
+
+impl Span {
+    /// Computes the span of `node` from the locations of its descendants.
+    ///
+    /// Returns None if neither the node nor any of its descendants has a known location.
+    pub fn of(node: NodeRef<'_>) -> Option<Span> {
+        let mut locations = node.iter().filter_map(|n| n.location());
+        let first = locations.next()?;
+        let (start, last) = locations.fold((first, first), |(start, last), l| {
+            (start.min(l), last.max(l))
+        });
+        Some(Span {
+            start: start as usize,
+            last: last as usize,
+        })
+    }
+}
+
+/// The fields and list indices leading from the statements of a parse result to a node.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AstPath(pub Vec<PathSegment>);
+
+/// A step in an [`AstPath`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment {
+    Field(&'static str),
+    Index(usize),
+}
+
+impl fmt::Display for AstPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Field(name) if i == 0 => write!(f, "{name}")?,
+                PathSegment::Field(name) => write!(f, ".{name}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collects the changes between two trees while walking them side by side
+struct Differ<'a> {
+    path: Vec<PathSegment>,
+    // The spans of the closest enclosing nodes with a location in the old and new tree
+    spans: (Option<Span>, Option<Span>),
+    changes: Vec<AstChange<'a>>,
+}
+
+impl<'a> Differ<'a> {
+    fn push(&mut self, kind: ChangeKind<'a>, old_span: Option<Span>, new_span: Option<Span>) {
+        self.changes.push(AstChange {
+            path: AstPath(self.path.clone()),
+            kind,
+            old_span,
+            new_span,
+        });
+    }
+
+    /// Compares a scalar field
+    fn value<T: StructuralEq>(
+        &mut self,
+        field: &'static str,
+        a: &T,
+        b: &T,
+        render: impl Fn(&T) -> String,
+    ) {
+        if !a.structural_eq(b) {
+            self.path.push(PathSegment::Field(field));
+            let kind = ChangeKind::Changed {
+                old: DiffValue::Value(render(a)),
+                new: DiffValue::Value(render(b)),
+            };
+            self.push(kind, self.spans.0, self.spans.1);
+            self.path.pop();
+        }
+    }
+
+    /// Compares an optional node field
+    fn node(&mut self, field: &'static str, a: Option<NodeRef<'a>>, b: Option<NodeRef<'a>>) {
+        self.path.push(PathSegment::Field(field));
+        self.compare(a, b);
+        self.path.pop();
+    }
+
+    /// Compares a list of nodes, aligning the lists on their longest common subsequence
+    fn list(&mut self, field: &'static str, a: Vec<NodeRef<'a>>, b: Vec<NodeRef<'a>>) {
+        self.path.push(PathSegment::Field(field));
+
+        // lengths[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+        let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lengths[i][j] = if a[i].structural_eq(&b[j]) {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        let (mut removed, mut added) = (Vec::new(), Vec::new());
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i].structural_eq(&b[j]) {
+                self.unmatched(&a, &b, &mut removed, &mut added);
+                i += 1;
+                j += 1;
+            } else if j == b.len() || (i < a.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+                removed.push(i);
+                i += 1;
+            } else {
+                added.push(j);
+                j += 1;
+            }
+        }
+        self.unmatched(&a, &b, &mut removed, &mut added);
+
+        self.path.pop();
+    }
+
+    /// Compares a run of list elements that have no structurally equal counterpart. Elements at
+    /// the same position in the run are compared field by field, the rest are added or removed.
+    fn unmatched(
+        &mut self,
+        a: &[NodeRef<'a>],
+        b: &[NodeRef<'a>],
+        removed: &mut Vec<usize>,
+        added: &mut Vec<usize>,
+    ) {
+        let paired = removed.len().min(added.len());
+        for k in 0..removed.len().max(added.len()) {
+            let (index, old, new) = if k < paired {
+                (removed[k], Some(a[removed[k]]), Some(b[added[k]]))
+            } else if k < removed.len() {
+                (removed[k], Some(a[removed[k]]), None)
+            } else {
+                (added[k], None, Some(b[added[k]]))
+            };
+            self.path.push(PathSegment::Index(index));
+            self.compare(old, new);
+            self.path.pop();
+        }
+        removed.clear();
+        added.clear();
+    }
+
+    fn compare(&mut self, a: Option<NodeRef<'a>>, b: Option<NodeRef<'a>>) {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                if std::mem::discriminant(&a) != std::mem::discriminant(&b) {
+                    let kind = ChangeKind::Changed {
+                        old: DiffValue::Node(a),
+                        new: DiffValue::Node(b),
+                    };
+                    self.push(
+                        kind,
+                        Span::of(a).or(self.spans.0),
+                        Span::of(b).or(self.spans.1),
+                    );
+                } else if !a.structural_eq(&b) {
+                    let parent = self.spans;
+                    self.spans = (Span::of(a).or(parent.0), Span::of(b).or(parent.1));
+                    diff_fields(a, b, self);
+                    self.spans = parent;
+                }
+            }
+            (Some(a), None) => {
+                self.push(ChangeKind::Removed(a), Span::of(a), self.spans.1);
+            }
+            (None, Some(b)) => {
+                self.push(ChangeKind::Added(b), self.spans.0, Span::of(b));
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn diff(a: &str, b: &str) -> Vec<(String, String)> {
+        let a = parse(a).unwrap();
+        let b = parse(b).unwrap();
+        ast_diff(&a, &b)
+            .into_iter()
+            .map(|c| {
+                let kind = match c.kind {
+                    ChangeKind::Added(_) => "added".to_string(),
+                    ChangeKind::Removed(_) => "removed".to_string(),
+                    ChangeKind::Changed {
+                        old: DiffValue::Value(old),
+                        new: DiffValue::Value(new),
+                    } => format!("{old} -> {new}"),
+                    ChangeKind::Changed { .. } => "replaced".to_string(),
+                };
+                (c.path.to_string(), kind)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_ignores_formatting() {
+        assert!(diff("SELECT a, b FROM t", "select a,\n       b\n  from t").is_empty());
+    }
+
+    #[test]
+    fn it_reports_changed_fields() {
+        assert_eq!(
+            diff("SELECT a FROM t", "SELECT a FROM u"),
+            vec![(
+                "stmts[0].from_clause[0].relname".to_string(),
+                "\"t\" -> \"u\"".to_string()
+            )]
+        );
+        assert_eq!(
+            diff("SELECT a FROM t WHERE a < 1", "SELECT a FROM t WHERE a > 1"),
+            vec![(
+                "stmts[0].where_clause.name[0].sval".to_string(),
+                "\"<\" -> \">\"".to_string()
+            )]
+        );
+        assert_eq!(
+            diff("SELECT 1 UNION SELECT 2", "SELECT 1 INTERSECT SELECT 2"),
+            vec![(
+                "stmts[0].op".to_string(),
+                "SETOP_UNION -> SETOP_INTERSECT".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn it_aligns_lists() {
+        assert_eq!(
+            diff("SELECT a, c FROM t", "SELECT a, b, c FROM t"),
+            vec![("stmts[0].target_list[1]".to_string(), "added".to_string())]
+        );
+        assert_eq!(
+            diff("SELECT 1; SELECT 2; SELECT 3", "SELECT 1; SELECT 3"),
+            vec![("stmts[1]".to_string(), "removed".to_string())]
+        );
+    }
+
+    #[test]
+    fn it_reports_added_and_removed_fields() {
+        assert_eq!(
+            diff("SELECT a FROM t", "SELECT a FROM t WHERE a = 1"),
+            vec![("stmts[0].where_clause".to_string(), "added".to_string())]
+        );
+        assert_eq!(
+            diff("SELECT a FROM t LIMIT 1", "SELECT a FROM t"),
+            vec![
+                ("stmts[0].limit_count".to_string(), "removed".to_string()),
+                (
+                    "stmts[0].limit_option".to_string(),
+                    "LIMIT_OPTION_COUNT -> LIMIT_OPTION_DEFAULT".to_string()
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_replaced_nodes() {
+        assert_eq!(
+            diff(
+                "SELECT a FROM t WHERE a = 1",
+                "SELECT a FROM t WHERE a IS NULL"
+            ),
+            vec![("stmts[0].where_clause".to_string(), "replaced".to_string())]
+        );
+        assert_eq!(
+            diff("SELECT 1", "SELECT 'a'"),
+            vec![(
+                "stmts[0].target_list[0].val.val".to_string(),
+                "replaced".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn it_returns_spans_into_both_inputs() {
+        let a = parse("SELECT a FROM t WHERE b = 1").unwrap();
+        let b = parse("SELECT a\nFROM t\nWHERE b = 2").unwrap();
+        let changes = ast_diff(&a, &b);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].old_span,
+            Some(Span {
+                start: 26,
+                last: 26
+            })
+        );
+        assert_eq!(
+            changes[0].new_span,
+            Some(Span {
+                start: 26,
+                last: 26
+            })
+        );
+
+        let b = parse("SELECT a FROM t WHERE b = 1 AND c = 2").unwrap();
+        let changes = ast_diff(&a, &b);
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0].kind, ChangeKind::Changed { .. }));
+        assert_eq!(
+            changes[0].old_span,
+            Some(Span {
+                start: 22,
+                last: 26
+            })
+        );
+        assert_eq!(
+            changes[0].new_span,
+            Some(Span {
+                start: 22,
+                last: 36
+            })
+        );
+    }
+}
@@ -17,6 +17,16 @@ pub enum Error {
     Scan(String),
     #[error("Error splitting: {0}")]
     Split(String),
+    #[error("Invalid name: {0}")]
+    Name(String),
+    #[error("Invalid mutation: {0}")]
+    Mutation(String),
+    #[error("Invalid tree: {0}")]
+    Validation(crate::ValidationError),
+    #[error("Error deparsing {0}")]
+    Deparse(crate::DeparseError),
+    #[error("Invalid builder call: {0}")]
+    Build(String),
 }
 
 /// Convenient Result alias for returning `pg_query::Error`.
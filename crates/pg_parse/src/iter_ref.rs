@@ -1 +1,100 @@
 pg_parse_macros::iter_ref_codegen!();
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{ColumnRef, FuncCall, RangeVar, SelectStmt};
+    use crate::{parse, AstNode, NodeKind, NodeMut, NodeRef};
+
+    #[test]
+    fn it_yields_oneof_members() {
+        let result = parse("SELECT 1, 'a', true").unwrap();
+        let nodes = result.stmts()[0].nodes();
+
+        assert!(nodes
+            .iter()
+            .any(|n| matches!(n, NodeRef::Integer(i) if i.ival == 1)));
+        assert!(nodes
+            .iter()
+            .any(|n| matches!(n, NodeRef::String(s) if s.sval == "a")));
+        assert!(nodes
+            .iter()
+            .any(|n| matches!(n, NodeRef::Boolean(b) if b.boolval)));
+    }
+
+    #[test]
+    fn it_descends_into_a_expr() {
+        let result = parse("SELECT * FROM t WHERE a = b + 1").unwrap();
+        let columns: Vec<_> = result.stmts()[0]
+            .iter()
+            .filter_map(|n| match n {
+                NodeRef::ColumnRef(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+
+        // `*`, `a` and `b`
+        assert_eq!(columns.len(), 3);
+    }
+
+    #[test]
+    fn it_yields_typed_message_fields() {
+        let result = parse("INSERT INTO t (a) VALUES (1)").unwrap();
+        assert!(result.stmts()[0]
+            .iter()
+            .any(|n| matches!(n, NodeRef::RangeVar(r) if r.relname == "t")));
+    }
+
+    #[test]
+    fn it_mutates_oneof_members() {
+        let mut result = parse("SELECT * FROM t WHERE a = 1").unwrap();
+        for node in result.stmts_mut()[0].iter_mut() {
+            if let NodeMut::Integer(i) = node {
+                unsafe { (*i).ival = 42 };
+            }
+        }
+        assert_eq!(result.deparse().unwrap(), "SELECT * FROM t WHERE a = 42");
+    }
+
+    #[test]
+    fn it_returns_direct_children() {
+        let result = parse("SELECT a, b FROM t WHERE c").unwrap();
+        let kinds: Vec<_> = result.stmts()[0]
+            .children()
+            .iter()
+            .map(|n| n.kind())
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                NodeKind::ResTarget,
+                NodeKind::ResTarget,
+                NodeKind::RangeVar,
+                NodeKind::ColumnRef
+            ]
+        );
+        assert!(result.stmts()[0].children()[2].children().is_empty());
+    }
+
+    #[test]
+    fn it_finds_typed_descendants() {
+        let result = parse("SELECT lower(a) FROM t WHERE b IN (SELECT upper(c) FROM u)").unwrap();
+        let stmt = result.stmts()[0];
+
+        let tables: Vec<_> = stmt
+            .descendants::<RangeVar>()
+            .map(|t| t.relname.as_str())
+            .collect();
+        assert_eq!(tables, ["t", "u"]);
+        assert_eq!(stmt.descendants::<ColumnRef>().count(), 3);
+
+        let func = stmt.find_first::<FuncCall>().unwrap();
+        assert_eq!(func.funcname[0].as_string().unwrap().sval, "lower");
+
+        // The node itself is not one of its descendants
+        let subquery = stmt.find_first::<SelectStmt>().unwrap();
+        assert!(subquery.to_ref().find_first::<SelectStmt>().is_none());
+        assert_eq!(SelectStmt::KIND, NodeKind::SelectStmt);
+        assert!(SelectStmt::cast(subquery.to_ref()).is_some());
+        assert!(RangeVar::cast(subquery.to_ref()).is_none());
+    }
+}
@@ -0,0 +1,427 @@
+//! `Serialize` and `Deserialize` for the syntax tree, using the JSON format of libpg_query.
+//!
+//! Nodes are written as an object with a single key naming the node type, e.g.
+//! `{"ColumnRef":{"fields":[...]}}`, and fields use the `json_name` from `pg_query.proto`. Like
+//! libpg_query, fields holding their default value are left out, except for enums, which are
+//! always written by name. When reading, fields can also be given by their protobuf name, enums
+//! by number, and unknown fields are ignored.
+use std::fmt;
+use std::marker::PhantomData;
+use std::result;
+
+use serde::de::{self, DeserializeSeed, MapAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::protobuf;
+
+pg_parse_macros::json_codegen!();
+
+impl ParseResult {
+    /// Reads a parse result from libpg_query's JSON format, as returned by
+    /// [`parse_json`](crate::parse_json).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use pg_parse::{parse_json, ParseResult};
+    ///
+    /// let json = parse_json("SELECT a FROM t WHERE b = 1").unwrap();
+    /// let result = ParseResult::from_json(&json).unwrap();
+    /// assert_eq!(result.deparse().unwrap(), "SELECT a FROM t WHERE b = 1");
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map(|protobuf| ParseResult::new(protobuf, String::new()))
+            .map_err(|e| Error::InvalidJson(e.to_string()))
+    }
+}
+
+/// Serializes as an empty object, which libpg_query writes for a missing node
+struct Empty;
+
+impl Serialize for Empty {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer.serialize_map(Some(0))?.end()
+    }
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// Reads the fields of a message from a JSON object
+trait DeserializeFields: Default {
+    /// The name of the message, for error messages
+    const NAME: &'static str;
+
+    /// Reads the value for `key` into the message, returning false if there is no such field
+    fn deserialize_field<'de, A: MapAccess<'de>>(
+        &mut self,
+        key: &str,
+        map: &mut A,
+    ) -> result::Result<bool, A::Error>;
+}
+
+struct MessageVisitor<T>(PhantomData<T>);
+
+impl<T> MessageVisitor<T> {
+    fn new() -> Self {
+        MessageVisitor(PhantomData)
+    }
+}
+
+impl<'de, T: DeserializeFields> Visitor<'de> for MessageVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a {} object", T::NAME)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> result::Result<T, A::Error> {
+        let mut message = T::default();
+        while let Some(key) = map.next_key::<String>()? {
+            if !message.deserialize_field(&key, &mut map)? {
+                map.next_value::<de::IgnoredAny>()?;
+            }
+        }
+        Ok(message)
+    }
+}
+
+/// Reads an enum value given by name or by number
+struct EnumSeed(fn(&str) -> Option<i32>);
+
+impl<'de> DeserializeSeed<'de> for EnumSeed {
+    type Value = i32;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> result::Result<i32, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl Visitor<'_> for EnumSeed {
+    type Value = i32;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an enum value name or number")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> result::Result<i32, E> {
+        (self.0)(v).ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> result::Result<i32, E> {
+        i32::try_from(v).map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> result::Result<i32, E> {
+        i32::try_from(v).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+    }
+}
+
+// Kept out of line, so that the dispatch on the node type in `deserialize_node_entry` does not
+// need stack space for every type of node while reading deeply nested trees.
+fn next_node<'de, A: MapAccess<'de>, T: Deserialize<'de> + Into<NodeEnum>>(
+    map: &mut A,
+) -> result::Result<NodeEnum, A::Error> {
+    map.next_value::<T>().map(Into::into)
+}
+
+/// Reads a node object, which is empty for a missing node
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Option<NodeEnum>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an object with a node type as its only key")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> result::Result<Self::Value, A::Error> {
+        let Some(label) = map.next_key::<String>()? else {
+            return Ok(None);
+        };
+        let node = deserialize_node_entry(&label, &mut map)?;
+        if map.next_key::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(2, &self));
+        }
+        Ok(Some(node))
+    }
+}
+
+impl Serialize for NodeEnum {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        serialize_node_entry(self, &mut map)?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeEnum {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer
+            .deserialize_map(NodeVisitor)?
+            .ok_or_else(|| de::Error::invalid_length(0, &NodeVisitor))
+    }
+}
+
+impl Serialize for protobuf::Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        match &self.node {
+            Some(node) => node.serialize(serializer),
+            None => Empty.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for protobuf::Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        Ok(protobuf::Node {
+            node: deserializer.deserialize_map(NodeVisitor)?,
+        })
+    }
+}
+
+impl Serialize for protobuf::ParseResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("version", &self.version)?;
+        map.serialize_entry("stmts", &self.stmts)?;
+        map.end()
+    }
+}
+
+impl DeserializeFields for protobuf::ParseResult {
+    const NAME: &'static str = "ParseResult";
+
+    fn deserialize_field<'de, A: MapAccess<'de>>(
+        &mut self,
+        key: &str,
+        map: &mut A,
+    ) -> result::Result<bool, A::Error> {
+        match key {
+            "version" => self.version = map.next_value()?,
+            "stmts" => self.stmts = map.next_value()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl<'de> Deserialize<'de> for protobuf::ParseResult {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer.deserialize_map(MessageVisitor::<Self>::new())
+    }
+}
+
+// libpg_query always writes the location of a constant, and writes a false boolean constant as
+// an empty object, unlike a `Boolean` node elsewhere in the tree.
+impl Serialize for protobuf::AConst {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        use protobuf::a_const::Val;
+
+        let mut map = serializer.serialize_map(None)?;
+        if self.isnull {
+            map.serialize_entry("isnull", &true)?;
+        } else {
+            match &self.val {
+                Some(Val::Ival(x)) => map.serialize_entry("ival", x)?,
+                Some(Val::Fval(x)) => map.serialize_entry("fval", x)?,
+                Some(Val::Boolval(x)) if x.boolval => map.serialize_entry("boolval", x)?,
+                Some(Val::Boolval(_)) => map.serialize_entry("boolval", &Empty)?,
+                Some(Val::Sval(x)) => map.serialize_entry("sval", x)?,
+                Some(Val::Bsval(x)) => map.serialize_entry("bsval", x)?,
+                None => {}
+            }
+        }
+        map.serialize_entry("location", &self.location)?;
+        map.end()
+    }
+}
+
+/// The items of an `IntList` or `OidList`, which libpg_query writes as plain numbers
+struct Numbers<'a>(&'a [protobuf::Node]);
+
+impl Serialize for Numbers<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for item in self.0 {
+            match &item.node {
+                Some(NodeEnum::Integer(i)) => seq.serialize_element(&i.ival)?,
+                _ => seq.serialize_element(item)?,
+            }
+        }
+        seq.end()
+    }
+}
+
+/// An item of an `IntList` or `OidList`, given either as a number or as a node
+struct NumberItem(protobuf::Node);
+
+impl<'de> Deserialize<'de> for NumberItem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        deserializer.deserialize_any(NumberItemVisitor)
+    }
+}
+
+struct NumberItemVisitor;
+
+impl NumberItemVisitor {
+    fn integer<E: de::Error>(ival: i32) -> result::Result<NumberItem, E> {
+        Ok(NumberItem(protobuf::Node {
+            node: Some(NodeEnum::Integer(protobuf::Integer { ival })),
+        }))
+    }
+}
+
+impl<'de> Visitor<'de> for NumberItemVisitor {
+    type Value = NumberItem;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number or a node")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> result::Result<NumberItem, E> {
+        let ival =
+            i32::try_from(v).map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))?;
+        Self::integer(ival)
+    }
+
+    // Oids are unsigned, and are stored in `Integer` nodes with their bits unchanged
+    fn visit_u64<E: de::Error>(self, v: u64) -> result::Result<NumberItem, E> {
+        let oid =
+            u32::try_from(v).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))?;
+        Self::integer(oid as i32)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> result::Result<NumberItem, A::Error> {
+        Ok(NumberItem(protobuf::Node {
+            node: NodeVisitor.visit_map(map)?,
+        }))
+    }
+}
+
+macro_rules! number_list {
+    ($($name:ident),*) => {$(
+        impl Serialize for protobuf::$name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("items", &Numbers(&self.items))?;
+                map.end()
+            }
+        }
+
+        impl DeserializeFields for protobuf::$name {
+            const NAME: &'static str = stringify!($name);
+
+            fn deserialize_field<'de, A: MapAccess<'de>>(
+                &mut self,
+                key: &str,
+                map: &mut A,
+            ) -> result::Result<bool, A::Error> {
+                if key != "items" {
+                    return Ok(false);
+                }
+                let items: Vec<NumberItem> = map.next_value()?;
+                self.items = items.into_iter().map(|item| item.0).collect();
+                Ok(true)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for protobuf::$name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+                deserializer.deserialize_map(MessageVisitor::<Self>::new())
+            }
+        }
+    )*};
+}
+
+number_list!(IntList, OidList);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, parse_json};
+
+    fn assert_matches_libpg_query(query: &str) {
+        let expected: serde_json::Value =
+            serde_json::from_str(&parse_json(query).unwrap()).unwrap();
+        let actual = serde_json::to_value(&parse(query).unwrap().protobuf).unwrap();
+        assert_eq!(actual, expected, "{query}");
+    }
+
+    #[test]
+    fn it_serializes_like_libpg_query() {
+        for query in [
+            "SELECT 1, 1.5, true, false, 'a', B'101', NULL, -1",
+            "SELECT DISTINCT ON (a) a, count(*) AS n FROM public.t AS x WHERE b IS NOT NULL GROUP BY a ORDER BY a DESC NULLS LAST LIMIT 10",
+            "SELECT * FROM a JOIN b USING (id) LEFT JOIN LATERAL (SELECT 1) c ON true UNION ALL SELECT * FROM d",
+            "WITH RECURSIVE r(n) AS (SELECT 1 UNION SELECT n + 1 FROM r) SELECT n FROM r WHERE n < $1",
+            "INSERT INTO t (a, b) VALUES (1, DEFAULT), (2, 'x') ON CONFLICT (a) DO UPDATE SET b = excluded.b RETURNING *",
+            "UPDATE t SET a = a + 1 WHERE id IN (SELECT id FROM u); DELETE FROM t WHERE a <> 0",
+            "CREATE TABLE t (id bigint PRIMARY KEY, name varchar(100) NOT NULL DEFAULT '', tags text[] CHECK (cardinality(tags) > 0))",
+            "ALTER TABLE t ADD COLUMN c int, DROP COLUMN IF EXISTS d CASCADE, ALTER COLUMN e TYPE numeric(10, 2)",
+            "CREATE INDEX CONCURRENTLY idx ON t USING btree (lower(a)) WHERE b",
+            "CREATE FUNCTION f(a int) RETURNS int LANGUAGE sql AS $$ SELECT a $$",
+            "SELECT a::int, CAST(b AS text), c[1], d -> 'k', row_number() OVER (PARTITION BY e ORDER BY f) FROM t",
+            "SELECT CASE WHEN a THEN 1 ELSE 2 END, COALESCE(b, 0), x BETWEEN 1 AND 2, y LIKE '<%>'",
+            "BEGIN; SET search_path = public; COMMIT",
+            "",
+        ] {
+            assert_matches_libpg_query(query);
+        }
+    }
+
+    #[test]
+    fn it_round_trips_libpg_query_json() {
+        for query in [
+            "SELECT a, b FROM t WHERE c = 1 AND d IS NULL ORDER BY a",
+            "INSERT INTO t (a) VALUES (true), (false) RETURNING id",
+            "CREATE TABLE t (id serial PRIMARY KEY, n numeric(10, 2) DEFAULT 0.5)",
+        ] {
+            let json = parse_json(query).unwrap();
+            let result = ParseResult::from_json(&json).unwrap();
+            assert_eq!(result.protobuf, parse(query).unwrap().protobuf);
+            assert_eq!(result.deparse().unwrap(), query);
+        }
+    }
+
+    #[test]
+    fn it_accepts_protobuf_field_names_and_enum_numbers() {
+        let json = r#"{"version":170004,"stmts":[{"stmt":{"SelectStmt":{
+            "target_list":[{"ResTarget":{"val":{"ColumnRef":{"fields":[{"A_Star":{}}]}}}}],
+            "fromClause":[{"RangeVar":{"relname":"t","inh":true,"relpersistence":"p"}}],
+            "limitOption":1,
+            "op":"SETOP_NONE",
+            "comment":"ignored"
+        }}}]}"#;
+        let result = ParseResult::from_json(json).unwrap();
+        assert_eq!(result.deparse().unwrap(), "SELECT * FROM t");
+    }
+
+    #[test]
+    fn it_reports_invalid_json() {
+        assert!(matches!(
+            ParseResult::from_json(r#"{"stmts":[{"stmt":{"NotANode":{}}}]}"#),
+            Err(Error::InvalidJson(message)) if message.contains("NotANode")
+        ));
+        assert!(matches!(
+            ParseResult::from_json(r#"{"stmts":[{"stmt":{"SelectStmt":{"op":"NOPE"}}}]}"#),
+            Err(Error::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn it_serializes_nodes() {
+        let result = parse("SELECT a FROM t").unwrap();
+        let node = result.stmts()[0].clone();
+        let json = serde_json::to_string(&node).unwrap();
+        assert!(json.starts_with(r#"{"SelectStmt":{"targetList":[{"ResTarget":"#));
+        assert_eq!(serde_json::from_str::<NodeEnum>(&json).unwrap(), node);
+        assert_eq!(
+            serde_json::to_string(&protobuf::Node { node: None }).unwrap(),
+            "{}"
+        );
+    }
+}
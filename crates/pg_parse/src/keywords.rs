@@ -0,0 +1,138 @@
+use crate::protobuf::KeywordKind;
+
+/// A keyword of the SQL grammar, as listed in Postgres's `kwlist.h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Keyword {
+    /// The keyword in lower case, e.g. `select`
+    pub name: &'static str,
+    /// Whether the keyword is reserved, and where it can be used as an identifier otherwise
+    pub kind: KeywordKind,
+    /// Whether the keyword can be used as a column label without `AS`
+    pub bare_label: bool,
+}
+
+/// Generated from `kwlist.h` of the Postgres version selected with the `postgres-*` features
+static KEYWORDS: &[Keyword] = include!(concat!(env!("OUT_DIR"), "/keywords.rs"));
+
+/// Returns the keywords of the grammar compiled into this crate, sorted by name.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::ast::KeywordKind;
+/// use pg_parse::keywords;
+///
+/// let select = keywords().iter().find(|k| k.name == "select").unwrap();
+/// assert_eq!(select.kind, KeywordKind::ReservedKeyword);
+/// assert!(keywords().iter().any(|k| k.kind == KeywordKind::ColNameKeyword));
+/// ```
+pub fn keywords() -> &'static [Keyword] {
+    KEYWORDS
+}
+
+/// Looks up a keyword, ignoring the case of ASCII letters like the Postgres scanner does.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::ast::KeywordKind;
+///
+/// assert_eq!(pg_parse::keyword("Abort").unwrap().kind, KeywordKind::UnreservedKeyword);
+/// assert_eq!(pg_parse::keyword("users"), None);
+/// ```
+pub fn keyword(word: &str) -> Option<&'static Keyword> {
+    let word = word.to_ascii_lowercase();
+    KEYWORDS
+        .binary_search_by(|k| k.name.cmp(&word))
+        .ok()
+        .map(|i| &KEYWORDS[i])
+}
+
+/// Returns whether an identifier has to be quoted to be used in SQL.
+///
+/// This is the case unless it only consists of lower case ASCII letters, digits and
+/// underscores, does not start with a digit, and is not a keyword other than an unreserved one.
+/// Like `quote_identifier` in Postgres, column name and type or function name keywords are
+/// always quoted even where they would be accepted.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::needs_quoting;
+///
+/// assert!(!needs_quoting("users"));
+/// assert!(!needs_quoting("action"));
+/// assert!(needs_quoting("Users"));
+/// assert!(needs_quoting("select"));
+/// assert!(needs_quoting("1st"));
+/// ```
+pub fn needs_quoting(ident: &str) -> bool {
+    let mut chars = ident.chars();
+    let safe = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+    !safe || keyword(ident).is_some_and(|k| k.kind != KeywordKind::UnreservedKeyword)
+}
+
+/// Quotes an identifier if necessary, exactly like `quote_identifier` in Postgres.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::quote_ident;
+///
+/// assert_eq!(quote_ident("users"), "users");
+/// assert_eq!(quote_ident("user"), r#""user""#);
+/// assert_eq!(quote_ident(r#"My "Table""#), r#""My ""Table""""#);
+/// ```
+pub fn quote_ident(ident: &str) -> String {
+    if needs_quoting(ident) {
+        format!("\"{}\"", ident.replace('"', "\"\""))
+    } else {
+        ident.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, scan};
+
+    #[test]
+    fn it_matches_the_scanner() {
+        assert!(keywords().windows(2).all(|w| w[0].name < w[1].name));
+        for keyword in keywords() {
+            let result = scan(keyword.name).unwrap();
+            assert_eq!(result.tokens.len(), 1, "{}", keyword.name);
+            assert_eq!(
+                result.tokens[0].keyword_kind(),
+                keyword.kind,
+                "{}",
+                keyword.name
+            );
+        }
+        assert_eq!(
+            keyword("BETWEEN").unwrap().kind,
+            KeywordKind::ColNameKeyword
+        );
+        assert!(!keyword("as").unwrap().bare_label);
+        assert!(keyword("abort").unwrap().bare_label);
+    }
+
+    #[test]
+    fn it_quotes_identifiers_like_postgres() {
+        assert_eq!(quote_ident(""), r#""""#);
+        assert_eq!(quote_ident("_x1"), "_x1");
+        assert_eq!(quote_ident("int"), r#""int""#);
+        assert_eq!(quote_ident("left"), r#""left""#);
+        assert_eq!(quote_ident("naïve"), r#""naïve""#);
+
+        // Every quoted keyword can be used as a column name
+        for keyword in keywords() {
+            let query = format!("SELECT {} FROM t", quote_ident(keyword.name));
+            assert!(parse(&query).is_ok(), "{query}");
+        }
+    }
+}
@@ -1,29 +1,64 @@
+// Allows the macros to refer to this crate as `::pg_parse` when used within it
+extern crate self as pg_parse;
+
+pub mod ast;
+pub mod builder;
+pub mod lint;
+
+mod classify;
+mod convert;
+mod cursor;
 mod deparse;
+mod diff;
 mod error;
 mod fingerprint;
 mod iter_mut;
 mod iter_ref;
+#[cfg(feature = "serde")]
+mod json;
+mod keywords;
+mod locks;
+mod name;
 mod node_enum;
 mod node_mut;
 mod node_ref;
 mod node_structs;
 mod normalize;
 mod parse;
+mod reflect;
 mod scan;
 mod split;
+mod sql;
+mod structural;
+mod traversal;
+mod validate;
+mod walk_mut;
 
+pub use classify::*;
+pub use cursor::*;
 pub use deparse::*;
+pub use diff::*;
 pub use error::*;
 pub use fingerprint::*;
 pub use iter_mut::*;
 pub use iter_ref::*;
+pub use keywords::*;
+pub use locks::*;
+pub use name::*;
 pub use node_enum::*;
 pub use node_mut::*;
 pub use node_ref::*;
+pub use node_structs::*;
 pub use normalize::*;
 pub use parse::*;
+pub use reflect::*;
 pub use scan::*;
 pub use split::*;
+pub use sql::*;
+pub use structural::*;
+pub use traversal::*;
+pub use validate::*;
+pub use walk_mut::*;
 
 pub use protobuf::Node;
 
@@ -0,0 +1,509 @@
+//! A rule engine for checking statements, with built-in rules for schema migrations and
+//! application queries.
+//!
+//! A [`Linter`] runs a set of [`Rule`]s over each statement of a query and collects
+//! [`Diagnostic`]s with the source span of the offending node, and for some problems a fixed
+//! version of the statement. Rules are configured by name:
+//! they can be disabled or given another [`Severity`]. A statement can opt out of all rules, or
+//! of some of them, with a `-- lint: ignore` comment placed before the statement, within it, or
+//! after it on the same line:
+//!
+//! ```sql
+//! -- lint: ignore create-index-without-concurrently, drop-without-if-exists
+//! CREATE INDEX users_email_idx ON users (email);
+//! ```
+//!
+//! # Example
+//!
+//! ```rust
+//! use pg_parse::lint::{Linter, Severity};
+//!
+//! let linter = Linter::migration()
+//!     .disable("renaming-column")
+//!     .severity("create-index-without-concurrently", Severity::Error);
+//!
+//! let diagnostics = linter
+//!     .lint(
+//!         "ALTER TABLE users RENAME COLUMN name TO full_name;
+//!          CREATE INDEX users_email_idx ON users (email);",
+//!     )
+//!     .unwrap();
+//!
+//! assert_eq!(diagnostics.len(), 1);
+//! assert_eq!(diagnostics[0].rule, "create-index-without-concurrently");
+//! assert_eq!(diagnostics[0].severity, Severity::Error);
+//! assert_eq!(diagnostics[0].statement, 1);
+//! ```
+use std::fmt;
+use std::ops::Range;
+
+use crate::protobuf::Token;
+use crate::{parse, scan, NodeEnum, NodeRef, Result, Span};
+
+pub mod migration;
+pub mod query;
+
+/// How serious a problem reported by a rule is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A problem found by a [`Rule`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The name of the rule that reported the problem
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    /// The index of the statement in the linted query
+    pub statement: usize,
+    /// Where the problem is in the linted query
+    pub span: Span,
+    /// The statement rewritten to fix the problem, if the rule knows how to fix it
+    pub fix: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] at {}: {}",
+            self.severity, self.rule, self.span.start, self.message
+        )
+    }
+}
+
+/// A check that is run on each statement.
+///
+/// Rules inspect the statement with the iterator API of [`NodeRef`], e.g.
+/// [`iter`](NodeRef::iter) or [`descendants`](NodeRef::descendants), and report problems to
+/// the [`LintContext`]. A rule can offer a fix by rewriting the statement in
+/// [`LintContext::report_with_fix`].
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::lint::{LintContext, Linter, Rule};
+/// use pg_parse::NodeRef;
+///
+/// /// Reports tables that are created without a primary key
+/// struct RequirePrimaryKey;
+///
+/// impl Rule for RequirePrimaryKey {
+///     fn name(&self) -> &'static str {
+///         "require-primary-key"
+///     }
+///
+///     fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+///         if let NodeRef::CreateStmt(create) = stmt {
+///             let has_primary_key = stmt.iter().any(|n| {
+///                 matches!(n, NodeRef::Constraint(c) if c.contype() == pg_parse::ast::ConstrType::ConstrPrimary)
+///             });
+///             if !has_primary_key {
+///                 ctx.report(stmt, format!("{} has no primary key", create.relation.as_ref().unwrap().relname));
+///             }
+///         }
+///     }
+/// }
+///
+/// let linter = Linter::new().with_rule(RequirePrimaryKey);
+/// let diagnostics = linter.lint("CREATE TABLE a (id int PRIMARY KEY); CREATE TABLE b (id int)").unwrap();
+/// assert_eq!(diagnostics.len(), 1);
+/// assert_eq!(diagnostics[0].message, "b has no primary key");
+/// ```
+pub trait Rule {
+    /// A unique name in kebab case, used to configure the rule and in ignore comments.
+    fn name(&self) -> &'static str;
+
+    /// The severity of the rule's diagnostics unless configured otherwise.
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Checks a statement and reports problems to `ctx`.
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>);
+}
+
+/// Collects the problems a [`Rule`] finds in a statement.
+#[derive(Debug)]
+pub struct LintContext<'a> {
+    statement: NodeRef<'a>,
+    statement_span: Span,
+    problems: Vec<(Span, String, Option<String>)>,
+}
+
+impl LintContext<'_> {
+    /// Reports a problem at `node`.
+    ///
+    /// The diagnostic spans the node, or the whole statement if neither the node nor its
+    /// descendants have a location.
+    pub fn report(&mut self, node: NodeRef<'_>, message: impl Into<String>) {
+        let span = Span::of(node).unwrap_or(self.statement_span);
+        self.problems.push((span, message.into(), None));
+    }
+
+    /// Reports a problem at `node` that `fix` knows how to fix.
+    ///
+    /// `fix` rewrites a copy of the statement, e.g. with [`NodeEnum::walk_mut`], and the
+    /// deparsed result becomes the [`fix`](Diagnostic::fix) of the diagnostic. Each fix is
+    /// applied to the original statement, so fixes of different problems are independent of each
+    /// other. The diagnostic has no fix if the rewritten statement cannot be deparsed.
+    pub fn report_with_fix(
+        &mut self,
+        node: NodeRef<'_>,
+        message: impl Into<String>,
+        fix: impl FnOnce(&mut NodeEnum),
+    ) {
+        let mut statement = self.statement.to_enum();
+        fix(&mut statement);
+        let span = Span::of(node).unwrap_or(self.statement_span);
+        self.problems
+            .push((span, message.into(), statement.deparse().ok()));
+    }
+}
+
+/// Runs a configurable set of rules over queries.
+#[derive(Default)]
+pub struct Linter {
+    rules: Vec<ConfiguredRule>,
+}
+
+struct ConfiguredRule {
+    rule: Box<dyn Rule>,
+    /// The configured severity, or None if the rule is disabled
+    severity: Option<Severity>,
+}
+
+impl Linter {
+    /// Creates a linter without any rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a linter with the built-in rules for schema migrations, see [`migration`].
+    pub fn migration() -> Self {
+        migration::rules()
+            .into_iter()
+            .fold(Self::new(), |linter, rule| linter.with_boxed_rule(rule))
+    }
+
+    /// Creates a linter with the built-in rules for application queries, see [`query`].
+    pub fn query() -> Self {
+        query::rules()
+            .into_iter()
+            .fold(Self::new(), |linter, rule| linter.with_boxed_rule(rule))
+    }
+
+    /// Adds a rule, which replaces an existing rule of the same name.
+    pub fn with_rule(self, rule: impl Rule + 'static) -> Self {
+        self.with_boxed_rule(Box::new(rule))
+    }
+
+    fn with_boxed_rule(mut self, rule: Box<dyn Rule>) -> Self {
+        self.rules.retain(|r| r.rule.name() != rule.name());
+        let severity = Some(rule.default_severity());
+        self.rules.push(ConfiguredRule { rule, severity });
+        self
+    }
+
+    /// Disables the rule with the given name.
+    pub fn disable(mut self, name: &str) -> Self {
+        self.configure(name, None);
+        self
+    }
+
+    /// Enables the rule with the given name and reports its diagnostics with `severity`.
+    pub fn severity(mut self, name: &str, severity: Severity) -> Self {
+        self.configure(name, Some(severity));
+        self
+    }
+
+    fn configure(&mut self, name: &str, severity: Option<Severity>) {
+        for rule in self.rules.iter_mut().filter(|r| r.rule.name() == name) {
+            rule.severity = severity;
+        }
+    }
+
+    /// Returns the names of the enabled rules.
+    pub fn rules(&self) -> Vec<&'static str> {
+        self.rules
+            .iter()
+            .filter(|r| r.severity.is_some())
+            .map(|r| r.rule.name())
+            .collect()
+    }
+
+    /// Parses `sql` and checks each statement with the enabled rules.
+    ///
+    /// Returns an error if the query cannot be parsed.
+    pub fn lint(&self, sql: &str) -> Result<Vec<Diagnostic>> {
+        let result = parse(sql)?;
+        let ranges: Vec<_> = result
+            .protobuf
+            .stmts
+            .iter()
+            .map(|s| {
+                let start = s.stmt_location as usize;
+                match s.stmt_len {
+                    0 => start..sql.len(),
+                    len => start..start + len as usize,
+                }
+            })
+            .collect();
+        let ignored = ignored_rules(sql, &ranges)?;
+
+        let mut diagnostics = Vec::new();
+        for (index, raw_stmt) in result.protobuf.stmts.iter().enumerate() {
+            let Some(stmt) = raw_stmt.stmt.as_ref().and_then(|s| s.node.as_ref()) else {
+                continue;
+            };
+            let statement_span = Span::of(stmt.to_ref()).unwrap_or(Span {
+                start: ranges[index].start,
+                last: ranges[index].start,
+            });
+
+            for configured in &self.rules {
+                let Some(severity) = configured.severity else {
+                    continue;
+                };
+                let rule = &configured.rule;
+                if let Some(ignored) = &ignored[index] {
+                    if ignored.is_empty() || ignored.contains(&rule.name()) {
+                        continue;
+                    }
+                }
+
+                let mut ctx = LintContext {
+                    statement: stmt.to_ref(),
+                    statement_span,
+                    problems: Vec::new(),
+                };
+                rule.check(stmt.to_ref(), &mut ctx);
+                diagnostics.extend(ctx.problems.into_iter().map(|(span, message, fix)| {
+                    Diagnostic {
+                        rule: rule.name(),
+                        severity,
+                        message,
+                        statement: index,
+                        span,
+                        fix,
+                    }
+                }));
+            }
+        }
+        Ok(diagnostics)
+    }
+}
+
+/// Returns the rules that each statement ignores with `lint: ignore` comments, or None for
+/// statements without such comments. An empty list means that all rules are ignored.
+///
+/// Comments belong to the statement whose text contains them, except for comments that follow
+/// the end of a statement on the same line, which belong to that statement.
+fn ignored_rules<'a>(sql: &'a str, ranges: &[Range<usize>]) -> Result<Vec<Option<Vec<&'a str>>>> {
+    let mut ignored: Vec<Option<Vec<&str>>> = vec![None; ranges.len()];
+    let comments = scan(sql)?
+        .tokens
+        .into_iter()
+        .filter(|t| matches!(t.token(), Token::SqlComment | Token::CComment));
+
+    for token in comments {
+        let (start, end) = (token.start as usize, token.end as usize);
+        let index = match ranges.iter().position(|r| r.contains(&start)) {
+            // A statement's text starts right after the previous semicolon, so a comment that
+            // only follows whitespace on that line comes after the previous statement
+            Some(index) if index > 0 && follows_end(&sql[ranges[index].start..start]) => index - 1,
+            Some(index) => index,
+            // Past the end of the last statement, e.g. after its semicolon
+            None => match ranges.last() {
+                Some(last) if start >= last.end && follows_end(&sql[last.end..start]) => {
+                    ranges.len() - 1
+                }
+                _ => continue,
+            },
+        };
+
+        let text = sql[start..end]
+            .trim_start_matches("--")
+            .trim_start_matches("/*")
+            .trim_end_matches("*/")
+            .trim();
+        let Some(rules) = text
+            .strip_prefix("lint:")
+            .map(str::trim_start)
+            .and_then(|t| t.strip_prefix("ignore"))
+        else {
+            continue;
+        };
+        let rules: Vec<_> = rules
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|r| !r.is_empty())
+            .collect();
+
+        ignored[index] = match ignored[index].take() {
+            Some(previous) if previous.is_empty() || rules.is_empty() => Some(Vec::new()),
+            Some(previous) => Some(previous.into_iter().chain(rules).collect()),
+            None => Some(rules),
+        };
+    }
+    Ok(ignored)
+}
+
+/// Returns whether the text between the end of a statement and a comment places the comment on
+/// the same line as the end of the statement
+fn follows_end(between: &str) -> bool {
+    !between.contains('\n') && between.trim_start_matches(';').trim().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EveryStatement;
+
+    impl Rule for EveryStatement {
+        fn name(&self) -> &'static str {
+            "every-statement"
+        }
+
+        fn default_severity(&self) -> Severity {
+            Severity::Info
+        }
+
+        fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+            ctx.report(stmt, "found a statement");
+        }
+    }
+
+    #[test]
+    fn it_reports_spans_and_statements() {
+        let sql = "SELECT 1;\nDROP TABLE t;\n  SELECT a FROM t";
+        let diagnostics = Linter::new().with_rule(EveryStatement).lint(sql).unwrap();
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].span, Span { start: 7, last: 7 });
+        // DROP has no location, so the diagnostic falls back to the start of the statement
+        assert_eq!(diagnostics[1].span.start, 9);
+        assert_eq!(
+            diagnostics[2].span,
+            Span {
+                start: 33,
+                last: 40
+            }
+        );
+        assert_eq!(diagnostics[2].statement, 2);
+        assert_eq!(
+            diagnostics[2].to_string(),
+            "info[every-statement] at 33: found a statement"
+        );
+    }
+
+    #[test]
+    fn it_configures_rules() {
+        let linter = Linter::migration().with_rule(EveryStatement);
+        assert!(linter.rules().contains(&"every-statement"));
+        assert!(linter.rules().contains(&"renaming-column"));
+
+        let linter = linter
+            .disable("every-statement")
+            .severity("renaming-column", Severity::Error);
+        assert!(!linter.rules().contains(&"every-statement"));
+
+        let diagnostics = linter.lint("ALTER TABLE t RENAME a TO b").unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+
+        assert!(matches!(
+            linter.lint("ALTER TABLE"),
+            Err(crate::Error::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn it_honours_ignore_comments() {
+        let linter = Linter::migration().with_rule(EveryStatement);
+        let sql = "
+            -- lint: ignore
+            DROP TABLE a;
+            /* lint: ignore drop-without-if-exists */
+            DROP TABLE b;
+            -- lint: ignore every-statement, drop-without-if-exists
+            DROP TABLE c;
+            DROP TABLE d; -- lint:ignore
+            DROP TABLE e;
+        ";
+        let diagnostics: Vec<_> = linter
+            .lint(sql)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.statement, d.rule))
+            .collect();
+
+        assert_eq!(
+            diagnostics,
+            [
+                (1, "every-statement"),
+                (4, "drop-without-if-exists"),
+                (4, "every-statement"),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_honours_ignore_comments_after_the_last_statement() {
+        let linter = Linter::migration();
+        assert_eq!(linter.lint("DROP TABLE d; -- lint: ignore").unwrap(), []);
+        assert_eq!(
+            linter
+                .lint("DROP TABLE d; /* lint: ignore drop-without-if-exists */")
+                .unwrap(),
+            []
+        );
+        assert_eq!(linter.lint("DROP TABLE d -- lint: ignore").unwrap(), []);
+
+        // A comment on the next line follows the statement rather than annotating it
+        let diagnostics = linter.lint("DROP TABLE d;\n-- lint: ignore\n").unwrap();
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn it_honours_ignore_comments_in_statements_on_one_line() {
+        let linter = Linter::migration().with_rule(EveryStatement);
+        let statements = |sql| -> Vec<_> {
+            linter
+                .lint(sql)
+                .unwrap()
+                .into_iter()
+                .map(|d| d.statement)
+                .collect()
+        };
+
+        assert_eq!(
+            statements("DROP TABLE a; DROP TABLE b -- lint: ignore"),
+            [0, 0]
+        );
+        assert_eq!(
+            statements("DROP TABLE a; DROP TABLE b; -- lint: ignore"),
+            [0, 0]
+        );
+        assert_eq!(
+            statements("DROP TABLE a; -- lint: ignore\nDROP TABLE b"),
+            [1, 1]
+        );
+        assert_eq!(statements("SELECT 1; SELECT /* lint: ignore */ 2"), [0]);
+        assert_eq!(statements("SELECT 1; /* lint: ignore */ SELECT 2"), [1]);
+    }
+}
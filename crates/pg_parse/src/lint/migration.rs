@@ -0,0 +1,292 @@
+//! Built-in rules for schema migrations.
+//!
+//! The rules flag statements that take long-held locks on busy tables, or that break
+//! application code still running against the old schema. They are enabled by
+//! [`Linter::migration`](super::Linter::migration).
+
+use crate::protobuf::{AlterTableCmd, AlterTableType, ColumnDef, ConstrType, ObjectType};
+use crate::{NodeEnum, NodeRef};
+
+use super::{LintContext, Rule};
+
+pub(super) fn rules() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(NotNullColumnWithoutDefault),
+        Box::new(CreateIndexWithoutConcurrently),
+        Box::new(ChangingColumnType),
+        Box::new(ForeignKeyWithoutNotValid),
+        Box::new(RenamingColumn),
+        Box::new(DropWithoutIfExists),
+    ]
+}
+
+/// Returns the subcommands of an `ALTER TABLE` statement
+fn alter_table_cmds(stmt: NodeRef<'_>) -> impl Iterator<Item = &AlterTableCmd> {
+    let cmds = match stmt {
+        NodeRef::AlterTableStmt(s) => s.cmds.as_slice(),
+        _ => &[],
+    };
+    cmds.iter().filter_map(|c| match &c.node {
+        Some(NodeEnum::AlterTableCmd(cmd)) => Some(cmd.as_ref()),
+        _ => None,
+    })
+}
+
+/// Returns the column added or altered by an `ALTER TABLE` subcommand
+fn column_def(cmd: &AlterTableCmd) -> Option<&ColumnDef> {
+    match cmd.def.as_ref().and_then(|d| d.node.as_ref()) {
+        Some(NodeEnum::ColumnDef(c)) => Some(c),
+        _ => None,
+    }
+}
+
+fn constraint_types(column: &ColumnDef) -> Vec<ConstrType> {
+    column
+        .constraints
+        .iter()
+        .filter_map(|c| match &c.node {
+            Some(NodeEnum::Constraint(c)) => Some(c.contype()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `ALTER TABLE ... ADD COLUMN ... NOT NULL` without a default, which fails on tables with rows.
+pub struct NotNullColumnWithoutDefault;
+
+impl Rule for NotNullColumnWithoutDefault {
+    fn name(&self) -> &'static str {
+        "not-null-column-without-default"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        for cmd in alter_table_cmds(stmt) {
+            let Some(column) = column_def(cmd) else {
+                continue;
+            };
+            if cmd.subtype() != AlterTableType::AtAddColumn {
+                continue;
+            }
+            let types = constraint_types(column);
+            let not_null = types.contains(&ConstrType::ConstrNotnull);
+            let filled = types.iter().any(|t| {
+                matches!(
+                    t,
+                    ConstrType::ConstrDefault
+                        | ConstrType::ConstrGenerated
+                        | ConstrType::ConstrIdentity
+                )
+            });
+            if not_null && !filled {
+                ctx.report(
+                    column.to_ref(),
+                    format!(
+                        "adding NOT NULL column {} without a default fails if the table has rows",
+                        column.colname
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// `CREATE INDEX` without `CONCURRENTLY`, which blocks writes to the table while the index is
+/// built.
+pub struct CreateIndexWithoutConcurrently;
+
+impl Rule for CreateIndexWithoutConcurrently {
+    fn name(&self) -> &'static str {
+        "create-index-without-concurrently"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        if let NodeRef::IndexStmt(index) = stmt {
+            if !index.concurrent {
+                ctx.report(
+                    stmt,
+                    "CREATE INDEX without CONCURRENTLY blocks writes to the table while the \
+                     index is built",
+                );
+            }
+        }
+    }
+}
+
+/// `ALTER TABLE ... ALTER COLUMN ... TYPE`, which usually rewrites the table under an
+/// `ACCESS EXCLUSIVE` lock and breaks code that expects the old type.
+pub struct ChangingColumnType;
+
+impl Rule for ChangingColumnType {
+    fn name(&self) -> &'static str {
+        "changing-column-type"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        for cmd in alter_table_cmds(stmt) {
+            if cmd.subtype() != AlterTableType::AtAlterColumnType {
+                continue;
+            }
+            let node = column_def(cmd).map_or(stmt, |c| c.to_ref());
+            ctx.report(
+                node,
+                format!(
+                    "changing the type of column {} may rewrite the table while blocking reads \
+                     and writes",
+                    cmd.name
+                ),
+            );
+        }
+    }
+}
+
+/// A foreign key added to an existing table without `NOT VALID`, which blocks writes to both
+/// tables while all rows are checked.
+pub struct ForeignKeyWithoutNotValid;
+
+impl Rule for ForeignKeyWithoutNotValid {
+    fn name(&self) -> &'static str {
+        "foreign-key-without-not-valid"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        for cmd in alter_table_cmds(stmt) {
+            let constraints = match cmd.def.as_ref().and_then(|d| d.node.as_ref()) {
+                Some(NodeEnum::Constraint(c)) => vec![c.as_ref()],
+                Some(NodeEnum::ColumnDef(c)) => c
+                    .constraints
+                    .iter()
+                    .filter_map(|c| match &c.node {
+                        Some(NodeEnum::Constraint(c)) => Some(c.as_ref()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => continue,
+            };
+            for constraint in constraints {
+                if constraint.contype() == ConstrType::ConstrForeign && !constraint.skip_validation
+                {
+                    ctx.report(
+                        constraint.to_ref(),
+                        "adding a foreign key without NOT VALID blocks writes to both tables \
+                         while existing rows are validated",
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// `ALTER TABLE ... RENAME COLUMN`, which breaks code that uses the old name.
+pub struct RenamingColumn;
+
+impl Rule for RenamingColumn {
+    fn name(&self) -> &'static str {
+        "renaming-column"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        if let NodeRef::RenameStmt(rename) = stmt {
+            if rename.rename_type() == ObjectType::ObjectColumn {
+                ctx.report(
+                    stmt,
+                    format!(
+                        "renaming column {} to {} breaks queries that use the old name",
+                        rename.subname, rename.newname
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// `DROP` without `IF EXISTS`, which makes the migration fail if it is run again.
+pub struct DropWithoutIfExists;
+
+impl Rule for DropWithoutIfExists {
+    fn name(&self) -> &'static str {
+        "drop-without-if-exists"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        if let NodeRef::DropStmt(drop) = stmt {
+            if !drop.missing_ok {
+                ctx.report(
+                    stmt,
+                    "DROP without IF EXISTS fails if the object does not exist",
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::Linter;
+
+    fn rules(sql: &str) -> Vec<&'static str> {
+        Linter::migration()
+            .lint(sql)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.rule)
+            .collect()
+    }
+
+    #[test]
+    fn it_flags_unsafe_migrations() {
+        assert_eq!(
+            rules("ALTER TABLE t ADD COLUMN a int NOT NULL"),
+            ["not-null-column-without-default"]
+        );
+        assert_eq!(
+            rules("CREATE INDEX i ON t (a)"),
+            ["create-index-without-concurrently"]
+        );
+        assert_eq!(
+            rules("ALTER TABLE t ALTER COLUMN a TYPE bigint"),
+            ["changing-column-type"]
+        );
+        assert_eq!(
+            rules("ALTER TABLE t ADD CONSTRAINT fk FOREIGN KEY (a) REFERENCES u (id)"),
+            ["foreign-key-without-not-valid"]
+        );
+        assert_eq!(
+            rules("ALTER TABLE t ADD COLUMN u_id int REFERENCES u"),
+            ["foreign-key-without-not-valid"]
+        );
+        assert_eq!(
+            rules("ALTER TABLE t RENAME COLUMN a TO b"),
+            ["renaming-column"]
+        );
+        assert_eq!(rules("DROP INDEX i"), ["drop-without-if-exists"]);
+    }
+
+    #[test]
+    fn it_accepts_safe_migrations() {
+        for sql in [
+            "ALTER TABLE t ADD COLUMN a int NOT NULL DEFAULT 0",
+            "ALTER TABLE t ADD COLUMN a int GENERATED ALWAYS AS IDENTITY",
+            "ALTER TABLE t ADD COLUMN a int",
+            "CREATE INDEX CONCURRENTLY i ON t (a)",
+            "ALTER TABLE t ADD CONSTRAINT fk FOREIGN KEY (a) REFERENCES u (id) NOT VALID",
+            "ALTER TABLE t VALIDATE CONSTRAINT fk",
+            "CREATE TABLE t (a int NOT NULL, u_id int REFERENCES u)",
+            "ALTER TABLE t RENAME TO s",
+            "DROP TABLE IF EXISTS t",
+        ] {
+            assert_eq!(rules(sql), Vec::<&str>::new(), "{sql}");
+        }
+    }
+
+    #[test]
+    fn it_points_at_the_offending_node() {
+        let sql = "ALTER TABLE t ADD COLUMN a int, ADD COLUMN b int NOT NULL";
+        let diagnostics = Linter::migration().lint(sql).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(&sql[diagnostics[0].span.start..], "b int NOT NULL");
+        assert_eq!(
+            diagnostics[0].message,
+            "adding NOT NULL column b without a default fails if the table has rows"
+        );
+    }
+}
@@ -0,0 +1,626 @@
+//! Built-in rules for application queries.
+//!
+//! The rules flag queries that return wrong results, are likely to be slow on large tables, or
+//! touch more rows than intended. They are enabled by [`Linter::query`](super::Linter::query).
+//! Some of them offer a [fix](super::Diagnostic::fix).
+//!
+//! # Example
+//!
+//! ```rust
+//! use pg_parse::lint::query::LargeOffset;
+//! use pg_parse::lint::Linter;
+//!
+//! let linter = Linter::query().with_rule(LargeOffset { max: 10_000 });
+//! let diagnostics = linter
+//!     .lint("SELECT * FROM users WHERE email = NULL OFFSET 5000")
+//!     .unwrap();
+//!
+//! assert_eq!(diagnostics.len(), 1);
+//! assert_eq!(diagnostics[0].rule, "null-comparison");
+//! assert_eq!(
+//!     diagnostics[0].fix.as_deref(),
+//!     Some("SELECT * FROM users WHERE email IS NULL OFFSET 5000")
+//! );
+//! ```
+
+use crate::protobuf::{
+    a_const, AConst, AExpr, AExprKind, Alias, BoolExprType, ColumnRef, JoinExpr, JoinType, Node,
+    ObjectType, SelectStmt, SubLink, SubLinkType,
+};
+use crate::{builder, AstNode, NamePart, NodeEnum, NodeRef, QualifiedName, Span};
+
+use super::{LintContext, Rule, Severity};
+
+pub(super) fn rules() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(NullComparison),
+        Box::new(NotInSubquery),
+        Box::new(LeadingWildcardLike),
+        Box::new(SelectStarInView),
+        Box::new(LargeOffset::default()),
+        Box::new(OrderByRandom),
+        Box::new(ImplicitCrossJoin),
+        Box::new(UpdateOrDeleteWithoutWhere),
+    ]
+}
+
+/// Returns the statement itself and all `SELECT`s below it
+fn selects(stmt: NodeRef<'_>) -> impl Iterator<Item = &SelectStmt> {
+    stmt.iter().filter_map(SelectStmt::cast)
+}
+
+/// Returns the operator of an expression, without its schema
+fn operator(expr: &AExpr) -> Option<String> {
+    QualifiedName::from_nodes(&expr.name).and_then(|name| name.last().map(str::to_string))
+}
+
+fn constant(node: Option<&Node>) -> Option<&AConst> {
+    match node?.node.as_ref()? {
+        NodeEnum::AConst(c) => Some(c),
+        _ => None,
+    }
+}
+
+/// `= NULL` or `<> NULL`, which is never true. `IS NULL` and `IS NOT NULL` test for nulls.
+pub struct NullComparison;
+
+impl Rule for NullComparison {
+    fn name(&self) -> &'static str {
+        "null-comparison"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        for expr in stmt.iter().filter_map(AExpr::cast) {
+            if expr.kind() != AExprKind::AexprOp {
+                continue;
+            }
+            let is_null = |n: Option<&Node>| constant(n).is_some_and(|c| c.isnull);
+            let (lexpr, rexpr) = (expr.lexpr.as_deref(), expr.rexpr.as_deref());
+            let operand = match (is_null(lexpr), is_null(rexpr)) {
+                (false, true) => lexpr,
+                (true, false) => rexpr,
+                _ => continue,
+            };
+            let Some(operand) = operand.and_then(|n| n.node.clone()) else {
+                continue;
+            };
+            let (test, replacement) = match operator(expr).as_deref() {
+                Some("=") => ("IS NULL", builder::is_null(operand)),
+                Some("<>") => ("IS NOT NULL", builder::is_not_null(operand)),
+                _ => continue,
+            };
+
+            let location = expr.location;
+            ctx.report_with_fix(
+                expr.to_ref(),
+                format!("comparing with NULL is never true, use {test} instead"),
+                |stmt| {
+                    stmt.walk_mut(|node, _| {
+                        if matches!(node, NodeEnum::AExpr(e) if e.location == location) {
+                            *node = replacement.clone();
+                        }
+                    })
+                },
+            );
+        }
+    }
+}
+
+/// `NOT IN (SELECT ...)`, which returns no rows at all if the subquery returns a null.
+///
+/// `<> ALL (SELECT ...)` is the same query and is flagged as well. The rule has no fix:
+/// rewriting the query with `NOT EXISTS` requires knowing which columns the subquery can see.
+pub struct NotInSubquery;
+
+impl Rule for NotInSubquery {
+    fn name(&self) -> &'static str {
+        "not-in-subquery"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        for node in stmt.iter() {
+            let sublink = match node {
+                NodeRef::BoolExpr(b) if b.boolop() == BoolExprType::NotExpr => {
+                    match b.args.first().and_then(|a| a.node.as_ref()) {
+                        Some(NodeEnum::SubLink(s))
+                            if s.sub_link_type() == SubLinkType::AnySublink =>
+                        {
+                            s
+                        }
+                        _ => continue,
+                    }
+                }
+                NodeRef::SubLink(s) if s.sub_link_type() == SubLinkType::AllSublink => s,
+                _ => continue,
+            };
+            // IN has no operator, while = ANY and <> ALL name theirs
+            let operator = QualifiedName::from_nodes(&sublink.oper_name);
+            let operator = operator.as_ref().and_then(|o| o.last()).unwrap_or("=");
+            let negated = match sublink.sub_link_type() {
+                SubLinkType::AnySublink => "=",
+                _ => "<>",
+            };
+            if operator == negated {
+                ctx.report(
+                    node,
+                    "NOT IN with a subquery returns no rows if the subquery returns a NULL, \
+                     use NOT EXISTS instead",
+                );
+            }
+        }
+    }
+}
+
+/// `LIKE` or `ILIKE` with a pattern that starts with a wildcard, which cannot use a B-tree
+/// index and scans the whole table.
+pub struct LeadingWildcardLike;
+
+impl Rule for LeadingWildcardLike {
+    fn name(&self) -> &'static str {
+        "leading-wildcard-like"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        for expr in stmt.iter().filter_map(AExpr::cast) {
+            if !matches!(expr.kind(), AExprKind::AexprLike | AExprKind::AexprIlike) {
+                continue;
+            }
+            let pattern = match constant(expr.rexpr.as_deref()).and_then(|c| c.val.as_ref()) {
+                Some(a_const::Val::Sval(s)) => &s.sval,
+                _ => continue,
+            };
+            if pattern.starts_with(['%', '_']) {
+                ctx.report(
+                    expr.to_ref(),
+                    format!(
+                        "the pattern '{pattern}' starts with a wildcard, so the match cannot \
+                         use an index"
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// `SELECT *` in a view, which freezes the column list when the view is created: columns added
+/// to the table later do not show up in the view.
+pub struct SelectStarInView;
+
+impl Rule for SelectStarInView {
+    fn name(&self) -> &'static str {
+        "select-star-in-view"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        let query = match stmt {
+            NodeRef::ViewStmt(v) => v.query.as_deref(),
+            NodeRef::CreateTableAsStmt(c) if c.objtype() == ObjectType::ObjectMatview => {
+                c.query.as_deref()
+            }
+            _ => return,
+        };
+        let Some(NodeEnum::SelectStmt(select)) = query.and_then(|q| q.node.as_ref()) else {
+            return;
+        };
+
+        // Only the output columns of the view matter, not those of subqueries
+        let mut branches = vec![select.as_ref()];
+        while let Some(select) = branches.pop() {
+            branches.extend(select.larg.as_deref());
+            branches.extend(select.rarg.as_deref());
+            for target in &select.target_list {
+                let Some(NodeEnum::ResTarget(target)) = &target.node else {
+                    continue;
+                };
+                let Some(NodeEnum::ColumnRef(column)) =
+                    target.val.as_ref().and_then(|v| v.node.as_ref())
+                else {
+                    continue;
+                };
+                if matches!(
+                    column.fields.last().and_then(|f| f.node.as_ref()),
+                    Some(NodeEnum::AStar(_))
+                ) {
+                    ctx.report(
+                        column.to_ref(),
+                        "SELECT * in a view is expanded when the view is created, so columns \
+                         added later are missing from the view",
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// `OFFSET` with a large constant, which reads and discards all skipped rows. Keyset
+/// pagination (`WHERE id > $1 ORDER BY id LIMIT n`) avoids that.
+pub struct LargeOffset {
+    /// The largest offset that is not reported
+    pub max: i64,
+}
+
+impl Default for LargeOffset {
+    fn default() -> Self {
+        Self { max: 1000 }
+    }
+}
+
+impl Rule for LargeOffset {
+    fn name(&self) -> &'static str {
+        "large-offset"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        for select in selects(stmt) {
+            let Some(offset) = constant(select.limit_offset.as_deref()) else {
+                continue;
+            };
+            let value = match &offset.val {
+                Some(a_const::Val::Ival(i)) => i64::from(i.ival),
+                // Constants that do not fit into an int4 are stored as floats
+                Some(a_const::Val::Fval(f)) => f.fval.parse().unwrap_or(i64::MAX),
+                _ => continue,
+            };
+            if value > self.max {
+                ctx.report(
+                    offset.to_ref(),
+                    format!(
+                        "OFFSET {value} reads and discards {value} rows, consider keyset \
+                         pagination"
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// `ORDER BY random()`, which sorts the whole result to pick random rows. `TABLESAMPLE` picks
+/// random rows without a sort.
+pub struct OrderByRandom;
+
+impl Rule for OrderByRandom {
+    fn name(&self) -> &'static str {
+        "order-by-random"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        for select in selects(stmt) {
+            for sort in &select.sort_clause {
+                let Some(NodeEnum::SortBy(sort)) = &sort.node else {
+                    continue;
+                };
+                let Some(NodeEnum::FuncCall(call)) =
+                    sort.node.as_ref().and_then(|n| n.node.as_ref())
+                else {
+                    continue;
+                };
+                let name = QualifiedName::from_nodes(&call.funcname);
+                if call.args.is_empty() && name.as_ref().and_then(|n| n.last()) == Some("random") {
+                    ctx.report(
+                        call.to_ref(),
+                        "ORDER BY random() sorts all rows, consider TABLESAMPLE",
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Tables in a comma-separated `FROM` list that no `WHERE` condition joins, so the query
+/// returns every combination of their rows.
+///
+/// Conditions are matched to tables by the qualifiers of their columns, so the rule skips
+/// queries with unqualified columns in the `WHERE` clause. Functions and `LATERAL` subqueries
+/// in the `FROM` list are assumed to be joined on purpose. The fix makes the cross join
+/// explicit with `CROSS JOIN`.
+pub struct ImplicitCrossJoin;
+
+impl Rule for ImplicitCrossJoin {
+    fn name(&self) -> &'static str {
+        "implicit-cross-join"
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        for select in selects(stmt) {
+            let Some(unjoined) = unjoined_item(select) else {
+                continue;
+            };
+            let item = select.from_clause[unjoined].node.as_ref().unwrap();
+            let first = select.from_clause[0]
+                .node
+                .as_ref()
+                .and_then(|n| Span::of(n.to_ref()));
+
+            let mut items = select.from_clause.clone().into_iter();
+            let mut from = items.next().unwrap();
+            for item in items {
+                from = Node {
+                    node: Some(NodeEnum::JoinExpr(Box::new(JoinExpr {
+                        jointype: JoinType::JoinInner as i32,
+                        larg: Some(Box::new(from)),
+                        rarg: Some(Box::new(item)),
+                        ..Default::default()
+                    }))),
+                };
+            }
+
+            ctx.report_with_fix(
+                item.to_ref(),
+                "this FROM item is not joined with the others, so every combination of their \
+                 rows is returned; use CROSS JOIN if that is intended",
+                |stmt| {
+                    stmt.walk_mut(|node, _| {
+                        let NodeEnum::SelectStmt(select) = node else {
+                            return;
+                        };
+                        let span = select
+                            .from_clause
+                            .first()
+                            .and_then(|n| n.node.as_ref())
+                            .and_then(|n| Span::of(n.to_ref()));
+                        if span == first {
+                            select.from_clause = vec![from.clone()];
+                        }
+                    })
+                },
+            );
+        }
+    }
+}
+
+/// Returns the index of the first `FROM` item that is not joined with the first item by the
+/// `WHERE` clause, or None if all items are joined or the joins cannot be determined.
+fn unjoined_item(select: &SelectStmt) -> Option<usize> {
+    if select.from_clause.len() < 2 {
+        return None;
+    }
+    // The names each item can be referred to by, or None for items that are not checked
+    let names: Vec<Option<Vec<&str>>> = select
+        .from_clause
+        .iter()
+        .map(|item| item.node.as_ref().and_then(|n| item_names(n.to_ref())))
+        .collect();
+
+    fn root(parents: &[usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            i = parents[i];
+        }
+        i
+    }
+    // Union-find over the items, where the conditions of the WHERE clause join items
+    let mut parents: Vec<usize> = (0..names.len()).collect();
+
+    let mut conditions = Vec::new();
+    if let Some(condition) = select.where_clause.as_ref().and_then(|w| w.node.as_ref()) {
+        conjuncts(condition, &mut conditions);
+    }
+    for condition in conditions {
+        // Unqualified columns in subqueries usually belong to the tables of the subquery
+        let nested: Vec<*const ColumnRef> = condition
+            .to_ref()
+            .iter()
+            .filter_map(SubLink::cast)
+            .filter_map(|s| s.subselect.as_ref()?.node.as_ref())
+            .flat_map(|s| s.to_ref().iter().filter_map(ColumnRef::cast))
+            .map(|c| c as *const _)
+            .collect();
+
+        let mut joined = Vec::new();
+        for column in condition.to_ref().iter().filter_map(ColumnRef::cast) {
+            let name = QualifiedName::from_nodes(&column.fields)?;
+            let qualifier = match name.0.len().checked_sub(2) {
+                Some(i) => &name.0[i],
+                None if nested.contains(&(column as *const _)) => continue,
+                // Unqualified columns could belong to any of the items
+                None => return None,
+            };
+            let NamePart::Ident(qualifier) = qualifier else {
+                continue;
+            };
+            joined.extend(
+                names
+                    .iter()
+                    .position(|n| n.as_ref().is_some_and(|n| n.contains(&qualifier.as_str()))),
+            );
+        }
+        for pair in joined.windows(2) {
+            let (a, b) = (root(&parents, pair[0]), root(&parents, pair[1]));
+            parents[a] = b;
+        }
+    }
+
+    let first = root(&parents, 0);
+    (1..names.len())
+        .find(|&i| names[0].is_some() && names[i].is_some() && root(&parents, i) != first)
+}
+
+/// Returns the names a `FROM` item can be referred to by, or None for items that can depend on
+/// other items of the `FROM` list
+fn item_names(item: NodeRef<'_>) -> Option<Vec<&str>> {
+    fn alias(alias: &Option<Alias>) -> Option<&str> {
+        alias.as_ref().map(|a| a.aliasname.as_str())
+    }
+    match item {
+        NodeRef::RangeVar(r) => Some(vec![alias(&r.alias).unwrap_or(&r.relname)]),
+        NodeRef::RangeSubselect(s) if !s.lateral => alias(&s.alias).map(|a| vec![a]),
+        NodeRef::JoinExpr(j) => match alias(&j.alias) {
+            Some(a) => Some(vec![a]),
+            None => {
+                let mut names = item_names(j.larg.as_ref()?.node.as_ref()?.to_ref())?;
+                names.extend(item_names(j.rarg.as_ref()?.node.as_ref()?.to_ref())?);
+                Some(names)
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Splits a condition into the operands of its top-level `AND`s
+fn conjuncts<'a>(condition: &'a NodeEnum, conditions: &mut Vec<&'a NodeEnum>) {
+    match condition {
+        NodeEnum::BoolExpr(b) if b.boolop() == BoolExprType::AndExpr => {
+            for arg in b.args.iter().filter_map(|a| a.node.as_ref()) {
+                conjuncts(arg, conditions);
+            }
+        }
+        _ => conditions.push(condition),
+    }
+}
+
+/// `UPDATE` or `DELETE` without a `WHERE` clause, which changes every row of the table.
+pub struct UpdateOrDeleteWithoutWhere;
+
+impl Rule for UpdateOrDeleteWithoutWhere {
+    fn name(&self) -> &'static str {
+        "update-or-delete-without-where"
+    }
+
+    fn default_severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, stmt: NodeRef<'_>, ctx: &mut LintContext<'_>) {
+        for node in stmt.iter() {
+            let (statement, relation) = match node {
+                NodeRef::UpdateStmt(u) if u.where_clause.is_none() => ("UPDATE", &u.relation),
+                NodeRef::DeleteStmt(d) if d.where_clause.is_none() => ("DELETE", &d.relation),
+                _ => continue,
+            };
+            let table = relation
+                .as_ref()
+                .map_or("the table", |r| r.relname.as_str());
+            ctx.report(
+                node,
+                format!("{statement} without WHERE changes every row of {table}"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::{Linter, Severity};
+
+    fn rules(sql: &str) -> Vec<&'static str> {
+        Linter::query()
+            .lint(sql)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.rule)
+            .collect()
+    }
+
+    #[test]
+    fn it_flags_anti_patterns() {
+        assert_eq!(rules("SELECT * FROM t WHERE a = NULL"), ["null-comparison"]);
+        assert_eq!(
+            rules("SELECT * FROM t WHERE a NOT IN (SELECT b FROM u)"),
+            ["not-in-subquery"]
+        );
+        assert_eq!(
+            rules("SELECT * FROM t WHERE a <> ALL (SELECT b FROM u)"),
+            ["not-in-subquery"]
+        );
+        assert_eq!(
+            rules("SELECT * FROM t WHERE name ILIKE '%smith'"),
+            ["leading-wildcard-like"]
+        );
+        assert_eq!(
+            rules("CREATE VIEW v AS SELECT t.*, 1 FROM t"),
+            ["select-star-in-view"]
+        );
+        assert_eq!(
+            rules("CREATE MATERIALIZED VIEW v AS SELECT a FROM t UNION SELECT * FROM u"),
+            ["select-star-in-view"]
+        );
+        assert_eq!(
+            rules("SELECT * FROM t LIMIT 10 OFFSET 50000"),
+            ["large-offset"]
+        );
+        assert_eq!(
+            rules("SELECT * FROM t OFFSET 10000000000"),
+            ["large-offset"]
+        );
+        assert_eq!(
+            rules("SELECT * FROM t ORDER BY random() LIMIT 1"),
+            ["order-by-random"]
+        );
+        assert_eq!(rules("SELECT * FROM a, b"), ["implicit-cross-join"]);
+        assert_eq!(
+            rules("SELECT * FROM a, b x, c WHERE a.id = x.id AND c.v > 0"),
+            ["implicit-cross-join"]
+        );
+        assert_eq!(
+            rules("UPDATE t SET a = 1"),
+            ["update-or-delete-without-where"]
+        );
+        assert_eq!(
+            rules("WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d"),
+            ["update-or-delete-without-where"]
+        );
+    }
+
+    #[test]
+    fn it_accepts_good_queries() {
+        for sql in [
+            "SELECT * FROM t WHERE a IS NULL AND b = 1",
+            "SELECT * FROM t WHERE a NOT IN (1, 2) AND b IN (SELECT b FROM u)",
+            "SELECT * FROM t WHERE NOT EXISTS (SELECT FROM u WHERE u.b = t.a)",
+            "SELECT * FROM t WHERE name LIKE 'smith%'",
+            "CREATE VIEW v AS SELECT a, count(*) FROM t WHERE EXISTS (SELECT * FROM u) GROUP BY a",
+            "SELECT * FROM t LIMIT 10 OFFSET 1000",
+            "SELECT * FROM t ORDER BY random_id",
+            "SELECT * FROM a, b WHERE a.id = b.a_id",
+            "SELECT * FROM a, b, c WHERE a.id = b.id AND b.id = c.id",
+            "SELECT * FROM a, b WHERE id = a_id",
+            "SELECT * FROM a, unnest(a.tags) tag",
+            "SELECT * FROM a CROSS JOIN b",
+            "UPDATE t SET a = 1 WHERE id = 2",
+            "DELETE FROM t WHERE CURRENT OF c",
+        ] {
+            assert_eq!(rules(sql), Vec::<&str>::new(), "{sql}");
+        }
+    }
+
+    #[test]
+    fn it_fixes_null_comparisons() {
+        let sql = "SELECT * FROM t WHERE a = NULL OR NULL <> b";
+        let diagnostics = Linter::query().lint(sql).unwrap();
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(&sql[diagnostics[0].span.start..], "a = NULL OR NULL <> b");
+        assert_eq!(
+            diagnostics[0].fix.as_deref(),
+            Some("SELECT * FROM t WHERE a IS NULL OR NULL <> b")
+        );
+        assert_eq!(
+            diagnostics[1].fix.as_deref(),
+            Some("SELECT * FROM t WHERE a = NULL OR b IS NOT NULL")
+        );
+    }
+
+    #[test]
+    fn it_fixes_implicit_cross_joins() {
+        let sql = "SELECT * FROM a, b WHERE a.id IN (SELECT id FROM c, d)";
+        let diagnostics = Linter::query().lint(sql).unwrap();
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            &sql[diagnostics[0].span.start..],
+            "b WHERE a.id IN (SELECT id FROM c, d)"
+        );
+        assert_eq!(
+            diagnostics[0].fix.as_deref(),
+            Some("SELECT * FROM a CROSS JOIN b WHERE a.id IN (SELECT id FROM c, d)")
+        );
+        assert_eq!(
+            diagnostics[1].fix.as_deref(),
+            Some("SELECT * FROM a, b WHERE a.id IN (SELECT id FROM c CROSS JOIN d)")
+        );
+    }
+}
@@ -0,0 +1,771 @@
+use std::fmt;
+
+use crate::classify::def_elem_is_true;
+use crate::protobuf::{
+    self, AlterTableCmd, AlterTableType, ConstrType, ObjectType, ReindexObjectType,
+};
+use crate::{NodeEnum, NodeRef, NodeRefIterator, QualifiedName, TraversalOrder};
+
+/// A table-level lock mode, ordered like Postgres numbers them from weakest to strongest.
+///
+/// See the [Postgres documentation](https://www.postgresql.org/docs/current/explicit-locking.html#LOCKING-TABLES)
+/// for which modes conflict and what takes them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LockMode {
+    AccessShare = 1,
+    RowShare = 2,
+    RowExclusive = 3,
+    ShareUpdateExclusive = 4,
+    Share = 5,
+    ShareRowExclusive = 6,
+    Exclusive = 7,
+    AccessExclusive = 8,
+}
+
+/// A lock that a statement takes on a relation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LockRequirement {
+    /// The table, index, view or sequence, as named in the statement
+    pub relation: QualifiedName,
+    pub mode: LockMode,
+}
+
+impl LockMode {
+    const ALL: [LockMode; 8] = [
+        LockMode::AccessShare,
+        LockMode::RowShare,
+        LockMode::RowExclusive,
+        LockMode::ShareUpdateExclusive,
+        LockMode::Share,
+        LockMode::ShareRowExclusive,
+        LockMode::Exclusive,
+        LockMode::AccessExclusive,
+    ];
+
+    /// Converts the lock mode number used in `LockStmt.mode`.
+    pub fn from_i32(mode: i32) -> Option<LockMode> {
+        LockMode::ALL.into_iter().find(|m| *m as i32 == mode)
+    }
+
+    /// Returns whether a transaction holding this lock blocks another one from taking `other`
+    /// on the same relation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use pg_parse::LockMode;
+    ///
+    /// assert!(LockMode::Share.conflicts_with(LockMode::RowExclusive));
+    /// assert!(!LockMode::ShareUpdateExclusive.conflicts_with(LockMode::RowExclusive));
+    /// assert!(LockMode::AccessExclusive.conflicts_with(LockMode::AccessShare));
+    /// ```
+    pub fn conflicts_with(self, other: LockMode) -> bool {
+        use LockMode::*;
+
+        let conflicts: &[LockMode] = match self {
+            AccessShare => &[AccessExclusive],
+            RowShare => &[Exclusive, AccessExclusive],
+            RowExclusive => &[Share, ShareRowExclusive, Exclusive, AccessExclusive],
+            ShareUpdateExclusive => &[
+                ShareUpdateExclusive,
+                Share,
+                ShareRowExclusive,
+                Exclusive,
+                AccessExclusive,
+            ],
+            Share => &[
+                RowExclusive,
+                ShareUpdateExclusive,
+                ShareRowExclusive,
+                Exclusive,
+                AccessExclusive,
+            ],
+            ShareRowExclusive => &[
+                RowExclusive,
+                ShareUpdateExclusive,
+                Share,
+                ShareRowExclusive,
+                Exclusive,
+                AccessExclusive,
+            ],
+            Exclusive => &LockMode::ALL[1..],
+            AccessExclusive => &LockMode::ALL,
+        };
+        conflicts.contains(&other)
+    }
+
+    /// Returns the lock an `ALTER TABLE` subcommand takes on the altered table, following
+    /// `AlterTableGetLockLevel` in Postgres.
+    ///
+    /// Subcommands that are not known to take a weaker lock take `ACCESS EXCLUSIVE`. An
+    /// `ALTER TABLE` statement takes the strongest lock of its subcommands.
+    pub fn for_alter_table_cmd(cmd: &AlterTableCmd) -> LockMode {
+        use AlterTableType::*;
+
+        match cmd.subtype() {
+            AtSetStatistics
+            | AtSetOptions
+            | AtResetOptions
+            | AtClusterOn
+            | AtDropCluster
+            | AtValidateConstraint
+            | AtAttachPartition
+            | AtDetachPartitionFinalize => LockMode::ShareUpdateExclusive,
+            AtDetachPartition => match cmd.def.as_ref().and_then(|d| d.node.as_ref()) {
+                Some(NodeEnum::PartitionCmd(p)) if p.concurrent => LockMode::ShareUpdateExclusive,
+                _ => LockMode::AccessExclusive,
+            },
+            AtSetRelOptions | AtResetRelOptions => reloptions_lock(cmd),
+            AtEnableTrig | AtEnableAlwaysTrig | AtEnableReplicaTrig | AtEnableTrigAll
+            | AtEnableTrigUser | AtDisableTrig | AtDisableTrigAll | AtDisableTrigUser => {
+                LockMode::ShareRowExclusive
+            }
+            // Rules may change what SELECT returns, and Postgres does not take a weaker lock
+            // for altering constraints yet either
+            AtEnableRule | AtEnableAlwaysRule | AtEnableReplicaRule | AtDisableRule
+            | AtAlterConstraint => LockMode::AccessExclusive,
+            AtAddConstraint => match cmd.def.as_ref().and_then(|d| d.node.as_ref()) {
+                Some(NodeEnum::Constraint(c)) if c.contype() == ConstrType::ConstrForeign => {
+                    LockMode::ShareRowExclusive
+                }
+                _ => LockMode::AccessExclusive,
+            },
+            _ => LockMode::AccessExclusive,
+        }
+    }
+}
+
+/// Storage parameters that can be set or reset under `SHARE UPDATE EXCLUSIVE`, as declared in
+/// Postgres's `reloptions.c`. The other ones, e.g. `user_catalog_table` or the view options
+/// `security_barrier`, `security_invoker` and `check_option`, take `ACCESS EXCLUSIVE`.
+const SHARE_UPDATE_EXCLUSIVE_RELOPTIONS: &[&str] = &[
+    "autosummarize",
+    "deduplicate_items",
+    "fillfactor",
+    "gin_pending_list_limit",
+    "log_autovacuum_min_duration",
+    "pages_per_range",
+    "parallel_workers",
+    "toast_tuple_target",
+    "vacuum_index_cleanup",
+    "vacuum_truncate",
+];
+
+/// Returns the strongest lock the storage parameters of `ALTER TABLE ... SET (...)` or
+/// `RESET (...)` take, like `AlterTableGetRelOptionsLockLevel`
+fn reloptions_lock(cmd: &AlterTableCmd) -> LockMode {
+    let options = match cmd.def.as_ref().and_then(|d| d.node.as_ref()) {
+        Some(NodeEnum::List(list)) => list.items.as_slice(),
+        _ => return LockMode::AccessExclusive,
+    };
+    let weak = options.iter().all(|option| match &option.node {
+        Some(NodeEnum::DefElem(d)) => {
+            d.defname.starts_with("autovacuum_")
+                || SHARE_UPDATE_EXCLUSIVE_RELOPTIONS.contains(&d.defname.as_str())
+        }
+        _ => false,
+    });
+    if weak && !options.is_empty() {
+        LockMode::ShareUpdateExclusive
+    } else {
+        LockMode::AccessExclusive
+    }
+}
+
+impl fmt::Display for LockMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LockMode::AccessShare => "ACCESS SHARE",
+            LockMode::RowShare => "ROW SHARE",
+            LockMode::RowExclusive => "ROW EXCLUSIVE",
+            LockMode::ShareUpdateExclusive => "SHARE UPDATE EXCLUSIVE",
+            LockMode::Share => "SHARE",
+            LockMode::ShareRowExclusive => "SHARE ROW EXCLUSIVE",
+            LockMode::Exclusive => "EXCLUSIVE",
+            LockMode::AccessExclusive => "ACCESS EXCLUSIVE",
+        })
+    }
+}
+
+/// Returns the relations a statement locks and the lock mode taken on each.
+///
+/// The modes follow Postgres's documented table-level lock rules: queries take `ACCESS SHARE`
+/// on the tables they read, `ROW SHARE` on tables locked with `FOR UPDATE` and similar, and
+/// `ROW EXCLUSIVE` on the tables they modify. DDL takes the lock documented for the statement,
+/// and `ALTER TABLE` the strongest lock of its subcommands, see
+/// [`LockMode::for_alter_table_cmd`]. Tables referenced by new foreign keys are locked with
+/// `SHARE ROW EXCLUSIVE`.
+///
+/// Each relation is listed once, with the strongest mode taken on it. Relations are named as
+/// in the statement, so tables that are reached through views, triggers or an index, e.g. for
+/// `DROP INDEX`, are not included. Statements that take no table locks, or only lock objects
+/// other than relations, return an empty list.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::{lock_requirements, parse, LockMode, LockRequirement, QualifiedName};
+///
+/// let result = parse(
+///     "ALTER TABLE orders ADD COLUMN id uuid DEFAULT gen_random_uuid();
+///      CREATE INDEX CONCURRENTLY ON orders (customer_id);
+///      UPDATE orders SET total = 0 FROM customers c WHERE c.id = customer_id",
+/// )
+/// .unwrap();
+/// let locks: Vec<_> = result.stmts().into_iter().map(lock_requirements).collect();
+///
+/// assert_eq!(locks[0][0].mode, LockMode::AccessExclusive);
+/// assert_eq!(locks[1][0].mode, LockMode::ShareUpdateExclusive);
+/// assert_eq!(
+///     locks[2],
+///     [
+///         LockRequirement {
+///             relation: QualifiedName::new(["orders"]),
+///             mode: LockMode::RowExclusive,
+///         },
+///         LockRequirement {
+///             relation: QualifiedName::new(["customers"]),
+///             mode: LockMode::AccessShare,
+///         },
+///     ]
+/// );
+/// ```
+pub fn lock_requirements(stmt: &NodeEnum) -> Vec<LockRequirement> {
+    let mut locks = Locks(Vec::new());
+    locks.statement(stmt.to_ref());
+    locks.0
+}
+
+struct Locks(Vec<LockRequirement>);
+
+impl Locks {
+    fn add(&mut self, relation: QualifiedName, mode: LockMode) {
+        match self.0.iter_mut().find(|l| l.relation == relation) {
+            Some(lock) => lock.mode = lock.mode.max(mode),
+            None => self.0.push(LockRequirement { relation, mode }),
+        }
+    }
+
+    fn range_var(&mut self, relation: Option<&protobuf::RangeVar>, mode: LockMode) {
+        if let Some(relation) = relation {
+            self.add(range_var_name(relation), mode);
+        }
+    }
+
+    fn range_vars(&mut self, relations: &[protobuf::Node], mode: LockMode) {
+        for relation in relations {
+            if let Some(NodeEnum::RangeVar(r)) = &relation.node {
+                self.range_var(Some(r), mode);
+            }
+        }
+    }
+
+    fn statement(&mut self, stmt: NodeRef<'_>) {
+        match stmt {
+            NodeRef::SelectStmt(_)
+            | NodeRef::InsertStmt(_)
+            | NodeRef::UpdateStmt(_)
+            | NodeRef::DeleteStmt(_)
+            | NodeRef::MergeStmt(_) => self.query(stmt),
+            NodeRef::ExplainStmt(s) => self.child_statement(&s.query),
+            NodeRef::DeclareCursorStmt(s) => self.child_statement(&s.query),
+            NodeRef::PrepareStmt(s) => self.child_statement(&s.query),
+            NodeRef::CreateTableAsStmt(s) => self.child_statement(&s.query),
+            NodeRef::ViewStmt(s) => {
+                if s.replace {
+                    self.range_var(s.view.as_ref(), LockMode::AccessExclusive);
+                }
+                self.child_statement(&s.query);
+            }
+            NodeRef::CopyStmt(s) => {
+                let mode = if s.is_from {
+                    LockMode::RowExclusive
+                } else {
+                    LockMode::AccessShare
+                };
+                self.range_var(s.relation.as_ref(), mode);
+                self.child_statement(&s.query);
+            }
+            NodeRef::TruncateStmt(s) => self.range_vars(&s.relations, LockMode::AccessExclusive),
+            NodeRef::LockStmt(s) => {
+                let mode = LockMode::from_i32(s.mode).unwrap_or(LockMode::AccessExclusive);
+                self.range_vars(&s.relations, mode);
+            }
+            NodeRef::IndexStmt(s) => {
+                let mode = if s.concurrent {
+                    LockMode::ShareUpdateExclusive
+                } else {
+                    LockMode::Share
+                };
+                self.range_var(s.relation.as_ref(), mode);
+            }
+            NodeRef::ReindexStmt(s) => {
+                let concurrently = has_option(&s.params, "concurrently");
+                let mode = match s.kind() {
+                    _ if concurrently => LockMode::ShareUpdateExclusive,
+                    ReindexObjectType::ReindexObjectIndex => LockMode::AccessExclusive,
+                    _ => LockMode::Share,
+                };
+                self.range_var(s.relation.as_ref(), mode);
+            }
+            NodeRef::VacuumStmt(s) => {
+                let mode = if s.is_vacuumcmd && has_option(&s.options, "full") {
+                    LockMode::AccessExclusive
+                } else {
+                    LockMode::ShareUpdateExclusive
+                };
+                for rel in &s.rels {
+                    if let Some(NodeEnum::VacuumRelation(v)) = &rel.node {
+                        self.range_var(v.relation.as_ref(), mode);
+                    }
+                }
+            }
+            NodeRef::ClusterStmt(s) => {
+                self.range_var(s.relation.as_ref(), LockMode::AccessExclusive)
+            }
+            NodeRef::RefreshMatViewStmt(s) => {
+                let mode = if s.concurrent {
+                    LockMode::Exclusive
+                } else {
+                    LockMode::AccessExclusive
+                };
+                self.range_var(s.relation.as_ref(), mode);
+            }
+            NodeRef::CreateStmt(s) => {
+                let mode = if s.partbound.is_some() {
+                    LockMode::AccessExclusive
+                } else {
+                    LockMode::ShareUpdateExclusive
+                };
+                self.range_vars(&s.inh_relations, mode);
+                self.foreign_keys(stmt);
+            }
+            NodeRef::AlterTableStmt(s) => {
+                let relation = s.relation.as_ref().map(range_var_name);
+                for cmd in &s.cmds {
+                    let Some(NodeEnum::AlterTableCmd(cmd)) = &cmd.node else {
+                        continue;
+                    };
+                    let mode = LockMode::for_alter_table_cmd(cmd);
+                    if let Some(relation) = &relation {
+                        self.add(relation.clone(), mode);
+                    }
+                    if let Some(NodeEnum::PartitionCmd(p)) =
+                        cmd.def.as_ref().and_then(|d| d.node.as_ref())
+                    {
+                        // The partition is locked like the parent when detaching, and
+                        // exclusively when attaching
+                        let mode = match cmd.subtype() {
+                            AlterTableType::AtAttachPartition => LockMode::AccessExclusive,
+                            _ => mode,
+                        };
+                        self.range_var(p.name.as_ref(), mode);
+                    }
+                }
+                self.foreign_keys(stmt);
+            }
+            NodeRef::RenameStmt(s) => {
+                let mode = match s.rename_type() {
+                    ObjectType::ObjectIndex => LockMode::ShareUpdateExclusive,
+                    _ => LockMode::AccessExclusive,
+                };
+                self.range_var(s.relation.as_ref(), mode);
+            }
+            NodeRef::AlterObjectSchemaStmt(s) => {
+                self.range_var(s.relation.as_ref(), LockMode::AccessExclusive)
+            }
+            NodeRef::DropStmt(s) => {
+                let mode = if s.concurrent {
+                    LockMode::ShareUpdateExclusive
+                } else {
+                    LockMode::AccessExclusive
+                };
+                for object in &s.objects {
+                    let Some(name) = object
+                        .node
+                        .as_ref()
+                        .and_then(|n| QualifiedName::from_node(n.to_ref()))
+                    else {
+                        continue;
+                    };
+                    match s.remove_type() {
+                        ObjectType::ObjectTable
+                        | ObjectType::ObjectView
+                        | ObjectType::ObjectMatview
+                        | ObjectType::ObjectSequence
+                        | ObjectType::ObjectForeignTable
+                        | ObjectType::ObjectIndex => self.add(name, mode),
+                        // The object is named by its table followed by its own name
+                        ObjectType::ObjectTrigger
+                        | ObjectType::ObjectRule
+                        | ObjectType::ObjectPolicy => {
+                            if let Some(table) = parent_name(name) {
+                                self.add(table, LockMode::AccessExclusive);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            NodeRef::CommentStmt(s) => {
+                let Some(name) = s
+                    .object
+                    .as_ref()
+                    .and_then(|o| o.node.as_ref())
+                    .and_then(|n| QualifiedName::from_node(n.to_ref()))
+                else {
+                    return;
+                };
+                let relation = match s.objtype() {
+                    ObjectType::ObjectTable
+                    | ObjectType::ObjectView
+                    | ObjectType::ObjectMatview
+                    | ObjectType::ObjectSequence
+                    | ObjectType::ObjectForeignTable
+                    | ObjectType::ObjectIndex => Some(name),
+                    ObjectType::ObjectColumn => parent_name(name),
+                    _ => None,
+                };
+                if let Some(relation) = relation {
+                    self.add(relation, LockMode::ShareUpdateExclusive);
+                }
+            }
+            NodeRef::CreateTrigStmt(s) => {
+                self.range_var(s.relation.as_ref(), LockMode::ShareRowExclusive)
+            }
+            NodeRef::CreateStatsStmt(s) => {
+                self.range_vars(&s.relations, LockMode::ShareUpdateExclusive)
+            }
+            NodeRef::CreatePolicyStmt(s) => {
+                self.range_var(s.table.as_ref(), LockMode::AccessExclusive)
+            }
+            NodeRef::AlterPolicyStmt(s) => {
+                self.range_var(s.table.as_ref(), LockMode::AccessExclusive)
+            }
+            NodeRef::RuleStmt(s) => self.range_var(s.relation.as_ref(), LockMode::AccessExclusive),
+            _ => {}
+        }
+    }
+
+    fn child_statement(&mut self, stmt: &Option<Box<protobuf::Node>>) {
+        if let Some(stmt) = stmt.as_ref().and_then(|s| s.node.as_ref()) {
+            self.statement(stmt.to_ref());
+        }
+    }
+
+    /// Locks the tables a query reads, locks with a locking clause and modifies, including in
+    /// subqueries and CTEs
+    fn query(&mut self, query: NodeRef<'_>) {
+        let nodes: Vec<_> =
+            NodeRefIterator::with_order(query, TraversalOrder::SourceOrder).collect();
+        let ctes: Vec<_> = nodes
+            .iter()
+            .filter_map(|n| match n {
+                NodeRef::CommonTableExpr(cte) => Some(cte.ctename.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        // Tables listed in `FOR UPDATE OF` are handled with the FROM clause they refer to
+        let locked: Vec<_> = nodes
+            .iter()
+            .filter_map(|n| match n {
+                NodeRef::LockingClause(c) => Some(&c.locked_rels),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|n| match &n.node {
+                Some(NodeEnum::RangeVar(r)) => Some(r as *const protobuf::RangeVar),
+                _ => None,
+            })
+            .collect();
+
+        // Tables are listed in the order they appear, so stronger locks are added afterwards
+        for node in &nodes {
+            match node {
+                NodeRef::RangeVar(r)
+                    if locked.contains(&(*r as *const _))
+                        || r.schemaname.is_empty() && ctes.contains(&r.relname.as_str()) => {}
+                NodeRef::RangeVar(r) => self.range_var(Some(r), LockMode::AccessShare),
+                _ => {}
+            }
+        }
+        for node in &nodes {
+            match node {
+                NodeRef::InsertStmt(s) => {
+                    self.range_var(s.relation.as_ref(), LockMode::RowExclusive)
+                }
+                NodeRef::UpdateStmt(s) => {
+                    self.range_var(s.relation.as_ref(), LockMode::RowExclusive)
+                }
+                NodeRef::DeleteStmt(s) => {
+                    self.range_var(s.relation.as_ref(), LockMode::RowExclusive)
+                }
+                NodeRef::MergeStmt(s) => {
+                    self.range_var(s.relation.as_ref(), LockMode::RowExclusive)
+                }
+                NodeRef::SelectStmt(s) => {
+                    for clause in &s.locking_clause {
+                        if let Some(NodeEnum::LockingClause(c)) = &clause.node {
+                            self.locking_clause(&s.from_clause, &c.locked_rels, &ctes);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Locks the tables in `FROM` that a locking clause applies to, either the ones listed
+    /// after `OF`, which may refer to them by alias, or all of them
+    fn locking_clause(
+        &mut self,
+        from: &[protobuf::Node],
+        locked: &[protobuf::Node],
+        ctes: &[&str],
+    ) {
+        let locked: Vec<_> = locked
+            .iter()
+            .filter_map(|n| match &n.node {
+                Some(NodeEnum::RangeVar(r)) => Some(r.relname.as_str()),
+                _ => None,
+            })
+            .collect();
+        for node in from {
+            for node in node.node.iter().flat_map(|n| n.nodes()) {
+                let NodeRef::RangeVar(r) = node else {
+                    continue;
+                };
+                let name = r
+                    .alias
+                    .as_ref()
+                    .map_or(r.relname.as_str(), |a| a.aliasname.as_str());
+                let is_cte = r.schemaname.is_empty() && ctes.contains(&r.relname.as_str());
+                if !is_cte && (locked.is_empty() || locked.contains(&name)) {
+                    self.range_var(Some(r), LockMode::RowShare);
+                }
+            }
+        }
+    }
+
+    /// Locks the tables referenced by foreign keys that a statement adds
+    fn foreign_keys(&mut self, stmt: NodeRef<'_>) {
+        for node in NodeRefIterator::with_order(stmt, TraversalOrder::SourceOrder) {
+            if let NodeRef::Constraint(c) = node {
+                if c.contype() == ConstrType::ConstrForeign {
+                    self.range_var(c.pktable.as_ref(), LockMode::ShareRowExclusive);
+                }
+            }
+        }
+    }
+}
+
+fn range_var_name(relation: &protobuf::RangeVar) -> QualifiedName {
+    QualifiedName::from_node(NodeRef::RangeVar(relation)).unwrap_or_default()
+}
+
+/// Returns the name without its last part, e.g. the table of a column
+fn parent_name(mut name: QualifiedName) -> Option<QualifiedName> {
+    name.0.pop();
+    (!name.0.is_empty()).then_some(name)
+}
+
+/// Returns whether a boolean option like `CONCURRENTLY` in `REINDEX (CONCURRENTLY)` is set
+fn has_option(options: &[protobuf::Node], name: &str) -> bool {
+    options.iter().any(|o| match &o.node {
+        Some(NodeEnum::DefElem(d)) => d.defname == name && def_elem_is_true(d),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn locks(query: &str) -> Vec<(String, LockMode)> {
+        let result = parse(query).unwrap();
+        lock_requirements(result.stmts()[0])
+            .into_iter()
+            .map(|l| (l.relation.to_string(), l.mode))
+            .collect()
+    }
+
+    fn lock(query: &str) -> LockMode {
+        let locks = locks(query);
+        assert_eq!(locks.len(), 1, "{query}: {locks:?}");
+        locks[0].1
+    }
+
+    #[test]
+    fn it_infers_locks_for_queries() {
+        use LockMode::*;
+
+        assert_eq!(
+            locks("SELECT * FROM a JOIN s.b ON true WHERE x IN (SELECT x FROM c)"),
+            [
+                ("a".into(), AccessShare),
+                ("s.b".into(), AccessShare),
+                ("c".into(), AccessShare)
+            ]
+        );
+        assert_eq!(
+            locks("WITH x AS (SELECT * FROM a) SELECT * FROM x, b FOR UPDATE OF b"),
+            [("a".into(), AccessShare), ("b".into(), RowShare)]
+        );
+        assert_eq!(
+            locks("SELECT * FROM a t1, b t2 FOR SHARE OF t1"),
+            [("a".into(), RowShare), ("b".into(), AccessShare)]
+        );
+        assert_eq!(
+            locks("WITH d AS (DELETE FROM a RETURNING *) INSERT INTO b SELECT * FROM d"),
+            [("a".into(), RowExclusive), ("b".into(), RowExclusive)]
+        );
+        assert_eq!(
+            locks("MERGE INTO a USING b ON a.id = b.id WHEN MATCHED THEN DELETE"),
+            [("a".into(), RowExclusive), ("b".into(), AccessShare)]
+        );
+        assert_eq!(lock("EXPLAIN UPDATE a SET x = 1"), RowExclusive);
+        assert_eq!(lock("COPY a FROM STDIN"), RowExclusive);
+        assert_eq!(lock("COPY (SELECT * FROM a) TO STDOUT"), AccessShare);
+        assert_eq!(locks("SELECT * FROM generate_series(1, 2)"), []);
+    }
+
+    #[test]
+    fn it_infers_locks_for_ddl() {
+        use LockMode::*;
+
+        assert_eq!(lock("CREATE INDEX ON a (x)"), Share);
+        assert_eq!(
+            lock("CREATE INDEX CONCURRENTLY ON a (x)"),
+            ShareUpdateExclusive
+        );
+        assert_eq!(lock("REINDEX TABLE a"), Share);
+        assert_eq!(lock("REINDEX (CONCURRENTLY) INDEX i"), ShareUpdateExclusive);
+        assert_eq!(lock("DROP INDEX CONCURRENTLY i"), ShareUpdateExclusive);
+        assert_eq!(lock("DROP TABLE IF EXISTS s.a"), AccessExclusive);
+        assert_eq!(lock("DROP TRIGGER tr ON a"), AccessExclusive);
+        assert_eq!(lock("TRUNCATE a"), AccessExclusive);
+        assert_eq!(lock("LOCK a IN SHARE MODE"), Share);
+        assert_eq!(lock("LOCK a"), AccessExclusive);
+        assert_eq!(lock("VACUUM a"), ShareUpdateExclusive);
+        assert_eq!(lock("VACUUM (FULL) a"), AccessExclusive);
+        assert_eq!(lock("ANALYZE a"), ShareUpdateExclusive);
+        assert_eq!(lock("REFRESH MATERIALIZED VIEW CONCURRENTLY m"), Exclusive);
+        assert_eq!(
+            lock("CREATE TRIGGER t AFTER INSERT ON a EXECUTE FUNCTION f()"),
+            ShareRowExclusive
+        );
+        assert_eq!(lock("ALTER TABLE a RENAME COLUMN x TO y"), AccessExclusive);
+        assert_eq!(lock("ALTER INDEX i RENAME TO j"), ShareUpdateExclusive);
+        assert_eq!(lock("COMMENT ON COLUMN s.a.x IS 'x'"), ShareUpdateExclusive);
+        assert_eq!(
+            locks("CREATE TABLE a (id int REFERENCES b, c int, FOREIGN KEY (c) REFERENCES s.c)"),
+            [
+                ("b".into(), ShareRowExclusive),
+                ("s.c".into(), ShareRowExclusive)
+            ]
+        );
+        assert_eq!(
+            lock("CREATE TABLE a_1 PARTITION OF a DEFAULT"),
+            AccessExclusive
+        );
+        assert_eq!(
+            locks("CREATE VIEW v AS SELECT * FROM a"),
+            [("a".into(), AccessShare)]
+        );
+    }
+
+    #[test]
+    fn it_infers_locks_for_alter_table() {
+        use LockMode::*;
+
+        assert_eq!(
+            lock("ALTER TABLE a ADD COLUMN x int DEFAULT random()"),
+            AccessExclusive
+        );
+        assert_eq!(
+            lock("ALTER TABLE a ALTER COLUMN x SET STATISTICS 100"),
+            ShareUpdateExclusive
+        );
+        assert_eq!(
+            lock("ALTER TABLE a SET (fillfactor = 70)"),
+            ShareUpdateExclusive
+        );
+        assert_eq!(
+            lock("ALTER TABLE a VALIDATE CONSTRAINT c"),
+            ShareUpdateExclusive
+        );
+        assert_eq!(
+            lock("ALTER TABLE a SET (autovacuum_enabled = off, toast.fillfactor = 90)"),
+            ShareUpdateExclusive
+        );
+        assert_eq!(
+            lock("ALTER TABLE a RESET (parallel_workers)"),
+            ShareUpdateExclusive
+        );
+        assert_eq!(
+            lock("ALTER TABLE a SET (fillfactor = 70, user_catalog_table = true)"),
+            AccessExclusive
+        );
+        assert_eq!(
+            lock("ALTER VIEW v SET (security_barrier = true)"),
+            AccessExclusive
+        );
+        assert_eq!(lock("ALTER VIEW v RESET (check_option)"), AccessExclusive);
+        assert_eq!(lock("ALTER TABLE a DISABLE TRIGGER t"), ShareRowExclusive);
+        for query in [
+            "ALTER TABLE a ENABLE RULE r",
+            "ALTER TABLE a ENABLE ALWAYS RULE r",
+            "ALTER TABLE a ENABLE REPLICA RULE r",
+            "ALTER TABLE a DISABLE RULE r",
+            "ALTER TABLE a ALTER CONSTRAINT c DEFERRABLE",
+        ] {
+            assert_eq!(lock(query), AccessExclusive, "{query}");
+        }
+        assert_eq!(
+            lock("ALTER TABLE a ADD CHECK (x > 0) NOT VALID"),
+            AccessExclusive
+        );
+        assert_eq!(
+            lock("ALTER TABLE a ALTER COLUMN x SET STATISTICS 100, ALTER COLUMN x TYPE bigint"),
+            AccessExclusive
+        );
+        assert_eq!(
+            locks("ALTER TABLE a ADD FOREIGN KEY (x) REFERENCES b NOT VALID"),
+            [
+                ("a".into(), ShareRowExclusive),
+                ("b".into(), ShareRowExclusive)
+            ]
+        );
+        assert_eq!(
+            locks("ALTER TABLE a ATTACH PARTITION a_1 FOR VALUES IN (1)"),
+            [
+                ("a".into(), ShareUpdateExclusive),
+                ("a_1".into(), AccessExclusive)
+            ]
+        );
+        assert_eq!(
+            locks("ALTER TABLE a DETACH PARTITION a_1 CONCURRENTLY"),
+            [
+                ("a".into(), ShareUpdateExclusive),
+                ("a_1".into(), ShareUpdateExclusive)
+            ]
+        );
+    }
+
+    #[test]
+    fn it_checks_lock_conflicts() {
+        for a in LockMode::ALL {
+            assert_eq!(LockMode::from_i32(a as i32), Some(a));
+            for b in LockMode::ALL {
+                assert_eq!(a.conflicts_with(b), b.conflicts_with(a), "{a} {b}");
+            }
+        }
+        assert_eq!(LockMode::from_i32(0), None);
+        assert_eq!(
+            LockMode::ShareRowExclusive.to_string(),
+            "SHARE ROW EXCLUSIVE"
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::protobuf::{self, Node};
+use crate::{quote_ident, Error, NodeEnum, NodeRef};
+
+/// A possibly qualified name, such as `public.users`, `pg_catalog.lower` or `t.*`.
+///
+/// The AST stores names as lists of `String` nodes, e.g. in `FuncCall.funcname`,
+/// `ColumnRef.fields`, `TypeName.names` and `DropStmt.objects`. Their parts are already folded
+/// to lower case by the parser unless they were quoted, so two names are equal if their parts
+/// are. Parsing a name from a string with [`FromStr`] folds it the same way, and `Display`
+/// quotes the parts that need it with [`quote_ident`].
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::{parse, NodeKind, QualifiedName};
+///
+/// let result = parse(r#"SELECT "Users".* FROM Public."Users""#).unwrap();
+/// let stmt = result.stmts()[0].to_ref();
+///
+/// let column = stmt.nodes().into_iter().find(|n| n.kind() == NodeKind::ColumnRef).unwrap();
+/// let name = QualifiedName::from_node(column).unwrap();
+/// assert_eq!(name.to_string(), r#""Users".*"#);
+///
+/// let table = stmt.nodes().into_iter().find(|n| n.kind() == NodeKind::RangeVar).unwrap();
+/// let name = QualifiedName::from_node(table).unwrap();
+/// assert_eq!(name, "public.\"Users\"");
+/// assert_eq!(name.last(), Some("Users"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct QualifiedName(pub Vec<NamePart>);
+
+/// A part of a [`QualifiedName`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NamePart {
+    /// An identifier, as stored in the AST.
+    Ident(String),
+    /// `*` at the end of a column reference.
+    Star,
+}
+
+impl QualifiedName {
+    /// Creates a name from identifiers as they are stored in the AST, without case folding.
+    pub fn new<S: Into<String>>(parts: impl IntoIterator<Item = S>) -> Self {
+        QualifiedName(
+            parts
+                .into_iter()
+                .map(|part| NamePart::Ident(part.into()))
+                .collect(),
+        )
+    }
+
+    /// Reads a name from a list of `String` and `A_Star` nodes.
+    ///
+    /// Returns None if the list contains other nodes.
+    pub fn from_nodes(nodes: &[Node]) -> Option<Self> {
+        nodes
+            .iter()
+            .map(|node| match node.node.as_ref()? {
+                NodeEnum::String(s) => Some(NamePart::Ident(s.sval.clone())),
+                NodeEnum::AStar(_) => Some(NamePart::Star),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .map(QualifiedName)
+    }
+
+    /// Reads the name of a node that refers to an object by name.
+    ///
+    /// Supports `List` and `String` nodes as found in `DropStmt.objects`, as well as
+    /// `ColumnRef`, `FuncCall`, `TypeName`, `ObjectWithArgs` and `RangeVar`. Returns None for
+    /// other nodes.
+    pub fn from_node(node: NodeRef<'_>) -> Option<Self> {
+        match node {
+            NodeRef::List(n) => Self::from_nodes(&n.items),
+            NodeRef::String(n) => Some(Self::new([n.sval.as_str()])),
+            NodeRef::ColumnRef(n) => Self::from_nodes(&n.fields),
+            NodeRef::FuncCall(n) => Self::from_nodes(&n.funcname),
+            NodeRef::TypeName(n) => Self::from_nodes(&n.names),
+            NodeRef::ObjectWithArgs(n) => Self::from_nodes(&n.objname),
+            NodeRef::RangeVar(n) => Some(Self::new(
+                [&n.catalogname, &n.schemaname, &n.relname]
+                    .into_iter()
+                    .filter(|part| !part.is_empty())
+                    .cloned(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns the name as a list of `String` and `A_Star` nodes.
+    pub fn to_nodes(&self) -> Vec<Node> {
+        self.0
+            .iter()
+            .map(|part| {
+                let node = match part {
+                    NamePart::Ident(name) => {
+                        NodeEnum::String(protobuf::String { sval: name.clone() })
+                    }
+                    NamePart::Star => NodeEnum::AStar(protobuf::AStar {}),
+                };
+                Node { node: Some(node) }
+            })
+            .collect()
+    }
+
+    /// Returns the name as a `List` node, as used in `DropStmt.objects`.
+    pub fn to_list(&self) -> NodeEnum {
+        NodeEnum::List(protobuf::List {
+            items: self.to_nodes(),
+        })
+    }
+
+    /// Returns the last identifier, e.g. the table in `schema.table`.
+    ///
+    /// Returns None if the name is empty or ends with `*`.
+    pub fn last(&self) -> Option<&str> {
+        match self.0.last()? {
+            NamePart::Ident(name) => Some(name),
+            NamePart::Star => None,
+        }
+    }
+}
+
+impl fmt::Display for QualifiedName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, part) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            match part {
+                NamePart::Ident(name) => f.write_str(&quote_ident(name))?,
+                NamePart::Star => f.write_str("*")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for QualifiedName {
+    type Err = Error;
+
+    /// Parses a dot-separated name, folding unquoted parts to lower case like Postgres does.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut parts = Vec::new();
+        let mut chars = s.chars().peekable();
+        loop {
+            let part = match chars.peek() {
+                Some('"') => {
+                    chars.next();
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') if chars.peek() == Some(&'"') => {
+                                chars.next();
+                                name.push('"');
+                            }
+                            Some('"') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(Error::Name(format!("unterminated quote in {s}"))),
+                        }
+                    }
+                    if name.is_empty() {
+                        return Err(Error::Name(format!("empty quoted identifier in {s}")));
+                    }
+                    NamePart::Ident(name)
+                }
+                Some('*') => {
+                    chars.next();
+                    NamePart::Star
+                }
+                _ => {
+                    let mut name = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if c == '.' {
+                            break;
+                        }
+                        if c == '"' || c == '*' || c.is_whitespace() {
+                            return Err(Error::Name(format!("unexpected {c:?} in {s}")));
+                        }
+                        name.push(c.to_ascii_lowercase());
+                        chars.next();
+                    }
+                    if name.is_empty() {
+                        return Err(Error::Name(format!("empty identifier in {s}")));
+                    }
+                    NamePart::Ident(name)
+                }
+            };
+            parts.push(part);
+
+            match chars.next() {
+                None => break,
+                Some('.') if parts.last() != Some(&NamePart::Star) => {}
+                Some(c) => return Err(Error::Name(format!("unexpected {c:?} in {s}"))),
+            }
+        }
+        Ok(QualifiedName(parts))
+    }
+}
+
+impl PartialEq<str> for QualifiedName {
+    /// Compares with a name given as a string, folding its unquoted parts to lower case.
+    fn eq(&self, other: &str) -> bool {
+        other
+            .parse::<QualifiedName>()
+            .is_ok_and(|name| *self == name)
+    }
+}
+
+impl PartialEq<&str> for QualifiedName {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, NodeKind};
+
+    fn names(query: &str, kind: NodeKind) -> Vec<QualifiedName> {
+        let result = parse(query).unwrap();
+        result.stmts()[0]
+            .to_ref()
+            .nodes()
+            .into_iter()
+            .filter(|n| n.kind() == kind)
+            .map(|n| QualifiedName::from_node(n).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn it_reads_names_from_nodes() {
+        assert_eq!(
+            names("SELECT t.*, a.\"B\" FROM t", NodeKind::ColumnRef),
+            [
+                QualifiedName(vec![NamePart::Ident("t".into()), NamePart::Star]),
+                QualifiedName::new(["a", "B"]),
+            ]
+        );
+        assert_eq!(
+            names("SELECT pg_catalog.lower('A')", NodeKind::FuncCall),
+            [QualifiedName::new(["pg_catalog", "lower"])]
+        );
+        assert_eq!(
+            names("SELECT 1::public.My_Type", NodeKind::TypeName),
+            [QualifiedName::new(["public", "my_type"])]
+        );
+        assert_eq!(
+            names("DROP FUNCTION s.f(int)", NodeKind::ObjectWithArgs),
+            [QualifiedName::new(["s", "f"])]
+        );
+
+        let result = parse("DROP TABLE a.b, c").unwrap();
+        let NodeEnum::DropStmt(drop) = result.stmts()[0] else {
+            panic!("expected a DROP statement");
+        };
+        let objects: Vec<_> = drop
+            .objects
+            .iter()
+            .map(|o| QualifiedName::from_node(o.node.as_ref().unwrap().to_ref()).unwrap())
+            .collect();
+        assert_eq!(objects, ["a.b", "c"]);
+    }
+
+    #[test]
+    fn it_converts_names_back_to_nodes() {
+        let mut result = parse("DROP TABLE a.b").unwrap();
+        let NodeEnum::DropStmt(drop) = &mut result.stmts_mut()[0] else {
+            panic!("expected a DROP statement");
+        };
+        let name: QualifiedName = "Other.\"Table\"".parse().unwrap();
+        drop.objects = vec![Node {
+            node: Some(name.to_list()),
+        }];
+        assert_eq!(result.deparse().unwrap(), "DROP TABLE other.\"Table\"");
+
+        let star = QualifiedName(vec![NamePart::Ident("t".into()), NamePart::Star]);
+        assert_eq!(QualifiedName::from_nodes(&star.to_nodes()), Some(star));
+        assert_eq!(
+            QualifiedName::from_nodes(&[Node::from(NodeEnum::Integer(protobuf::Integer {
+                ival: 1
+            }))]),
+            None
+        );
+    }
+
+    #[test]
+    fn it_quotes_like_postgres() {
+        let name = QualifiedName::new(["public", "Users", "select", "name", "a\"b", "x1", "1x"]);
+        assert_eq!(
+            name.to_string(),
+            r#"public."Users"."select".name."a""b".x1."1x""#
+        );
+        // Column name keywords are quoted, unreserved ones are not
+        assert_eq!(
+            QualifiedName::new(["int", "abort"]).to_string(),
+            "\"int\".abort"
+        );
+    }
+
+    #[test]
+    fn it_compares_with_case_folding() {
+        let name = QualifiedName::new(["public", "users"]);
+        assert_eq!(name, "Public.USERS");
+        assert_eq!(name, "\"public\".users");
+        assert_ne!(name, "\"Public\".users");
+        assert_ne!(name, "users");
+
+        assert!("a..b".parse::<QualifiedName>().is_err());
+        assert!("a.\"b".parse::<QualifiedName>().is_err());
+        assert!("*.a".parse::<QualifiedName>().is_err());
+        assert_eq!("T.*".parse::<QualifiedName>().unwrap().to_string(), "t.*");
+    }
+}
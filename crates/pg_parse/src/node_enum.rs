@@ -1,22 +1,13 @@
 use crate::*;
 
 pub use protobuf::node::Node as NodeEnum;
-use protobuf::Node;
 
 pg_parse_macros::node_enum_codegen!();
 
 impl NodeEnum {
+    /// Converts this node back into SQL, see [`NodeRef::deparse`].
     pub fn deparse(&self) -> Result<String> {
-        crate::deparse(&protobuf::ParseResult {
-            version: crate::bindings::PG_VERSION_NUM as i32,
-            stmts: vec![protobuf::RawStmt {
-                stmt: Some(Box::new(Node {
-                    node: Some(self.clone()),
-                })),
-                stmt_location: 0,
-                stmt_len: 0,
-            }],
-        })
+        crate::deparse::deparse_node(self.to_ref())
     }
 
     pub fn nodes(&self) -> Vec<NodeRef<'_>> {
@@ -27,7 +18,30 @@ impl NodeEnum {
         NodeRefIterator::new(self.to_ref())
     }
 
+    pub fn iter_with_order(&self, order: TraversalOrder) -> NodeRefIterator<'_> {
+        NodeRefIterator::with_order(self.to_ref(), order)
+    }
+
+    /// Returns the direct children of this node, see [`NodeRef::children`].
+    pub fn children(&self) -> Vec<NodeRef<'_>> {
+        self.to_ref().children()
+    }
+
+    /// Returns all nodes of type `T` below this node, see [`NodeRef::descendants`].
+    pub fn descendants<T: AstNode>(&self) -> impl Iterator<Item = &T> {
+        self.to_ref().descendants()
+    }
+
+    /// Returns the first node of type `T` below this node, see [`NodeRef::find_first`].
+    pub fn find_first<T: AstNode>(&self) -> Option<&T> {
+        self.to_ref().find_first()
+    }
+
     pub fn iter_mut(&mut self) -> NodeMutIterator {
         NodeMutIterator::new(self.to_mut())
     }
+
+    pub fn iter_mut_with_order(&mut self, order: TraversalOrder) -> NodeMutIterator {
+        NodeMutIterator::with_order(self.to_mut(), order)
+    }
 }
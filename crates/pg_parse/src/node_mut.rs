@@ -23,4 +23,8 @@ impl NodeMut {
     pub fn iter_mut(&self) -> NodeMutIterator {
         NodeMutIterator::new(*self)
     }
+
+    pub fn iter_mut_with_order(&self, order: TraversalOrder) -> NodeMutIterator {
+        NodeMutIterator::with_order(*self, order)
+    }
 }
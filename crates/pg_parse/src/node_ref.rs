@@ -1,19 +1,23 @@
-use protobuf::Node;
-
 pg_parse_macros::node_ref_codegen!();
 
 impl<'a> NodeRef<'a> {
+    /// Converts this node back into SQL.
+    ///
+    /// Besides statements, this supports expressions, type names, select targets, `ORDER BY`
+    /// items, window definitions, `FROM` items and common table expressions, which are
+    /// deparsed as they would appear in a statement. Other nodes return an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use pg_parse::ast::AExpr;
+    ///
+    /// let result = pg_parse::parse("SELECT * FROM t WHERE a = lower($1)").unwrap();
+    /// let expr = result.stmts()[0].find_first::<AExpr>().unwrap();
+    /// assert_eq!(expr.to_ref().deparse().unwrap(), "a = lower($1)");
+    /// ```
     pub fn deparse(&self) -> Result<String> {
-        crate::deparse(&protobuf::ParseResult {
-            version: crate::bindings::PG_VERSION_NUM as i32,
-            stmts: vec![protobuf::RawStmt {
-                stmt: Some(Box::new(Node {
-                    node: Some(self.to_enum()),
-                })),
-                stmt_location: 0,
-                stmt_len: 0,
-            }],
-        })
+        crate::deparse::deparse_node(*self)
     }
 
     pub fn nodes(&self) -> Vec<NodeRef<'a>> {
@@ -23,4 +27,42 @@ impl<'a> NodeRef<'a> {
     pub fn iter(&self) -> NodeRefIterator<'a> {
         NodeRefIterator::new(*self)
     }
+
+    pub fn iter_with_order(&self, order: TraversalOrder) -> NodeRefIterator<'a> {
+        NodeRefIterator::with_order(*self, order)
+    }
+
+    /// Returns the direct children of this node in field declaration order.
+    pub fn children(&self) -> Vec<NodeRef<'a>> {
+        let mut children = Vec::new();
+        push_children(self, &mut children);
+        children
+    }
+
+    /// Returns all nodes of type `T` below this node, in depth-first pre-order. The node itself
+    /// is not included.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use pg_parse::ast::RangeVar;
+    ///
+    /// let result = pg_parse::parse("SELECT * FROM a JOIN b ON a.id = b.id").unwrap();
+    /// let tables: Vec<_> = result.stmts()[0]
+    ///     .to_ref()
+    ///     .descendants::<RangeVar>()
+    ///     .map(|t| t.relname.as_str())
+    ///     .collect();
+    /// assert_eq!(tables, ["a", "b"]);
+    /// ```
+    pub fn descendants<T: AstNode>(&self) -> impl Iterator<Item = &'a T> {
+        self.iter_with_order(TraversalOrder::PreOrder)
+            .skip(1)
+            .filter_map(T::cast)
+    }
+
+    /// Returns the first node of type `T` below this node in depth-first pre-order.
+    pub fn find_first<T: AstNode>(&self) -> Option<&'a T> {
+        self.descendants().next()
+    }
 }
@@ -2,15 +2,31 @@ use protobuf::Node;
 
 pg_parse_macros::node_structs_codegen!();
 
+/// A struct that can appear as a node of the tree, such as [`SelectStmt`](crate::ast::SelectStmt)
+/// or [`RangeVar`](crate::ast::RangeVar).
+///
+/// This is implemented for every type that has a [`NodeEnum`] variant and allows searching the
+/// tree by type with [`NodeRef::descendants`] and [`NodeRef::find_first`].
+pub trait AstNode: 'static {
+    /// The kind of node this type is stored as.
+    const KIND: NodeKind;
+
+    /// Returns the node as this type if it is of this kind.
+    fn cast(node: NodeRef<'_>) -> Option<&Self>;
+
+    fn to_ref(&self) -> NodeRef<'_>;
+}
+
 impl Node {
+    /// Converts this node back into SQL, see [`NodeRef::deparse`].
     pub fn deparse(&self) -> Result<String> {
-        crate::deparse(&protobuf::ParseResult {
-            version: crate::bindings::PG_VERSION_NUM as i32,
-            stmts: vec![protobuf::RawStmt {
-                stmt: Some(Box::new(self.clone())),
-                stmt_location: 0,
-                stmt_len: 0,
-            }],
-        })
+        match &self.node {
+            Some(node) => crate::deparse::deparse_node(node.to_ref()),
+            None => Err(Error::Deparse(DeparseError {
+                kind: None,
+                path: AstPath::default(),
+                message: "cannot deparse an empty node".to_string(),
+            })),
+        }
     }
 }
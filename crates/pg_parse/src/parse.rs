@@ -20,6 +20,35 @@ use prost::Message;
 /// assert_eq!(result.protobuf.stmts.len(), 1);
 /// ```
 pub fn parse(statement: &str) -> Result<ParseResult> {
+    parse_protobuf(statement, |data, stderr| {
+        protobuf::ParseResult::decode(data)
+            .map_err(Error::Decode)
+            .map(|result| ParseResult::new(result, stderr))
+    })
+}
+
+/// Parses the given SQL statement into the protobuf encoding of the syntax tree, as defined by
+/// `pg_query.proto`, without decoding it.
+///
+/// The bytes can be stored, passed to [`deparse_protobuf_bytes`](crate::deparse_protobuf_bytes),
+/// decoded later with [`ParseResult::from_protobuf_bytes`], or read by the pg_query bindings for
+/// other languages.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::{parse, parse_to_protobuf_bytes, ParseResult};
+///
+/// let bytes = parse_to_protobuf_bytes("SELECT * FROM contacts").unwrap();
+/// let result = ParseResult::from_protobuf_bytes(&bytes).unwrap();
+/// assert_eq!(result.protobuf, parse("SELECT * FROM contacts").unwrap().protobuf);
+/// ```
+pub fn parse_to_protobuf_bytes(statement: &str) -> Result<Vec<u8>> {
+    parse_protobuf(statement, |data, _| Ok(data.to_vec()))
+}
+
+/// Calls the C parser and passes the encoded tree and the output on stderr to `f`
+fn parse_protobuf<T>(statement: &str, f: impl FnOnce(&[u8], String) -> Result<T>) -> Result<T> {
     let input = CString::new(statement)?;
     let result = unsafe { pg_query_parse_protobuf(input.as_ptr()) };
     let parse_result = if !result.error.is_null() {
@@ -37,14 +66,42 @@ pub fn parse(statement: &str) -> Result<ParseResult> {
         let stderr = unsafe { CStr::from_ptr(result.stderr_buffer) }
             .to_string_lossy()
             .to_string();
-        protobuf::ParseResult::decode(data)
-            .map_err(Error::Decode)
-            .map(|result| ParseResult::new(result, stderr))
+        f(data, stderr)
     };
     unsafe { pg_query_free_protobuf_parse_result(result) };
     parse_result
 }
 
+/// Parses the given SQL statement into libpg_query's JSON representation of the syntax tree.
+///
+/// The output is exactly what `pg_query_parse` in libpg_query returns, so it can be exchanged
+/// with the pg_query bindings for other languages. With the `serde` feature enabled, it can be
+/// read back with [`ParseResult::from_json`].
+///
+/// # Example
+///
+/// ```rust
+/// let json = pg_parse::parse_json("SELECT 1").unwrap();
+/// assert!(json.starts_with(r#"{"version":"#));
+/// assert!(json.contains(r#"{"A_Const":{"ival":{"ival":1},"location":7}}"#));
+/// ```
+pub fn parse_json(statement: &str) -> Result<String> {
+    let input = CString::new(statement)?;
+    let result = unsafe { pg_query_parse(input.as_ptr()) };
+    let json = if !result.error.is_null() {
+        let message = unsafe { CStr::from_ptr((*result.error).message) }
+            .to_string_lossy()
+            .to_string();
+        Err(Error::Parse(message))
+    } else {
+        Ok(unsafe { CStr::from_ptr(result.parse_tree) }
+            .to_string_lossy()
+            .to_string())
+    };
+    unsafe { pg_query_free_parse_result(result) };
+    json
+}
+
 /// The result of parsing a SQL query
 #[derive(Debug)]
 pub struct ParseResult {
@@ -71,10 +128,34 @@ impl ParseResult {
         Self { protobuf, warnings }
     }
 
+    /// Decodes a parse result from the protobuf encoding of the syntax tree, as returned by
+    /// [`parse_to_protobuf_bytes`] or [`to_protobuf_bytes`](ParseResult::to_protobuf_bytes).
+    pub fn from_protobuf_bytes(bytes: &[u8]) -> Result<Self> {
+        protobuf::ParseResult::decode(bytes)
+            .map_err(Error::Decode)
+            .map(|result| ParseResult::new(result, String::new()))
+    }
+
+    /// Encodes the syntax tree with protobuf, as defined by `pg_query.proto`.
+    pub fn to_protobuf_bytes(&self) -> Vec<u8> {
+        self.protobuf.encode_to_vec()
+    }
+
     pub fn deparse(&self) -> Result<String> {
         crate::deparse(&self.protobuf)
     }
 
+    /// Deparses the tree after checking it with [`validate`](crate::validate), so that problems
+    /// in hand-built trees are reported with their path instead of failing in the deparser.
+    ///
+    /// Returns the first problem found as [`Error::Validation`].
+    pub fn deparse_validated(&self) -> Result<String> {
+        if let Err(mut errors) = crate::validate(self) {
+            return Err(Error::Validation(errors.swap_remove(0)));
+        }
+        self.deparse()
+    }
+
     pub fn stmts(&self) -> Vec<&NodeEnum> {
         self.protobuf
             .stmts
@@ -0,0 +1,180 @@
+use std::fmt;
+
+use crate::{protobuf, NodeEnum, NodeRef};
+
+pg_parse_macros::reflect_codegen!();
+
+/// The value of a node field, as returned by [`NodeRef::fields`].
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::{parse, FieldValue, NodeKind};
+///
+/// let result = parse("SELECT * FROM t ORDER BY a DESC").unwrap();
+/// let stmt = result.stmts()[0].to_ref();
+/// assert_eq!(stmt.kind(), NodeKind::SelectStmt);
+///
+/// let sort = stmt.nodes().into_iter().find(|n| n.kind() == NodeKind::SortBy).unwrap();
+/// let fields = sort.fields();
+/// assert!(matches!(fields[0], ("node", FieldValue::Node(n)) if n.kind() == NodeKind::ColumnRef));
+/// assert!(matches!(fields[1], ("sortby_dir", FieldValue::Enum("SORTBY_DESC"))));
+/// ```
+#[derive(Debug, Clone)]
+pub enum FieldValue<'a> {
+    /// A child node.
+    Node(NodeRef<'a>),
+    /// A repeated field. Lists of nodes can contain [`FieldValue::Null`] for empty nodes.
+    List(Vec<FieldValue<'a>>),
+    /// A node field that is not set.
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(&'a str),
+    /// The protobuf name of an enum value, e.g. `AEXPR_OP`.
+    Enum(&'static str),
+}
+
+impl<'a> From<&'a protobuf::Node> for FieldValue<'a> {
+    fn from(node: &'a protobuf::Node) -> Self {
+        match &node.node {
+            Some(n) => FieldValue::Node(n.to_ref()),
+            None => FieldValue::Null,
+        }
+    }
+}
+
+impl From<&bool> for FieldValue<'_> {
+    fn from(value: &bool) -> Self {
+        FieldValue::Bool(*value)
+    }
+}
+
+impl From<&i32> for FieldValue<'_> {
+    fn from(value: &i32) -> Self {
+        FieldValue::Int((*value).into())
+    }
+}
+
+impl From<&i64> for FieldValue<'_> {
+    fn from(value: &i64) -> Self {
+        FieldValue::Int(*value)
+    }
+}
+
+impl From<&u32> for FieldValue<'_> {
+    fn from(value: &u32) -> Self {
+        FieldValue::UInt((*value).into())
+    }
+}
+
+impl From<&u64> for FieldValue<'_> {
+    fn from(value: &u64) -> Self {
+        FieldValue::UInt(*value)
+    }
+}
+
+impl From<&f64> for FieldValue<'_> {
+    fn from(value: &f64) -> Self {
+        FieldValue::Float(*value)
+    }
+}
+
+impl<'a> From<&'a String> for FieldValue<'a> {
+    fn from(value: &'a String) -> Self {
+        FieldValue::String(value)
+    }
+}
+
+impl From<&Vec<u64>> for FieldValue<'_> {
+    fn from(values: &Vec<u64>) -> Self {
+        FieldValue::List(values.iter().map(FieldValue::from).collect())
+    }
+}
+
+impl NodeEnum {
+    /// Returns the type of this node.
+    pub fn kind(&self) -> NodeKind {
+        self.to_ref().kind()
+    }
+}
+
+impl fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn field<'a>(node: NodeRef<'a>, name: &str) -> FieldValue<'a> {
+        node.fields()
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v)
+            .unwrap_or_else(|| panic!("no field {name}"))
+    }
+
+    #[test]
+    fn it_names_node_kinds() {
+        assert_eq!(NodeKind::SelectStmt.name(), "SelectStmt");
+        assert_eq!(NodeKind::AExpr.to_string(), "AExpr");
+        assert!(NodeKind::ALL.contains(&NodeKind::RangeVar));
+
+        let result = parse("INSERT INTO t VALUES (1)").unwrap();
+        assert_eq!(result.stmts()[0].kind(), NodeKind::InsertStmt);
+    }
+
+    #[test]
+    fn it_reflects_fields() {
+        let result = parse("SELECT DISTINCT a FROM public.t WHERE b = 1").unwrap();
+        let stmt = result.stmts()[0].to_ref();
+
+        let names: Vec<_> = stmt.fields().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(
+            &names[..4],
+            [
+                "distinct_clause",
+                "into_clause",
+                "target_list",
+                "from_clause"
+            ]
+        );
+
+        assert!(matches!(field(stmt, "into_clause"), FieldValue::Null));
+        assert!(
+            matches!(field(stmt, "distinct_clause"), FieldValue::List(l) if matches!(l[..], [FieldValue::Null]))
+        );
+        assert!(matches!(field(stmt, "op"), FieldValue::Enum("SETOP_NONE")));
+        assert!(matches!(field(stmt, "all"), FieldValue::Bool(false)));
+
+        let FieldValue::List(from) = field(stmt, "from_clause") else {
+            panic!("from_clause is not a list");
+        };
+        let [FieldValue::Node(table)] = from[..] else {
+            panic!("unexpected from_clause {from:?}");
+        };
+        assert!(matches!(
+            field(table, "schemaname"),
+            FieldValue::String("public")
+        ));
+        assert!(matches!(field(table, "location"), FieldValue::Int(_)));
+
+        let FieldValue::Node(expr) = field(stmt, "where_clause") else {
+            panic!("where_clause is not a node");
+        };
+        let FieldValue::Node(constant) = field(expr, "rexpr") else {
+            panic!("rexpr is not a node");
+        };
+        let FieldValue::Node(value) = field(constant, "val") else {
+            panic!("val is not a node");
+        };
+        assert_eq!(value.kind(), NodeKind::Integer);
+        assert!(matches!(field(value, "ival"), FieldValue::Int(1)));
+    }
+}
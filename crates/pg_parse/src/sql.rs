@@ -0,0 +1,151 @@
+//! Statements written as SQL, parsed and checked when the crate is compiled.
+//!
+//! A literal that does not parse is a compile error rather than a runtime one:
+//!
+//! ```compile_fail
+//! use pg_parse::checked_sql;
+//!
+//! const LIST_USERS: &str = checked_sql!("SELEC * FROM users");
+//! ```
+//!
+//! The same holds for templates given to `sql!`:
+//!
+//! ```compile_fail
+//! use pg_parse::{builder::*, sql};
+//!
+//! let id = param(1);
+//! let stmt = sql!("SELECT * FROM users WHERE id = #id AND");
+//! ```
+
+pub use pg_parse_macros::{checked_sql, sql};
+
+/// Support code for the expansions of the macros in `pg_parse_macros`. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    use crate::{ast, NodeEnum};
+
+    /// Unwraps a node interpolated where the grammar only allows a table name.
+    pub fn into_range_var(node: NodeEnum) -> ast::RangeVar {
+        match node {
+            NodeEnum::RangeVar(r) => r,
+            other => panic!("only tables can be interpolated here, got {other:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::*;
+    use crate::{checked_sql, parse, sql, NodeEnum, StructuralEq};
+
+    fn assert_matches(node: NodeEnum, query: &str) {
+        let parsed = parse(query).unwrap();
+        assert!(
+            node.structural_eq(parsed.stmts()[0]),
+            "{}",
+            node.deparse().unwrap()
+        );
+    }
+
+    #[test]
+    fn it_expands_statements() {
+        let stmt = sql!("SELECT a, count(*) FROM t WHERE b = 'x' AND c > 1.5 GROUP BY a");
+        assert_matches(
+            stmt,
+            "SELECT a, count(*) FROM t WHERE b = 'x' AND c > 1.5 GROUP BY a",
+        );
+
+        let stmt = sql!("CREATE TABLE t (id bigint PRIMARY KEY, tags text[] NOT NULL)");
+        assert_matches(
+            stmt,
+            "CREATE TABLE t (id bigint PRIMARY KEY, tags text[] NOT NULL)",
+        );
+    }
+
+    #[test]
+    fn it_does_not_keep_template_locations() {
+        let stmt = sql!("SELECT a FROM t");
+        assert!(stmt.nodes().iter().all(|n| n.location().is_none()));
+    }
+
+    #[test]
+    fn it_interpolates_expressions_and_tables() {
+        let table = table("public", "users").alias("u");
+        let id = param(1);
+        let limit = int(10);
+        let stmt = sql!("SELECT * FROM #table WHERE id = #id OR parent = #id LIMIT #limit");
+        assert_eq!(
+            stmt.deparse().unwrap(),
+            "SELECT * FROM public.users u WHERE id = $1 OR parent = $1 LIMIT 10"
+        );
+
+        let condition = sql!("SELECT 1 WHERE a IS NULL");
+        let NodeEnum::SelectStmt(select) = condition else {
+            unreachable!()
+        };
+        let condition = select.where_clause.unwrap().node.unwrap();
+        let stmt = sql!("DELETE FROM #table WHERE #condition -- #ignored\n AND b = '#ignored'");
+        assert_eq!(
+            stmt.deparse().unwrap(),
+            "DELETE FROM public.users u WHERE a IS NULL AND b = '#ignored'"
+        );
+    }
+
+    #[test]
+    fn it_does_not_interpolate_in_comments_and_strings() {
+        let id = param(1);
+        let stmt = sql!("SELECT 1 /* #ignored */ WHERE id = #id");
+        assert_eq!(stmt.deparse().unwrap(), "SELECT 1 WHERE id = $1");
+
+        let stmt = sql!("SELECT $$#a$$, $tag$ #b $$ #c $tag$ WHERE id = #id");
+        assert_eq!(
+            stmt.deparse().unwrap(),
+            "SELECT '#a', ' #b $$ #c ' WHERE id = $1"
+        );
+
+        let stmt = sql!(r"SELECT E'it\'s #a' WHERE id = #id");
+        assert_eq!(stmt.deparse().unwrap(), "SELECT 'it''s #a' WHERE id = $1");
+    }
+
+    #[test]
+    fn it_interpolates_subqueries() {
+        let subquery = subquery(
+            select().columns([col("a")]).from(unqualified_table("t")),
+            "s",
+        );
+        let stmt = sql!("SELECT s.a FROM #subquery");
+        assert_eq!(
+            stmt.deparse().unwrap(),
+            "SELECT s.a FROM (SELECT a FROM t) s"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "only tables can be interpolated here")]
+    fn it_panics_for_expressions_in_table_positions() {
+        let table = col("a");
+        sql!("CREATE INDEX ON #table (a)");
+    }
+
+    const LIST_USERS: &str = checked_sql!("SELECT * FROM users; SELECT 1");
+
+    checked_sql! {
+        /// Looks up a user
+        const FIND_USER = r#"SELECT * FROM "users" WHERE id = $1 AND tenant = $2"#;
+        const ARCHIVE = "UPDATE orders\n SET archived = true WHERE created_at < $1";
+    }
+
+    #[test]
+    fn it_checks_sql_at_compile_time() {
+        assert_eq!(LIST_USERS, "SELECT * FROM users; SELECT 1");
+        assert_eq!(
+            FIND_USER,
+            r#"SELECT * FROM "users" WHERE id = $1 AND tenant = $2"#
+        );
+        assert_eq!(FIND_USER_KIND, "SelectStmt");
+        assert_eq!(FIND_USER_PARAMS, 2);
+        assert_eq!(ARCHIVE_KIND, "UpdateStmt");
+        assert_eq!(ARCHIVE_PARAMS, 1);
+        assert!(parse(ARCHIVE).is_ok());
+    }
+}
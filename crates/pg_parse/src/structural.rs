@@ -0,0 +1,331 @@
+use std::hash::{Hash, Hasher};
+
+use crate::{protobuf, ParseResult};
+
+/// Equality that ignores where nodes appear in the source text.
+///
+/// The derived `PartialEq` compares `location` fields, so the same statement written with
+/// different whitespace never compares equal. `structural_eq` compares everything except
+/// locations and statement lengths.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::{parse, StructuralEq};
+///
+/// let a = parse("SELECT a FROM t WHERE id = 1").unwrap();
+/// let b = parse("select a\n  from t\n where id = 1").unwrap();
+/// assert_ne!(a.protobuf, b.protobuf);
+/// assert!(a.structural_eq(&b));
+/// ```
+pub trait StructuralEq {
+    fn structural_eq(&self, other: &Self) -> bool;
+}
+
+/// Hashing that ignores where nodes appear in the source text, consistent with
+/// [`StructuralEq`].
+pub trait StructuralHash {
+    fn structural_hash<H: Hasher>(&self, state: &mut H);
+}
+
+/// Rewrites the source locations stored in a tree.
+pub trait Locations {
+    /// Resets all locations to -1 (unknown) and statement lengths to 0.
+    fn strip_locations(&mut self);
+
+    /// Moves all known locations by `delta` bytes, e.g. after splicing nodes into another query.
+    /// Unknown locations (-1) are left untouched.
+    fn shift_locations(&mut self, delta: i32);
+}
+
+/// Wraps a value so that its `Eq` and `Hash` implementations are location-insensitive, which
+/// allows using ASTs as `HashMap` keys or deduplicating them in a `HashSet`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::collections::HashSet;
+/// use pg_parse::{parse, StructuralKey};
+///
+/// let statements: HashSet<_> = ["SELECT 1", "SELECT  1", "SELECT 2"]
+///     .iter()
+///     .map(|q| StructuralKey(parse(q).unwrap()))
+///     .collect();
+/// assert_eq!(statements.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StructuralKey<T>(pub T);
+
+impl<T: StructuralEq> PartialEq for StructuralKey<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.structural_eq(&other.0)
+    }
+}
+
+impl<T: StructuralEq> Eq for StructuralKey<T> {}
+
+impl<T: StructuralHash> Hash for StructuralKey<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.structural_hash(state)
+    }
+}
+
+macro_rules! scalar_impls {
+    ($($t:ty),*) => {
+        $(
+            impl StructuralEq for $t {
+                fn structural_eq(&self, other: &Self) -> bool {
+                    self == other
+                }
+            }
+
+            impl StructuralHash for $t {
+                fn structural_hash<H: Hasher>(&self, state: &mut H) {
+                    self.hash(state)
+                }
+            }
+        )*
+    };
+}
+
+scalar_impls!(bool, i32, u32, i64, u64, String);
+
+impl StructuralEq for f64 {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.to_bits() == other.to_bits()
+    }
+}
+
+impl StructuralHash for f64 {
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bits().hash(state)
+    }
+}
+
+impl<T: StructuralEq> StructuralEq for Vec<T> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a.structural_eq(b))
+    }
+}
+
+impl<T: StructuralHash> StructuralHash for Vec<T> {
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        self.iter().for_each(|x| x.structural_hash(state));
+    }
+}
+
+impl<T: Locations> Locations for Vec<T> {
+    fn strip_locations(&mut self) {
+        self.iter_mut().for_each(|x| x.strip_locations());
+    }
+
+    fn shift_locations(&mut self, delta: i32) {
+        self.iter_mut().for_each(|x| x.shift_locations(delta));
+    }
+}
+
+impl<T: StructuralEq> StructuralEq for Option<T> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Some(a), Some(b)) => a.structural_eq(b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T: StructuralHash> StructuralHash for Option<T> {
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.is_some().hash(state);
+        if let Some(x) = self {
+            x.structural_hash(state);
+        }
+    }
+}
+
+impl<T: Locations> Locations for Option<T> {
+    fn strip_locations(&mut self) {
+        if let Some(x) = self {
+            x.strip_locations();
+        }
+    }
+
+    fn shift_locations(&mut self, delta: i32) {
+        if let Some(x) = self {
+            x.shift_locations(delta);
+        }
+    }
+}
+
+impl<T: StructuralEq> StructuralEq for Box<T> {
+    fn structural_eq(&self, other: &Self) -> bool {
+        (**self).structural_eq(other)
+    }
+}
+
+impl<T: StructuralHash> StructuralHash for Box<T> {
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        (**self).structural_hash(state)
+    }
+}
+
+impl<T: Locations> Locations for Box<T> {
+    fn strip_locations(&mut self) {
+        (**self).strip_locations()
+    }
+
+    fn shift_locations(&mut self, delta: i32) {
+        (**self).shift_locations(delta)
+    }
+}
+
+impl StructuralEq for protobuf::Node {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.node.structural_eq(&other.node)
+    }
+}
+
+impl StructuralHash for protobuf::Node {
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.node.structural_hash(state)
+    }
+}
+
+impl Locations for protobuf::Node {
+    fn strip_locations(&mut self) {
+        self.node.strip_locations()
+    }
+
+    fn shift_locations(&mut self, delta: i32) {
+        self.node.shift_locations(delta)
+    }
+}
+
+impl StructuralEq for protobuf::ParseResult {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.version == other.version && self.stmts.structural_eq(&other.stmts)
+    }
+}
+
+impl StructuralHash for protobuf::ParseResult {
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.version.hash(state);
+        self.stmts.structural_hash(state);
+    }
+}
+
+impl Locations for protobuf::ParseResult {
+    fn strip_locations(&mut self) {
+        self.stmts.strip_locations()
+    }
+
+    fn shift_locations(&mut self, delta: i32) {
+        self.stmts.shift_locations(delta)
+    }
+}
+
+impl StructuralEq for ParseResult {
+    fn structural_eq(&self, other: &Self) -> bool {
+        self.protobuf.structural_eq(&other.protobuf)
+    }
+}
+
+impl StructuralHash for ParseResult {
+    fn structural_hash<H: Hasher>(&self, state: &mut H) {
+        self.protobuf.structural_hash(state)
+    }
+}
+
+impl Locations for ParseResult {
+    fn strip_locations(&mut self) {
+        self.protobuf.strip_locations()
+    }
+
+    fn shift_locations(&mut self, delta: i32) {
+        self.protobuf.shift_locations(delta)
+    }
+}
+
+pg_parse_macros::structural_codegen!();
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    use super::*;
+    use crate::{parse, NodeRef};
+
+    fn hash<T: StructuralHash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.structural_hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn it_ignores_whitespace() {
+        let a = parse("SELECT a, b FROM t WHERE a = 'x' AND b > 1.5").unwrap();
+        let b = parse("SELECT   a,b\nFROM t\nWHERE a = 'x'   AND b > 1.5").unwrap();
+
+        assert!(a.stmts()[0].structural_eq(b.stmts()[0]));
+        assert!(a.structural_eq(&b));
+        assert_eq!(hash(a.stmts()[0]), hash(b.stmts()[0]));
+        assert_eq!(hash(&a), hash(&b));
+    }
+
+    #[test]
+    fn it_distinguishes_different_statements() {
+        let a = parse("SELECT a FROM t WHERE a = 1").unwrap();
+        for query in [
+            "SELECT a FROM t WHERE a = 2",
+            "SELECT a FROM t WHERE a = '1'",
+            "SELECT b FROM t WHERE a = 1",
+            "SELECT a FROM u WHERE a = 1",
+            "SELECT a FROM t",
+        ] {
+            let b = parse(query).unwrap();
+            assert!(!a.structural_eq(&b), "{query}");
+            assert_ne!(hash(&a), hash(&b), "{query}");
+        }
+    }
+
+    #[test]
+    fn it_strips_locations() {
+        let mut a = parse("SELECT a FROM t; SELECT 1").unwrap();
+        a.strip_locations();
+
+        assert!(a.protobuf.stmts.iter().all(|s| s.stmt_location == -1));
+        assert!(a.protobuf.stmts.iter().all(|s| s.stmt_len == 0));
+        for stmt in a.stmts() {
+            assert!(stmt.nodes().iter().all(|n| n.location().is_none()));
+        }
+
+        let mut b = parse("SELECT  a  FROM  t;  SELECT  1").unwrap();
+        b.strip_locations();
+        assert_eq!(a.protobuf, b.protobuf);
+    }
+
+    #[test]
+    fn it_shifts_locations() {
+        let mut result = parse("SELECT a FROM t").unwrap();
+        result.shift_locations(10);
+
+        assert_eq!(result.protobuf.stmts[0].stmt_location, 10);
+        let mut locations: Vec<_> = result.stmts()[0]
+            .nodes()
+            .iter()
+            .filter_map(|n| match n {
+                NodeRef::ColumnRef(c) => Some(c.location),
+                NodeRef::RangeVar(r) => Some(r.location),
+                _ => None,
+            })
+            .collect();
+        locations.sort();
+        assert_eq!(locations, vec![17, 24]);
+
+        result.strip_locations();
+        result.shift_locations(10);
+        assert_eq!(result.protobuf.stmts[0].stmt_location, -1);
+    }
+}
@@ -0,0 +1,146 @@
+/// The order in which [`NodeRefIterator`](crate::NodeRefIterator) and
+/// [`NodeMutIterator`](crate::NodeMutIterator) visit the nodes of a tree.
+///
+/// Children are always discovered in field declaration order of the protobuf definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraversalOrder {
+    /// Visits the tree level by level.
+    #[default]
+    BreadthFirst,
+    /// Depth-first, visiting each node before its children.
+    PreOrder,
+    /// Depth-first, visiting each node after all of its children.
+    PostOrder,
+    /// Visits nodes sorted by their location in the source text.
+    ///
+    /// Nodes without a location are placed at the location of their closest ancestor that has
+    /// one, so a node is never yielded before the parent it inherits its position from.
+    SourceOrder,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, NodeMut, NodeRef, TraversalOrder};
+
+    fn name(node: NodeRef) -> &'static str {
+        match node {
+            NodeRef::SelectStmt(_) => "SelectStmt",
+            NodeRef::ResTarget(_) => "ResTarget",
+            NodeRef::ColumnRef(_) => "ColumnRef",
+            NodeRef::String(_) => "String",
+            NodeRef::RangeVar(_) => "RangeVar",
+            NodeRef::WithClause(_) => "WithClause",
+            NodeRef::CommonTableExpr(_) => "CommonTableExpr",
+            _ => "Other",
+        }
+    }
+
+    fn names(query: &str, order: TraversalOrder) -> Vec<&'static str> {
+        let result = parse(query).unwrap();
+        result.stmts()[0].iter_with_order(order).map(name).collect()
+    }
+
+    #[test]
+    fn it_traverses_breadth_first_by_default() {
+        let result = parse("SELECT a, b FROM t").unwrap();
+        let default: Vec<_> = result.stmts()[0].iter().map(name).collect();
+        assert_eq!(
+            default,
+            vec![
+                "SelectStmt",
+                "ResTarget",
+                "ResTarget",
+                "RangeVar",
+                "ColumnRef",
+                "ColumnRef",
+                "String",
+                "String"
+            ]
+        );
+        assert_eq!(
+            default,
+            names("SELECT a, b FROM t", TraversalOrder::BreadthFirst)
+        );
+    }
+
+    #[test]
+    fn it_traverses_in_pre_order() {
+        assert_eq!(
+            names("SELECT a, b FROM t", TraversalOrder::PreOrder),
+            vec![
+                "SelectStmt",
+                "ResTarget",
+                "ColumnRef",
+                "String",
+                "ResTarget",
+                "ColumnRef",
+                "String",
+                "RangeVar"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_traverses_in_post_order() {
+        assert_eq!(
+            names("SELECT a, b FROM t", TraversalOrder::PostOrder),
+            vec![
+                "String",
+                "ColumnRef",
+                "ResTarget",
+                "String",
+                "ColumnRef",
+                "ResTarget",
+                "RangeVar",
+                "SelectStmt"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_traverses_in_source_order() {
+        // The WITH clause is the last field of SelectStmt, but comes first in the source text
+        let query = "WITH x AS (SELECT 1) SELECT a FROM x";
+        let pre_order = names(query, TraversalOrder::PreOrder);
+        let source_order = names(query, TraversalOrder::SourceOrder);
+
+        let position = |names: &[&str], name: &str| names.iter().position(|n| *n == name);
+        assert!(
+            position(&pre_order, "WithClause") > position(&pre_order, "ResTarget"),
+            "{pre_order:?}"
+        );
+        assert!(
+            position(&source_order, "WithClause") < position(&source_order, "ResTarget"),
+            "{source_order:?}"
+        );
+        assert_eq!(source_order.len(), pre_order.len());
+
+        let result = parse(query).unwrap();
+        let locations: Vec<_> = result.stmts()[0]
+            .iter_with_order(TraversalOrder::SourceOrder)
+            .filter_map(|n| n.location())
+            .collect();
+        assert!(locations.windows(2).all(|w| w[0] <= w[1]), "{locations:?}");
+    }
+
+    #[test]
+    fn it_traverses_mutably_in_the_same_order() {
+        let mut result = parse("SELECT a, b FROM t").unwrap();
+        for order in [
+            TraversalOrder::BreadthFirst,
+            TraversalOrder::PreOrder,
+            TraversalOrder::PostOrder,
+            TraversalOrder::SourceOrder,
+        ] {
+            let expected: Vec<_> = result.stmts()[0]
+                .iter_with_order(order)
+                .map(|n| n.to_enum())
+                .collect();
+            let actual: Vec<_> = result.stmts_mut()[0]
+                .iter_mut_with_order(order)
+                .map(|n: NodeMut| n.to_enum().unwrap())
+                .collect();
+            assert_eq!(actual, expected, "{order:?}");
+        }
+    }
+}
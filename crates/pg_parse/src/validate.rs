@@ -0,0 +1,381 @@
+use std::fmt;
+
+use crate::{AstPath, FieldValue, NodeKind, NodeRef, ParseResult, PathSegment};
+
+/// Checks a parse tree for structural problems that the deparser cannot handle.
+///
+/// Trees produced by [`parse`](crate::parse) always pass. Trees that were built or rewritten
+/// by hand can violate assumptions of the deparser, which then fails with an unhelpful message
+/// or an assertion failure. The checks cover:
+///
+/// - enum fields that are left at their `UNDEFINED` default
+/// - names that must not be empty: `RangeVar.relname`, `FuncCall.funcname`, `ColumnRef.fields`,
+///   `TypeName.names` and `A_Expr.name`
+/// - `SelectStmt`s that mix a `VALUES` list with a target list or other clauses, and set
+///   operations without both sides
+/// - select targets, operators, boolean expressions and constants that lack their operands
+/// - `INSERT`, `UPDATE` and `DELETE` statements without a table
+///
+/// Returns all problems found, each with the path of the offending node or field.
+///
+/// # Example
+///
+/// ```rust
+/// use pg_parse::{parse, validate, NodeEnum};
+///
+/// let mut result = parse("SELECT a FROM t").unwrap();
+/// assert!(validate(&result).is_ok());
+///
+/// if let NodeEnum::SelectStmt(select) = result.stmts_mut().remove(0) {
+///     select.from_clause[0].node = Some(NodeEnum::RangeVar(Default::default()));
+/// }
+/// let errors = validate(&result).unwrap_err();
+/// assert_eq!(
+///     errors[0].to_string(),
+///     "stmts[0].from_clause[0].relname: RangeVar.relname must not be empty"
+/// );
+/// ```
+pub fn validate(result: &ParseResult) -> Result<(), Vec<ValidationError>> {
+    let mut validator = Validator {
+        path: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    for (index, raw_stmt) in result.protobuf.stmts.iter().enumerate() {
+        validator.path.push(PathSegment::Field("stmts"));
+        validator.path.push(PathSegment::Index(index));
+        match raw_stmt.stmt.as_ref().and_then(|s| s.node.as_ref()) {
+            Some(stmt) => validator.node(stmt.to_ref()),
+            None => validator.error(NodeKind::RawStmt, &[], "the statement is empty"),
+        }
+        validator.path.truncate(0);
+    }
+
+    if validator.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(validator.errors)
+    }
+}
+
+/// A structural problem found by [`validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The path from the statements of the parse result to the offending node or field.
+    pub path: AstPath,
+    /// The kind of node the problem was found in.
+    pub kind: NodeKind,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+struct Validator {
+    path: Vec<PathSegment>,
+    errors: Vec<ValidationError>,
+}
+
+impl Validator {
+    /// Records a problem with the current node, or a field below it when `segments` is not
+    /// empty
+    fn error(&mut self, kind: NodeKind, segments: &[PathSegment], message: impl Into<String>) {
+        let mut path = self.path.clone();
+        path.extend_from_slice(segments);
+        self.errors.push(ValidationError {
+            path: AstPath(path),
+            kind,
+            message: message.into(),
+        });
+    }
+
+    fn node(&mut self, node: NodeRef<'_>) {
+        self.check(node);
+
+        for (field, value) in node.fields() {
+            self.path.push(PathSegment::Field(field));
+            self.value(node.kind(), &value);
+            self.path.pop();
+        }
+    }
+
+    fn value(&mut self, kind: NodeKind, value: &FieldValue<'_>) {
+        match value {
+            FieldValue::Node(node) => self.node(*node),
+            FieldValue::List(values) => {
+                for (index, value) in values.iter().enumerate() {
+                    self.path.push(PathSegment::Index(index));
+                    self.value(kind, value);
+                    self.path.pop();
+                }
+            }
+            FieldValue::Enum(name) if name.ends_with("_UNDEFINED") => {
+                let field = match self.path.last() {
+                    Some(PathSegment::Field(field)) => *field,
+                    _ => "value",
+                };
+                self.error(
+                    kind,
+                    &[],
+                    format!("{}.{field} must be set, got {name}", kind.name()),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks the rules specific to the type of `node`
+    fn check(&mut self, node: NodeRef<'_>) {
+        use PathSegment::{Field, Index};
+
+        let kind = node.kind();
+        match node {
+            NodeRef::SelectStmt(s) if s.op() == crate::ast::SetOperation::SetopNone => {
+                let has_clauses = !s.target_list.is_empty()
+                    || !s.from_clause.is_empty()
+                    || s.where_clause.is_some()
+                    || !s.group_clause.is_empty()
+                    || s.having_clause.is_some();
+                if !s.values_lists.is_empty() && has_clauses {
+                    self.error(
+                        kind,
+                        &[Field("values_lists")],
+                        "SelectStmt.values_lists cannot be combined with a target list, FROM, WHERE, GROUP BY or HAVING",
+                    );
+                }
+                if s.larg.is_some() || s.rarg.is_some() {
+                    self.error(
+                        kind,
+                        &[],
+                        "SelectStmt.larg and rarg are only allowed in set operations",
+                    );
+                }
+                for (index, target) in s.target_list.iter().enumerate() {
+                    match &target.node {
+                        Some(crate::NodeEnum::ResTarget(t)) if t.val.is_none() => self.error(
+                            NodeKind::ResTarget,
+                            &[Field("target_list"), Index(index), Field("val")],
+                            "ResTarget.val must be set in a target list",
+                        ),
+                        Some(crate::NodeEnum::ResTarget(_)) => {}
+                        _ => self.error(
+                            kind,
+                            &[Field("target_list"), Index(index)],
+                            "SelectStmt.target_list must only contain ResTarget nodes",
+                        ),
+                    }
+                }
+            }
+            NodeRef::SelectStmt(s) if s.larg.is_none() || s.rarg.is_none() => self.error(
+                kind,
+                &[],
+                "SelectStmt.larg and rarg must both be set in a set operation",
+            ),
+            NodeRef::RangeVar(r) if r.relname.is_empty() => self.error(
+                kind,
+                &[Field("relname")],
+                "RangeVar.relname must not be empty",
+            ),
+            NodeRef::FuncCall(f) => {
+                if f.funcname.is_empty() {
+                    self.error(
+                        kind,
+                        &[Field("funcname")],
+                        "FuncCall.funcname must not be empty",
+                    );
+                }
+                for (index, name) in f.funcname.iter().enumerate() {
+                    if !name.is_string() {
+                        self.error(
+                            kind,
+                            &[Field("funcname"), Index(index)],
+                            "FuncCall.funcname must only contain String nodes",
+                        );
+                    }
+                }
+            }
+            NodeRef::ColumnRef(c) => {
+                if c.fields.is_empty() {
+                    self.error(
+                        kind,
+                        &[Field("fields")],
+                        "ColumnRef.fields must not be empty",
+                    );
+                }
+                for (index, field) in c.fields.iter().enumerate() {
+                    let last = index + 1 == c.fields.len();
+                    if !(field.is_string() || (last && field.is_a_star())) {
+                        self.error(
+                            kind,
+                            &[Field("fields"), Index(index)],
+                            "ColumnRef.fields must contain String nodes, optionally followed by A_Star",
+                        );
+                    }
+                }
+            }
+            NodeRef::TypeName(t) if t.names.is_empty() && t.type_oid == 0 => {
+                self.error(kind, &[Field("names")], "TypeName.names must not be empty")
+            }
+            NodeRef::AExpr(e) => {
+                if e.name.is_empty() {
+                    self.error(kind, &[Field("name")], "A_Expr.name must not be empty");
+                }
+                if e.rexpr.is_none() {
+                    self.error(kind, &[Field("rexpr")], "A_Expr.rexpr must be set");
+                }
+            }
+            NodeRef::BoolExpr(b) => {
+                let valid = match b.boolop() {
+                    crate::ast::BoolExprType::NotExpr => b.args.len() == 1,
+                    _ => b.args.len() >= 2,
+                };
+                if !valid {
+                    self.error(
+                        kind,
+                        &[Field("args")],
+                        format!(
+                            "BoolExpr.args has the wrong number of arguments for {}",
+                            b.boolop().as_str_name()
+                        ),
+                    );
+                }
+            }
+            NodeRef::AConst(c) if !c.isnull && c.val.is_none() => self.error(
+                kind,
+                &[Field("val")],
+                "A_Const.val must be set unless the constant is NULL",
+            ),
+            NodeRef::InsertStmt(s) if s.relation.is_none() => self.error(
+                kind,
+                &[Field("relation")],
+                "InsertStmt.relation must be set",
+            ),
+            NodeRef::UpdateStmt(s) if s.relation.is_none() => self.error(
+                kind,
+                &[Field("relation")],
+                "UpdateStmt.relation must be set",
+            ),
+            NodeRef::DeleteStmt(s) if s.relation.is_none() => self.error(
+                kind,
+                &[Field("relation")],
+                "DeleteStmt.relation must be set",
+            ),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::BoolExprType;
+    use crate::{parse, NodeEnum};
+
+    fn errors(result: &ParseResult) -> Vec<String> {
+        match validate(result) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn it_accepts_parsed_queries() {
+        let queries = [
+            "SELECT a, b.*, count(*) FROM t JOIN u ON t.id = u.id WHERE NOT a AND b OR c GROUP BY a HAVING count(*) > 1 ORDER BY 1 DESC NULLS LAST LIMIT 5",
+            "VALUES (1, 'a'), (2, NULL)",
+            "SELECT 1 UNION ALL SELECT 2 EXCEPT SELECT 3",
+            "WITH x AS (SELECT 1) INSERT INTO t (a) SELECT * FROM x ON CONFLICT (a) DO UPDATE SET a = excluded.a RETURNING *",
+            "UPDATE t SET a = a + 1 FROM u WHERE t.id = u.id",
+            "DELETE FROM t USING u WHERE t.id = u.id",
+            "CREATE TABLE t (id bigint PRIMARY KEY, a text[] NOT NULL DEFAULT '{}', CHECK (id > 0))",
+            "CREATE INDEX CONCURRENTLY i ON t USING gin (a) WHERE id > 0",
+            "ALTER TABLE t ADD COLUMN b int, DROP COLUMN c CASCADE",
+            "CREATE VIEW v AS SELECT CAST(a AS numeric(10, 2)), b::text FROM t",
+            "SELECT CASE WHEN a THEN 1 ELSE 2 END, a IS NULL, a BETWEEN 1 AND 2, EXISTS (SELECT 1), sum(a) OVER (PARTITION BY b)",
+            "BEGIN; SET search_path = public; COMMIT",
+            "GRANT SELECT ON t TO r",
+            "COPY t FROM STDIN",
+        ];
+        for query in queries {
+            let result = parse(query).unwrap();
+            assert_eq!(errors(&result), Vec::<String>::new(), "{query}");
+        }
+    }
+
+    #[test]
+    fn it_reports_values_mixed_with_a_target_list() {
+        let mut result = parse("SELECT a FROM t").unwrap();
+        let values = parse("VALUES (1)").unwrap();
+        let NodeEnum::SelectStmt(values) = values.stmts()[0] else {
+            unreachable!()
+        };
+        if let NodeEnum::SelectStmt(select) = result.stmts_mut().remove(0) {
+            select.values_lists = values.values_lists.clone();
+        }
+        assert_eq!(
+            errors(&result),
+            ["stmts[0].values_lists: SelectStmt.values_lists cannot be combined with a target list, FROM, WHERE, GROUP BY or HAVING"]
+        );
+    }
+
+    #[test]
+    fn it_reports_empty_names_and_missing_operands() {
+        let mut result = parse("SELECT lower(a) FROM t WHERE b = 1 AND c").unwrap();
+        result.stmts_mut()[0].walk_mut(|node, _| match node {
+            NodeEnum::FuncCall(f) => f.funcname.clear(),
+            NodeEnum::AExpr(e) => e.rexpr = None,
+            NodeEnum::BoolExpr(b) => b.args.truncate(1),
+            _ => {}
+        });
+
+        let errors = errors(&result);
+        assert_eq!(
+            errors,
+            [
+                "stmts[0].target_list[0].val.funcname: FuncCall.funcname must not be empty",
+                "stmts[0].where_clause.args: BoolExpr.args has the wrong number of arguments for AND_EXPR",
+                "stmts[0].where_clause.args[0].rexpr: A_Expr.rexpr must be set",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_undefined_enums() {
+        let mut result = parse("SELECT a FROM t WHERE a AND b").unwrap();
+        result.stmts_mut()[0].walk_mut(|node, _| {
+            if let NodeEnum::BoolExpr(b) = node {
+                b.boolop = BoolExprType::Undefined as i32;
+            }
+        });
+
+        let errors = validate(&result).unwrap_err();
+        assert_eq!(errors[0].kind, NodeKind::BoolExpr);
+        assert_eq!(
+            errors[0].to_string(),
+            "stmts[0].where_clause.boolop: BoolExpr.boolop must be set, got BOOL_EXPR_TYPE_UNDEFINED"
+        );
+    }
+
+    #[test]
+    fn it_validates_before_deparsing() {
+        let mut result = parse("INSERT INTO t VALUES (1)").unwrap();
+        if let NodeEnum::InsertStmt(insert) = result.stmts_mut().remove(0) {
+            insert.relation = None;
+        }
+        assert_eq!(
+            result.deparse_validated(),
+            Err(crate::Error::Validation(ValidationError {
+                path: AstPath(vec![
+                    PathSegment::Field("stmts"),
+                    PathSegment::Index(0),
+                    PathSegment::Field("relation"),
+                ]),
+                kind: NodeKind::InsertStmt,
+                message: "InsertStmt.relation must be set".to_string(),
+            }))
+        );
+    }
+}
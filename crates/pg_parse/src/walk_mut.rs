@@ -0,0 +1,586 @@
+use protobuf::Node;
+
+pg_parse_macros::walk_mut_codegen!();
+
+/// Describes the position of the node currently visited by [`NodeEnum::walk_mut`] and collects
+/// the changes the callback wants to make to the node's parent.
+#[derive(Debug)]
+pub struct WalkContext {
+    depth: usize,
+    field: Option<&'static str>,
+    index: Option<usize>,
+    slot: Slot,
+    skip_children: bool,
+    removed: bool,
+    before: Vec<NodeEnum>,
+    after: Vec<NodeEnum>,
+}
+
+/// How the parent holds the visited node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    /// The node `walk_mut` was called on
+    Root,
+    /// An optional field
+    Field,
+    /// An element of a list
+    List,
+}
+
+impl WalkContext {
+    fn new(depth: usize, field: Option<&'static str>, index: Option<usize>, slot: Slot) -> Self {
+        Self {
+            depth,
+            field,
+            index,
+            slot,
+            skip_children: false,
+            removed: false,
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+
+    /// The number of ancestors between this node and the node `walk_mut` was called on.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The name of the parent's field holding this node, or None for the root.
+    pub fn field(&self) -> Option<&'static str> {
+        self.field
+    }
+
+    /// The position of this node if its parent holds it in a list.
+    ///
+    /// This is the position at the time of the visit, so it accounts for siblings that were
+    /// inserted or removed earlier in the walk.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Do not descend into the children of this node.
+    pub fn skip_children(&mut self) {
+        self.skip_children = true;
+    }
+
+    /// Removes this node from its parent once the callback returns.
+    ///
+    /// A node in a list is removed from the list, a node in a field leaves the field empty. The
+    /// children of a removed node are not visited.
+    pub fn remove(&mut self) -> Result<()> {
+        if self.slot == Slot::Root {
+            return Err(Error::Mutation(
+                "the node the walk started from cannot be removed".into(),
+            ));
+        }
+        self.removed = true;
+        self.skip_children = true;
+        Ok(())
+    }
+
+    /// Inserts a sibling in front of this node once the callback returns.
+    ///
+    /// Only nodes held in a list have siblings. Inserted nodes are not visited.
+    pub fn insert_before(&mut self, node: NodeEnum) -> Result<()> {
+        self.check_list("insert a sibling")?;
+        self.before.push(node);
+        Ok(())
+    }
+
+    /// Inserts a sibling after this node once the callback returns.
+    ///
+    /// Only nodes held in a list have siblings. Inserted nodes are not visited. Calling this
+    /// multiple times inserts the siblings in the order of the calls.
+    pub fn insert_after(&mut self, node: NodeEnum) -> Result<()> {
+        self.check_list("insert a sibling")?;
+        self.after.push(node);
+        Ok(())
+    }
+
+    fn check_list(&self, action: &str) -> Result<()> {
+        if self.slot == Slot::List {
+            Ok(())
+        } else {
+            Err(Error::Mutation(format!(
+                "cannot {action} of a node that is not held in a list"
+            )))
+        }
+    }
+}
+
+impl NodeEnum {
+    /// Visits this node and all of its descendants in depth-first pre-order, handing out a
+    /// mutable reference to each of them.
+    ///
+    /// The callback runs before the children of a node are visited, so it can replace the node
+    /// (`*node = ...`) and the walk continues with the children of the replacement. Through the
+    /// [`WalkContext`] it can also remove the node or insert siblings next to it. Nodes that
+    /// are stored in a typed field rather than a `Node` (for example `InsertStmt.relation`) are
+    /// visited as well, but they must keep their type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use pg_parse::{parse, NodeEnum};
+    ///
+    /// let mut result = parse("SELECT a, b FROM users").unwrap();
+    /// result.stmts_mut()[0].walk_mut(|node, ctx| match node {
+    ///     NodeEnum::RangeVar(r) => r.relname = "accounts".to_string(),
+    ///     NodeEnum::ResTarget(_) if ctx.index() == Some(1) => ctx.remove().unwrap(),
+    ///     _ => {}
+    /// });
+    /// assert_eq!(result.deparse().unwrap(), "SELECT a FROM accounts");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the callback replaces a node held in a typed field with a node of another type,
+    /// or inserts a node of another type next to it.
+    pub fn walk_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut NodeEnum, &mut WalkContext),
+    {
+        let mut walker = Walker {
+            callback: &mut f,
+            depth: 0,
+        };
+        walker.visit(self, WalkContext::new(0, None, None, Slot::Root));
+    }
+}
+
+/// Drives the callback of [`NodeEnum::walk_mut`] through the tree.
+///
+/// The callback is a trait object so the generated `walk_children` is only compiled once.
+struct Walker<'f> {
+    callback: &'f mut dyn FnMut(&mut NodeEnum, &mut WalkContext),
+    depth: usize,
+}
+
+impl Walker<'_> {
+    /// Visits `node` and its children and returns the changes requested for its parent.
+    fn visit(&mut self, node: &mut NodeEnum, mut ctx: WalkContext) -> WalkContext {
+        (self.callback)(node, &mut ctx);
+
+        if !ctx.skip_children {
+            self.depth += 1;
+            walk_children(node, self);
+            self.depth -= 1;
+        }
+
+        ctx
+    }
+
+    fn context(&self, field: &'static str, index: Option<usize>, slot: Slot) -> WalkContext {
+        WalkContext::new(self.depth, Some(field), index, slot)
+    }
+
+    /// Visits the node held in an optional field and returns whether it should be removed.
+    fn visit_node(&mut self, node: &mut Node, field: &'static str) -> bool {
+        match node.node.as_mut() {
+            Some(n) => {
+                self.visit(n, self.context(field, None, Slot::Field))
+                    .removed
+            }
+            None => false,
+        }
+    }
+
+    fn visit_list(&mut self, nodes: &mut Vec<Node>, field: &'static str) {
+        self.edit_list(
+            nodes,
+            |walker, node, index| match node.node.as_mut() {
+                Some(n) => walker.visit(n, walker.context(field, Some(index), Slot::List)),
+                None => walker.context(field, Some(index), Slot::List),
+            },
+            |node| Node { node: Some(node) },
+        );
+    }
+
+    /// Visits a node stored in a typed field and returns whether it should be removed.
+    fn visit_typed<T: Default>(
+        &mut self,
+        field: &'static str,
+        slot: &mut T,
+        wrap: fn(T) -> NodeEnum,
+        unwrap: fn(NodeEnum) -> Option<T>,
+    ) -> bool {
+        let ctx = self.context(field, None, Slot::Field);
+        self.visit_wrapped(ctx, slot, wrap, unwrap).removed
+    }
+
+    // No node of the current protobuf definition has a repeated field of a specific node type,
+    // but the generated code supports them.
+    #[allow(dead_code)]
+    fn visit_typed_list<T: Default>(
+        &mut self,
+        field: &'static str,
+        items: &mut Vec<T>,
+        wrap: fn(T) -> NodeEnum,
+        unwrap: fn(NodeEnum) -> Option<T>,
+    ) {
+        self.edit_list(
+            items,
+            |walker, item, index| {
+                let ctx = walker.context(field, Some(index), Slot::List);
+                walker.visit_wrapped(ctx, item, wrap, unwrap)
+            },
+            |node| {
+                unwrap(node).unwrap_or_else(|| {
+                    panic!("a node of another type was inserted into field `{field}`")
+                })
+            },
+        );
+    }
+
+    /// Visits a node stored in a typed field by moving it into a `NodeEnum` for the duration of
+    /// the visit.
+    fn visit_wrapped<T: Default>(
+        &mut self,
+        ctx: WalkContext,
+        slot: &mut T,
+        wrap: fn(T) -> NodeEnum,
+        unwrap: fn(NodeEnum) -> Option<T>,
+    ) -> WalkContext {
+        let mut node = wrap(std::mem::take(slot));
+        let ctx = self.visit(&mut node, ctx);
+        match unwrap(node) {
+            Some(value) => *slot = value,
+            None => panic!(
+                "the node in field `{}` was replaced with a node of another type",
+                ctx.field.unwrap_or_default()
+            ),
+        }
+        ctx
+    }
+
+    /// Visits every element of a list and applies the requested removals and insertions.
+    fn edit_list<T>(
+        &mut self,
+        items: &mut Vec<T>,
+        mut visit: impl FnMut(&mut Self, &mut T, usize) -> WalkContext,
+        into: impl Fn(NodeEnum) -> T,
+    ) {
+        let mut i = 0;
+        while i < items.len() {
+            let ctx = visit(self, &mut items[i], i);
+
+            let inserted = ctx.before.len();
+            items.splice(i..i, ctx.before.into_iter().map(&into));
+            i += inserted;
+
+            if ctx.removed {
+                items.remove(i);
+            } else {
+                i += 1;
+            }
+
+            let inserted = ctx.after.len();
+            items.splice(i..i, ctx.after.into_iter().map(&into));
+            i += inserted;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protobuf::{self, Node};
+    use crate::{Error, NodeEnum};
+
+    fn node(node: NodeEnum) -> Node {
+        Node { node: Some(node) }
+    }
+
+    fn column(name: &str) -> Node {
+        node(NodeEnum::ColumnRef(protobuf::ColumnRef {
+            fields: vec![node(NodeEnum::String(protobuf::String {
+                sval: name.to_string(),
+            }))],
+            location: -1,
+        }))
+    }
+
+    fn target(val: Node) -> Node {
+        node(NodeEnum::ResTarget(Box::new(protobuf::ResTarget {
+            val: Some(Box::new(val)),
+            location: -1,
+            ..Default::default()
+        })))
+    }
+
+    // SELECT a, b FROM t
+    fn select() -> NodeEnum {
+        NodeEnum::SelectStmt(Box::new(protobuf::SelectStmt {
+            target_list: vec![target(column("a")), target(column("b"))],
+            from_clause: vec![node(NodeEnum::RangeVar(protobuf::RangeVar {
+                relname: "t".to_string(),
+                inh: true,
+                relpersistence: "p".to_string(),
+                location: -1,
+                ..Default::default()
+            }))],
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn it_visits_every_node_in_pre_order() {
+        let mut visited = vec![];
+        select().walk_mut(|node, ctx| {
+            visited.push((
+                ctx.depth(),
+                ctx.field(),
+                ctx.index(),
+                node.to_ref().location(),
+                matches!(node, NodeEnum::String(_)),
+            ));
+        });
+
+        assert_eq!(
+            visited,
+            vec![
+                (0, None, None, None, false),
+                (1, Some("target_list"), Some(0), None, false),
+                (2, Some("val"), None, None, false),
+                (3, Some("fields"), Some(0), None, true),
+                (1, Some("target_list"), Some(1), None, false),
+                (2, Some("val"), None, None, false),
+                (3, Some("fields"), Some(0), None, true),
+                (1, Some("from_clause"), Some(0), None, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_replaces_nodes() {
+        let mut stmt = select();
+        stmt.walk_mut(|node, _| {
+            if let NodeEnum::ColumnRef(c) = node {
+                if c.fields.len() == 1 {
+                    *node = NodeEnum::AConst(protobuf::AConst {
+                        val: Some(protobuf::a_const::Val::Ival(protobuf::Integer { ival: 1 })),
+                        location: -1,
+                        ..Default::default()
+                    });
+                }
+            }
+        });
+
+        let mut integers = 0;
+        stmt.walk_mut(|node, _| {
+            assert!(!matches!(node, NodeEnum::ColumnRef(_)));
+            if let NodeEnum::Integer(i) = node {
+                assert_eq!(i.ival, 1);
+                integers += 1;
+            }
+        });
+        assert_eq!(integers, 2);
+    }
+
+    #[test]
+    fn it_skips_children() {
+        let mut count = 0;
+        select().walk_mut(|node, ctx| {
+            count += 1;
+            if matches!(node, NodeEnum::ResTarget(_)) {
+                ctx.skip_children();
+            }
+        });
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn it_visits_typed_fields() {
+        let mut stmt = NodeEnum::InsertStmt(Box::new(protobuf::InsertStmt {
+            relation: Some(protobuf::RangeVar {
+                relname: "t".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }));
+        stmt.walk_mut(|node, ctx| {
+            if let NodeEnum::RangeVar(r) = node {
+                assert_eq!(ctx.field(), Some("relation"));
+                r.relname = "u".to_string();
+            }
+        });
+
+        let NodeEnum::InsertStmt(insert) = stmt else {
+            unreachable!()
+        };
+        assert_eq!(insert.relation.unwrap().relname, "u");
+    }
+
+    #[test]
+    #[should_panic(expected = "the node in field `relation` was replaced")]
+    fn it_panics_when_changing_the_type_of_a_typed_field() {
+        let mut stmt = NodeEnum::InsertStmt(Box::new(protobuf::InsertStmt {
+            relation: Some(protobuf::RangeVar::default()),
+            ..Default::default()
+        }));
+        stmt.walk_mut(|node, _| {
+            if let NodeEnum::RangeVar(_) = node {
+                *node = NodeEnum::Alias(protobuf::Alias::default());
+            }
+        });
+    }
+
+    #[test]
+    fn it_removes_list_elements() {
+        let mut stmt = select();
+        let mut visited = vec![];
+        stmt.walk_mut(|node, ctx| {
+            if let NodeEnum::ResTarget(_) = node {
+                visited.push(ctx.index());
+                if visited.len() == 1 {
+                    ctx.remove().unwrap();
+                }
+            }
+        });
+
+        // The second target moved to the first position after the removal
+        assert_eq!(visited, vec![Some(0), Some(0)]);
+        let NodeEnum::SelectStmt(select) = stmt else {
+            unreachable!()
+        };
+        assert_eq!(select.target_list.len(), 1);
+    }
+
+    #[test]
+    fn it_removes_fields() {
+        let mut stmt = select();
+        stmt.walk_mut(|node, ctx| {
+            if let NodeEnum::ColumnRef(_) = node {
+                assert_eq!(ctx.field(), Some("val"));
+                ctx.remove().unwrap();
+            }
+        });
+
+        let NodeEnum::SelectStmt(select) = stmt else {
+            unreachable!()
+        };
+        assert!(select.target_list.iter().all(|t| match &t.node {
+            Some(NodeEnum::ResTarget(t)) => t.val.is_none(),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn it_inserts_siblings_without_visiting_them() {
+        let mut stmt = select();
+        let mut visited = 0;
+        stmt.walk_mut(|node, ctx| {
+            if let NodeEnum::ResTarget(_) = node {
+                visited += 1;
+                if ctx.index() == Some(0) {
+                    ctx.insert_before(target(column("x")).node.unwrap())
+                        .unwrap();
+                    ctx.insert_after(target(column("y")).node.unwrap()).unwrap();
+                    ctx.insert_after(target(column("z")).node.unwrap()).unwrap();
+                }
+            }
+        });
+        assert_eq!(visited, 2);
+
+        let mut names = vec![];
+        stmt.walk_mut(|node, _| {
+            if let NodeEnum::String(s) = node {
+                names.push(s.sval.clone());
+            }
+        });
+        assert_eq!(names, vec!["x", "a", "y", "z", "b"]);
+    }
+
+    #[test]
+    fn it_rejects_invalid_mutations() {
+        select().walk_mut(|node, ctx| match node {
+            NodeEnum::SelectStmt(_) => {
+                assert!(matches!(ctx.remove(), Err(Error::Mutation(_))));
+                assert!(matches!(
+                    ctx.insert_after(NodeEnum::Alias(Default::default())),
+                    Err(Error::Mutation(_))
+                ));
+            }
+            NodeEnum::ColumnRef(_) => {
+                assert!(matches!(
+                    ctx.insert_before(NodeEnum::Alias(Default::default())),
+                    Err(Error::Mutation(_))
+                ));
+            }
+            _ => {}
+        });
+    }
+
+    #[test]
+    fn it_removes_typed_fields() {
+        let mut stmt = NodeEnum::InsertStmt(Box::new(protobuf::InsertStmt {
+            relation: Some(protobuf::RangeVar::default()),
+            ..Default::default()
+        }));
+        stmt.walk_mut(|node, ctx| {
+            if let NodeEnum::RangeVar(_) = node {
+                ctx.remove().unwrap();
+            }
+        });
+
+        let NodeEnum::InsertStmt(insert) = stmt else {
+            unreachable!()
+        };
+        assert!(insert.relation.is_none());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn it_replaces_a_column_with_a_function_call() {
+        let mut result = crate::parse("SELECT a, b FROM t WHERE c = 1").unwrap();
+        let call = crate::parse("SELECT lower(b)").unwrap().protobuf.stmts[0]
+            .stmt
+            .as_ref()
+            .and_then(|s| match &s.node {
+                Some(NodeEnum::SelectStmt(s)) => s.target_list[0].node.clone(),
+                _ => None,
+            })
+            .and_then(|t| match t {
+                NodeEnum::ResTarget(t) => t.val.and_then(|v| v.node),
+                _ => None,
+            })
+            .unwrap();
+
+        let mut targets = 0;
+        result.stmts_mut()[0].walk_mut(|node, ctx| match node {
+            NodeEnum::ColumnRef(c) if matches!(&c.fields[0].node, Some(NodeEnum::String(s)) if s.sval == "b") => {
+                *node = call.clone();
+                ctx.skip_children();
+            }
+            NodeEnum::ResTarget(_) => {
+                targets += 1;
+                if targets == 1 {
+                    ctx.remove().unwrap();
+                }
+            }
+            NodeEnum::AExpr(_) if ctx.field() == Some("where_clause") => {
+                ctx.remove().unwrap();
+            }
+            _ => {}
+        });
+
+        assert_eq!(result.deparse().unwrap(), "SELECT lower(b) FROM t");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn it_rewrites_a_parsed_query() {
+        let mut result = crate::parse("SELECT a FROM t WHERE b = 1").unwrap();
+        for stmt in result.stmts_mut() {
+            stmt.walk_mut(|node, _| match node {
+                NodeEnum::String(s) => s.sval = s.sval.to_uppercase(),
+                NodeEnum::Integer(i) => i.ival += 1,
+                _ => {}
+            });
+        }
+        assert_eq!(
+            result.deparse().unwrap(),
+            "SELECT \"A\" FROM t WHERE \"B\" = 2"
+        );
+    }
+}
@@ -1,50 +1,92 @@
+use glob::glob;
 use std::env;
 use std::fs;
-use std::io::Write;
 use std::path::PathBuf;
+use std::process::Command;
 
-// This should match the version used by pg_parse crate
+// The default version, used when pg_parse does not select one through its features.
 // You can configure this via environment variable PG_QUERY_VERSION if needed
 static LIBPG_QUERY_TAG: &str = "17-6.1.0";
+static LIBPG_QUERY_REPO: &str = "https://github.com/pganalyze/libpg_query.git";
+static LIBRARY_NAME: &str = "pg_query";
+
+/// Returns the version matching the grammar feature pg_parse enables on this crate, checked in
+/// the same order as pg_parse's build script does.
+fn libpg_query_tag() -> &'static str {
+    if env::var_os("CARGO_FEATURE_POSTGRES_15").is_some() {
+        "15-5.3.0"
+    } else if env::var_os("CARGO_FEATURE_POSTGRES_16").is_some() {
+        "16-6.1.0"
+    } else {
+        LIBPG_QUERY_TAG
+    }
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Allow version override via environment variable
-    let version = env::var("PG_QUERY_VERSION").unwrap_or_else(|_| LIBPG_QUERY_TAG.to_string());
+    let version = env::var("PG_QUERY_VERSION").unwrap_or_else(|_| libpg_query_tag().to_string());
 
     let out_dir = PathBuf::from(env::var("OUT_DIR")?);
     let vendor_dir = out_dir.join("vendor");
-    let proto_dir = vendor_dir.join("libpg_query").join(&version);
-    let proto_path = proto_dir.join("pg_query.proto");
-    let stamp_file = proto_dir.join(".stamp");
+    let libpg_query_dir = vendor_dir.join("libpg_query").join(&version);
+    let proto_path = libpg_query_dir.join("protobuf").join("pg_query.proto");
+    let stamp_file = libpg_query_dir.join(".stamp");
 
-    // Download proto file if not already present
+    // Clone libpg_query if not already present. The macros need the protobuf definition for
+    // code generation and the parser itself to check SQL at compile time.
     if !stamp_file.exists() {
-        println!(
-            "cargo:warning=Downloading pg_query.proto for libpg_query {}",
-            version
-        );
+        println!("cargo:warning=Cloning libpg_query {}", version);
 
-        // Create directories
-        fs::create_dir_all(&proto_dir)?;
+        fs::create_dir_all(&vendor_dir)?;
 
-        // Download the proto file
-        let proto_url = format!(
-            "https://raw.githubusercontent.com/pganalyze/libpg_query/{}/protobuf/pg_query.proto",
-            version
-        );
+        let status = Command::new("git")
+            .args([
+                "clone",
+                "--depth",
+                "1",
+                "--branch",
+                &version,
+                LIBPG_QUERY_REPO,
+                libpg_query_dir.to_str().unwrap(),
+            ])
+            .status()?;
 
-        let response = ureq::get(&proto_url).call()?;
-        let proto_content = response.into_string()?;
+        if !status.success() {
+            return Err("Failed to clone libpg_query".into());
+        }
 
-        // Write proto file
-        let mut file = fs::File::create(&proto_path)?;
-        file.write_all(proto_content.as_bytes())?;
-
-        // Create stamp file
         fs::File::create(&stamp_file)?;
+    }
 
-        println!("cargo:warning=Successfully downloaded pg_query.proto");
+    // Compile the C library. Proc macros always run on the host, so unlike pg_parse there is
+    // no need to handle other targets.
+    let mut build = cc::Build::new();
+    build
+        .files(
+            glob(libpg_query_dir.join("src/*.c").to_str().unwrap())
+                .unwrap()
+                .map(|p| p.unwrap()),
+        )
+        .files(
+            glob(libpg_query_dir.join("src/postgres/*.c").to_str().unwrap())
+                .unwrap()
+                .map(|p| p.unwrap()),
+        )
+        .file(libpg_query_dir.join("vendor/protobuf-c/protobuf-c.c"))
+        .file(libpg_query_dir.join("vendor/xxhash/xxhash.c"))
+        .file(libpg_query_dir.join("protobuf/pg_query.pb-c.c"))
+        .include(&libpg_query_dir)
+        .include(libpg_query_dir.join("vendor"))
+        .include(libpg_query_dir.join("src/postgres/include"))
+        .include(libpg_query_dir.join("src/include"))
+        .warnings(false);
+    if env::var("TARGET")?.contains("windows") {
+        build.include(libpg_query_dir.join("src/postgres/include/port/win32"));
+        if env::var("TARGET")?.contains("msvc") {
+            build.include(libpg_query_dir.join("src/postgres/include/port/win32_msvc"));
+        }
     }
+    build.compile(LIBRARY_NAME);
 
     // Set environment variable for the proc macro
     println!(
@@ -54,6 +96,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Tell cargo to rerun if the stamp file changes
     println!("cargo:rerun-if-changed={}", stamp_file.display());
+    println!("cargo:rerun-if-env-changed=PG_QUERY_VERSION");
 
     Ok(())
 }
@@ -0,0 +1,164 @@
+use proc_macro2::{Span, TokenStream};
+use prost_reflect::{DynamicMessage, ReflectMessage, Value};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{Attribute, Ident, LitStr, Token, Visibility};
+
+use crate::parser;
+use crate::proto_analyser::ProtoAnalyzer;
+
+/// Either a single string literal or a list of `const NAME = "...";` items
+enum Input {
+    Literal(LitStr),
+    Items(Vec<Item>),
+}
+
+struct Item {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    sql: LitStr,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            let literal = input.parse()?;
+            input.parse::<Option<Token![,]>>()?;
+            return Ok(Input::Literal(literal));
+        }
+
+        let mut items = Vec::new();
+        while !input.is_empty() {
+            let attrs = input.call(Attribute::parse_outer)?;
+            let vis = input.parse()?;
+            input.parse::<Token![const]>()?;
+            let name = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let sql = input.parse()?;
+            input.parse::<Token![;]>()?;
+            items.push(Item {
+                attrs,
+                vis,
+                name,
+                sql,
+            });
+        }
+        Ok(Input::Items(items))
+    }
+}
+
+pub fn checked_sql_mod(analyser: &ProtoAnalyzer, input: TokenStream) -> syn::Result<TokenStream> {
+    match syn::parse2(input)? {
+        Input::Literal(literal) => {
+            check(&literal, parser::check)?;
+            Ok(quote! { #literal })
+        }
+        Input::Items(items) => {
+            let mut tokens = TokenStream::new();
+            for Item {
+                attrs,
+                vis,
+                name,
+                sql,
+            } in items
+            {
+                let result = check(&sql, |sql| parser::parse(analyser, sql))?;
+                let stmts = statements(&result);
+                let [stmt] = stmts.as_slice() else {
+                    return Err(syn::Error::new(
+                        sql.span(),
+                        format!("expected exactly one statement, found {}", stmts.len()),
+                    ));
+                };
+
+                let kind = stmt.descriptor().name().to_string();
+                let param_count = param_count(stmt);
+                let kind_name = format_ident!("{}_KIND", name);
+                let params_name = format_ident!("{}_PARAMS", name);
+                let kind_doc = format!("The statement kind of [`{name}`]");
+                let params_doc = format!("The number of parameters [`{name}`] expects");
+
+                tokens.extend(quote! {
+                    #(#attrs)*
+                    #vis const #name: &str = #sql;
+                    #[doc = #kind_doc]
+                    #vis const #kind_name: &str = #kind;
+                    #[doc = #params_doc]
+                    #vis const #params_name: usize = #param_count;
+                });
+            }
+            Ok(tokens)
+        }
+    }
+}
+
+/// Parses the literal's SQL with `parse`, turning syntax errors into compile errors at the error
+/// position
+fn check<T>(
+    literal: &LitStr,
+    parse: impl FnOnce(&str) -> Result<T, parser::SyntaxError>,
+) -> syn::Result<T> {
+    let sql = literal.value();
+    parse(&sql).map_err(|e| {
+        let span = error_span(literal, &sql, e.cursorpos).unwrap_or_else(|| literal.span());
+        syn::Error::new(span, e.render(&sql))
+    })
+}
+
+/// Narrows the span down to the character at `cursorpos` where the compiler supports it. This
+/// only works for literals without escapes, as the offsets would not line up otherwise.
+fn error_span(literal: &LitStr, sql: &str, cursorpos: usize) -> Option<Span> {
+    let token = literal.token();
+    let source = token.to_string();
+    let start = source.find('"')? + 1;
+    let end = source.rfind('"')?;
+    if start > end || &source[start..end] != sql {
+        return None;
+    }
+
+    let offset = sql.char_indices().nth(cursorpos.checked_sub(1)?)?.0;
+    let len = sql[offset..].chars().next()?.len_utf8();
+    token.subspan(start + offset..start + offset + len)
+}
+
+fn statements(result: &DynamicMessage) -> Vec<DynamicMessage> {
+    let Some(Value::List(stmts)) = result.get_field_by_name("stmts").as_deref().cloned() else {
+        return Vec::new();
+    };
+    stmts
+        .iter()
+        .filter_map(|raw| raw.as_message()?.get_field_by_name("stmt"))
+        .filter_map(|node| {
+            let node = node.as_message()?.clone();
+            let (_, value) = node.fields().next()?;
+            value.as_message().cloned()
+        })
+        .collect()
+}
+
+/// Returns the highest `$n` parameter number referenced anywhere in `message`
+fn param_count(message: &DynamicMessage) -> usize {
+    if message.descriptor().name() == "ParamRef" {
+        return match message.get_field_by_name("number").as_deref() {
+            Some(Value::I32(n)) => (*n).max(0) as usize,
+            _ => 0,
+        };
+    }
+
+    let max_in = |value: &Value| match value {
+        Value::Message(m) => param_count(m),
+        Value::List(values) => values
+            .iter()
+            .filter_map(Value::as_message)
+            .map(param_count)
+            .max()
+            .unwrap_or(0),
+        _ => 0,
+    };
+    message
+        .fields()
+        .map(|(_, value)| max_in(value))
+        .max()
+        .unwrap_or(0)
+}
@@ -0,0 +1,116 @@
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::proto_analyser::ProtoAnalyzer;
+
+pub fn convert_mod(analyser: ProtoAnalyzer) -> TokenStream {
+    let enum_variants = analyser.enum_variants();
+
+    let mut impls = Vec::new();
+    let mut enum_methods = Vec::new();
+    let mut ref_methods = Vec::new();
+    let mut node_methods = Vec::new();
+
+    for variant in &enum_variants {
+        let variant_ident = format_ident!("{}", variant.name);
+        let type_ident = format_ident!("{}", variant.type_name);
+        let snake = variant.name.to_case(Case::Snake);
+        let as_ident = format_ident!("as_{}", snake);
+        let is_ident = format_ident!("is_{}", snake);
+        let as_doc = format!(
+            "Returns the [`{0}`](crate::ast::{0}) if this is a `{1}` node.",
+            variant.type_name, variant.name
+        );
+        let is_doc = format!("Returns whether this is a `{}` node.", variant.name);
+
+        let (wrap, unwrap) = if variant.boxed {
+            (quote! { ::std::boxed::Box::new(value) }, quote! { *n })
+        } else {
+            (quote! { value }, quote! { n })
+        };
+
+        impls.push(quote! {
+            impl From<protobuf::#type_ident> for NodeEnum {
+                fn from(value: protobuf::#type_ident) -> Self {
+                    NodeEnum::#variant_ident(#wrap)
+                }
+            }
+
+            impl From<protobuf::#type_ident> for protobuf::Node {
+                fn from(value: protobuf::#type_ident) -> Self {
+                    NodeEnum::from(value).into()
+                }
+            }
+
+            impl TryFrom<NodeEnum> for protobuf::#type_ident {
+                type Error = NodeEnum;
+
+                fn try_from(node: NodeEnum) -> ::std::result::Result<Self, NodeEnum> {
+                    match node {
+                        NodeEnum::#variant_ident(n) => Ok(#unwrap),
+                        other => Err(other),
+                    }
+                }
+            }
+        });
+
+        enum_methods.push(quote! {
+            #[doc = #as_doc]
+            pub fn #as_ident(&self) -> Option<&protobuf::#type_ident> {
+                match self {
+                    NodeEnum::#variant_ident(n) => Some(n),
+                    _ => None,
+                }
+            }
+
+            #[doc = #is_doc]
+            pub fn #is_ident(&self) -> bool {
+                matches!(self, NodeEnum::#variant_ident(_))
+            }
+        });
+
+        ref_methods.push(quote! {
+            #[doc = #as_doc]
+            pub fn #as_ident(&self) -> Option<&'a protobuf::#type_ident> {
+                match self {
+                    NodeRef::#variant_ident(n) => Some(n),
+                    _ => None,
+                }
+            }
+
+            #[doc = #is_doc]
+            pub fn #is_ident(&self) -> bool {
+                matches!(self, NodeRef::#variant_ident(_))
+            }
+        });
+
+        node_methods.push(quote! {
+            #[doc = #as_doc]
+            pub fn #as_ident(&self) -> Option<&protobuf::#type_ident> {
+                self.node.as_ref()?.#as_ident()
+            }
+
+            #[doc = #is_doc]
+            pub fn #is_ident(&self) -> bool {
+                self.node.as_ref().is_some_and(NodeEnum::#is_ident)
+            }
+        });
+    }
+
+    quote! {
+        #(#impls)*
+
+        impl NodeEnum {
+            #(#enum_methods)*
+        }
+
+        impl<'a> NodeRef<'a> {
+            #(#ref_methods)*
+        }
+
+        impl protobuf::Node {
+            #(#node_methods)*
+        }
+    }
+}
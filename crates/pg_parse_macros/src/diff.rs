@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::proto_analyser::{FieldType, Node, ProtoAnalyzer};
+
+pub fn diff_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
+    let enum_variants = analyser.enum_variants();
+    let nodes = analyser.nodes();
+
+    let type_to_variant: HashMap<String, String> = enum_variants
+        .iter()
+        .map(|v| (v.type_name.clone(), v.name.clone()))
+        .collect();
+
+    let mut node_variant_names = Vec::new();
+    let mut node_field_handlers = Vec::new();
+
+    for node in &nodes {
+        if let Some(variant_name) = type_to_variant.get(&node.enum_variant_name) {
+            node_variant_names.push(format_ident!("{}", variant_name));
+            node_field_handlers.push(field_handlers(node));
+        }
+    }
+
+    quote! {
+        /// Compares the fields of two nodes of the same type in field declaration order.
+        #[allow(unused_variables)]
+        fn diff_fields<'a>(a: NodeRef<'a>, b: NodeRef<'a>, differ: &mut Differ<'a>) {
+            match (a, b) {
+                #((NodeRef::#node_variant_names(a), NodeRef::#node_variant_names(b)) => {#node_field_handlers}),*,
+                _ => unreachable!("diff_fields called with nodes of different types"),
+            };
+        }
+    }
+}
+
+fn field_handlers(node: &Node) -> TokenStream {
+    let handlers: Vec<TokenStream> = node
+        .stored_fields()
+        .into_iter()
+        .filter(|(_, field)| field.location.is_none())
+        .map(|(ident, field)| {
+            let label = ident.to_string().trim_start_matches("r#").to_string();
+
+            if let Some(one_of) = &field.one_of {
+                let members = node.one_of_members(&one_of.field_name);
+                if !members
+                    .iter()
+                    .all(|f| matches!(f.r#type, FieldType::Node(_)))
+                {
+                    return quote! {
+                        differ.value(#label, &a.#ident, &b.#ident, |v| format!("{v:?}"));
+                    };
+                }
+
+                let module_name = format_ident!("{}", one_of.module_name);
+                let enum_name = format_ident!("{}", one_of.enum_name);
+                let arms: Vec<_> = members
+                    .iter()
+                    .map(|f| {
+                        let variant_name =
+                            format_ident!("{}", f.one_of.as_ref().unwrap().variant_name);
+                        let to_ref = if f.r#type == FieldType::Node(None) {
+                            quote! { x.node.as_ref().map(|n| n.to_ref()) }
+                        } else {
+                            quote! { Some(x.to_ref()) }
+                        };
+                        quote! { protobuf::#module_name::#enum_name::#variant_name(x) => #to_ref }
+                    })
+                    .collect();
+                let as_ref = quote! { |v| match v { #(#arms,)* } };
+
+                return quote! {
+                    differ.node(
+                        #label,
+                        a.#ident.as_ref().and_then(#as_ref),
+                        b.#ident.as_ref().and_then(#as_ref),
+                    );
+                };
+            }
+
+            match (&field.r#type, field.repeated) {
+                (FieldType::Node(type_name), true) => {
+                    let to_refs = if type_name.is_none() {
+                        quote! { .filter_map(|n| n.node.as_ref()).map(|n| n.to_ref()) }
+                    } else {
+                        quote! { .map(|n| n.to_ref()) }
+                    };
+                    quote! {
+                        differ.list(
+                            #label,
+                            a.#ident.iter()#to_refs.collect(),
+                            b.#ident.iter()#to_refs.collect(),
+                        );
+                    }
+                }
+                (FieldType::Node(type_name), false) => {
+                    let to_ref = if type_name.is_none() {
+                        quote! { .and_then(|n| n.node.as_ref()).map(|n| n.to_ref()) }
+                    } else {
+                        quote! { .map(|n| n.to_ref()) }
+                    };
+                    quote! {
+                        differ.node(#label, a.#ident.as_ref()#to_ref, b.#ident.as_ref()#to_ref);
+                    }
+                }
+                (FieldType::Enum(type_name), false) => {
+                    let render = render_enum(type_name);
+                    quote! {
+                        differ.value(#label, &a.#ident, &b.#ident, #render);
+                    }
+                }
+                _ => quote! {
+                    differ.value(#label, &a.#ident, &b.#ident, |v| format!("{v:?}"));
+                },
+            }
+        })
+        .collect();
+
+    quote! {
+        #(#handlers)*
+    }
+}
+
+/// Renders the value of an enum field by its protobuf name, e.g. `AEXPR_OP`
+fn render_enum(type_name: &str) -> TokenStream {
+    let type_ident = format_ident!("{}", type_name.to_case(Case::Pascal));
+    quote! {
+        |v: &i32| match protobuf::#type_ident::try_from(*v) {
+            Ok(e) => e.as_str_name().to_string(),
+            Err(_) => v.to_string(),
+        }
+    }
+}
@@ -9,6 +9,7 @@ pub fn iter_mut_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
 
     let mut node_variant_names = Vec::new();
     let mut node_property_handlers = Vec::new();
+    let mut location_variant_names = Vec::new();
 
     // Create a map from type name to enum variant name
     let mut type_to_variant: std::collections::HashMap<String, String> =
@@ -19,12 +20,16 @@ pub fn iter_mut_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
 
     for node in &nodes {
         // Use the enum variant name from the Node enum
-        if let Some(variant_name) = type_to_variant.get(&node.name) {
+        if let Some(variant_name) = type_to_variant.get(&node.enum_variant_name) {
             let variant_ident = format_ident!("{}", variant_name);
-            node_variant_names.push(variant_ident);
+            node_variant_names.push(variant_ident.clone());
 
             let property_handlers = property_handlers(node);
             node_property_handlers.push(property_handlers);
+
+            if node.has_location {
+                location_variant_names.push(variant_ident);
+            }
         }
     }
 
@@ -33,10 +38,14 @@ pub fn iter_mut_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
 
         /// An iterator that provides mutable access to all nodes in an AST tree.
         ///
-        /// This iterator performs a depth-first traversal of the AST, yielding mutable
-        /// references to each node. It uses unsafe operations internally to work with
+        /// The order in which nodes are yielded is controlled by the [`TraversalOrder`]
+        /// passed to [`NodeMutIterator::with_order`]. [`NodeMutIterator::new`] traverses
+        /// the tree breadth-first. It uses unsafe operations internally to work with
         /// raw pointers in the AST structure.
         ///
+        /// Prefer [`NodeEnum::walk_mut`], which provides the same access through
+        /// mutable borrows and does not have the safety requirements below.
+        ///
         /// # Safety Requirements
         ///
         /// Users of this iterator must ensure:
@@ -54,11 +63,13 @@ pub fn iter_mut_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
         /// requirements above are violated.
         /// ```
         pub struct NodeMutIterator {
-            stack: VecDeque<NodeMut>,
+            order: TraversalOrder,
+            // Each entry carries whether its children have already been pushed.
+            stack: VecDeque<(NodeMut, bool)>,
         }
 
         impl NodeMutIterator {
-            /// Creates a new iterator starting from the given root node.
+            /// Creates a new breadth-first iterator starting from the given root node.
             ///
             /// # Safety
             ///
@@ -66,9 +77,25 @@ pub fn iter_mut_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
             /// and that the safety requirements documented on `NodeMutIterator`
             /// are met throughout the iterator's lifetime.
             pub fn new(root: NodeMut) -> Self {
-                Self {
-                    stack: VecDeque::from([root]),
-                }
+                Self::with_order(root, TraversalOrder::BreadthFirst)
+            }
+
+            /// Creates a new iterator starting from the given root node that yields nodes
+            /// in the given order.
+            ///
+            /// # Safety
+            ///
+            /// The same requirements as for [`NodeMutIterator::new`] apply.
+            pub fn with_order(root: NodeMut, order: TraversalOrder) -> Self {
+                let stack = match order {
+                    TraversalOrder::SourceOrder => source_order(root)
+                        .into_iter()
+                        .map(|n| (n, true))
+                        .collect(),
+                    _ => VecDeque::from([(root, false)]),
+                };
+
+                Self { order, stack }
             }
         }
 
@@ -76,22 +103,83 @@ pub fn iter_mut_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
             type Item = NodeMut;
 
             fn next(&mut self) -> Option<Self::Item> {
-                if self.stack.is_empty() {
-                    return None;
-                }
+                loop {
+                    let (node, expanded) = match self.order {
+                        TraversalOrder::BreadthFirst | TraversalOrder::SourceOrder => {
+                            self.stack.pop_front()?
+                        }
+                        TraversalOrder::PreOrder | TraversalOrder::PostOrder => {
+                            self.stack.pop_back()?
+                        }
+                    };
+
+                    if expanded {
+                        return Some(node);
+                    }
 
-                let node = self.stack.pop_front().unwrap();
+                    let mut children = Vec::new();
+                    push_children(node, &mut children);
 
-                unsafe {
-                    match node {
-                        #(NodeMut::#node_variant_names(n) => {#node_property_handlers}),*,
-                        _ => {
-                            // Some node types don't have any child nodes to traverse
+                    match self.order {
+                        TraversalOrder::BreadthFirst => {
+                            self.stack.extend(children.into_iter().map(|c| (c, false)));
+                            return Some(node);
                         }
-                    };
+                        TraversalOrder::PreOrder => {
+                            self.stack.extend(children.into_iter().rev().map(|c| (c, false)));
+                            return Some(node);
+                        }
+                        TraversalOrder::PostOrder | TraversalOrder::SourceOrder => {
+                            self.stack.push_back((node, true));
+                            self.stack.extend(children.into_iter().rev().map(|c| (c, false)));
+                        }
+                    }
+                }
+            }
+        }
+
+        /// Collects all nodes below and including `root`, sorted by their location.
+        ///
+        /// Nodes without a location inherit the location of their closest ancestor that
+        /// has one. The sort is stable, so ties keep their pre-order position.
+        fn source_order(root: NodeMut) -> Vec<NodeMut> {
+            let mut stack = vec![(root, -1)];
+            let mut nodes = Vec::new();
+
+            while let Some((node, inherited)) = stack.pop() {
+                let location = location(node).unwrap_or(inherited);
+
+                let mut children = Vec::new();
+                push_children(node, &mut children);
+                stack.extend(children.into_iter().rev().map(|c| (c, location)));
+
+                nodes.push((location, node));
+            }
+
+            nodes.sort_by_key(|(location, _)| *location);
+            nodes.into_iter().map(|(_, node)| node).collect()
+        }
+
+        fn location(node: NodeMut) -> Option<i32> {
+            unsafe {
+                match node {
+                    #(NodeMut::#location_variant_names(n) => {
+                        n.as_ref().map(|n| n.location).filter(|l| *l >= 0)
+                    }),*,
+                    _ => None,
                 }
+            }
+        }
 
-                Some(node)
+        /// Pushes the direct children of `node` in field declaration order.
+        fn push_children(node: NodeMut, children: &mut Vec<NodeMut>) {
+            unsafe {
+                match node {
+                    #(NodeMut::#node_variant_names(n) => {#node_property_handlers}),*,
+                    _ => {
+                        // Some node types don't have any child nodes to traverse
+                    }
+                };
             }
         }
     }
@@ -103,29 +191,56 @@ fn property_handlers(node: &Node) -> TokenStream {
         .iter()
         .filter_map(|field| {
             let field_name = format_ident!("{}", field.name.as_str());
-            if matches!(field.r#type, FieldType::Node(_)) && field.repeated {
+            if let (FieldType::Node(type_name), Some(one_of)) = (&field.r#type, &field.one_of) {
+                let oneof_field = format_ident!("{}", one_of.field_name);
+                let module_name = format_ident!("{}", one_of.module_name);
+                let enum_name = format_ident!("{}", one_of.enum_name);
+                let variant_name = format_ident!("{}", one_of.variant_name);
+                let push = if type_name.is_none() {
+                    quote! {
+                        if let Some(n) = x.node.as_mut() {
+                            children.push(n.to_mut());
+                        }
+                    }
+                } else {
+                    quote! {
+                        children.push(x.to_mut());
+                    }
+                };
+                Some(quote! {
+                    if let Some(protobuf::#module_name::#enum_name::#variant_name(x)) = n.#oneof_field.as_mut() {
+                        #push
+                    }
+                })
+            } else if field.r#type == FieldType::Node(None) && field.repeated {
                 Some(quote! {
                     n.#field_name
                         .iter_mut()
                         .for_each(|x| {
                             if let Some(n) = x.node.as_mut() {
-                                self.stack.push_back(n.to_mut());
+                                children.push(n.to_mut());
                             }
                         });
                 })
-            } else if matches!(field.r#type, FieldType::Node(_)) && !field.is_one_of {
+            } else if matches!(field.r#type, FieldType::Node(_)) && field.repeated {
+                Some(quote! {
+                    n.#field_name
+                        .iter_mut()
+                        .for_each(|x| children.push(x.to_mut()));
+                })
+            } else if matches!(field.r#type, FieldType::Node(_)) {
                 if field.r#type == FieldType::Node(None) {
                     Some(quote! {
                         if let Some(n) = n.#field_name.as_mut() {
                             if let Some(n) = n.node.as_mut() {
-                                self.stack.push_back(n.to_mut());
+                                children.push(n.to_mut());
                             }
                         }
                     })
                 } else {
                     Some(quote! {
                         if let Some(field_node) = n.#field_name.as_mut() {
-                            self.stack.push_back(field_node.to_mut());
+                            children.push(field_node.to_mut());
                         }
                     })
                 }
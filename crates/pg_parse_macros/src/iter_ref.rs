@@ -17,7 +17,7 @@ pub fn iter_ref_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
     }
 
     for node in &nodes {
-        if let Some(variant_name) = type_to_variant.get(&node.name) {
+        if let Some(variant_name) = type_to_variant.get(&node.enum_variant_name) {
             let variant_ident = format_ident!("{}", variant_name);
             node_variant_names.push(variant_ident);
 
@@ -31,15 +31,35 @@ pub fn iter_ref_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
     quote! {
         use std::collections::VecDeque;
 
+        /// An iterator over all nodes in an AST tree.
+        ///
+        /// The order in which nodes are yielded is controlled by the [`TraversalOrder`]
+        /// passed to [`NodeRefIterator::with_order`]. [`NodeRefIterator::new`] traverses
+        /// the tree breadth-first.
         pub struct NodeRefIterator<'a>{
-            stack: VecDeque<NodeRef<'a>>,
+            order: TraversalOrder,
+            // Each entry carries whether its children have already been pushed.
+            stack: VecDeque<(NodeRef<'a>, bool)>,
         }
 
         impl<'a> NodeRefIterator<'a> {
+            /// Creates a new breadth-first iterator starting from the given root node.
             pub fn new(root: NodeRef<'a>) -> Self {
-                Self {
-                    stack: VecDeque::from([root]),
-                }
+                Self::with_order(root, TraversalOrder::BreadthFirst)
+            }
+
+            /// Creates a new iterator starting from the given root node that yields nodes
+            /// in the given order.
+            pub fn with_order(root: NodeRef<'a>, order: TraversalOrder) -> Self {
+                let stack = match order {
+                    TraversalOrder::SourceOrder => source_order(root)
+                        .into_iter()
+                        .map(|n| (n, true))
+                        .collect(),
+                    _ => VecDeque::from([(root, false)]),
+                };
+
+                Self { order, stack }
             }
         }
 
@@ -47,21 +67,71 @@ pub fn iter_ref_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
             type Item = NodeRef<'a>;
 
             fn next(&mut self) -> Option<Self::Item> {
-                if self.stack.is_empty() {
-                    return None;
-                }
+                loop {
+                    let (node, expanded) = match self.order {
+                        TraversalOrder::BreadthFirst | TraversalOrder::SourceOrder => {
+                            self.stack.pop_front()?
+                        }
+                        TraversalOrder::PreOrder | TraversalOrder::PostOrder => {
+                            self.stack.pop_back()?
+                        }
+                    };
+
+                    if expanded {
+                        return Some(node);
+                    }
 
-                let node = self.stack.pop_front().unwrap();
+                    let mut children = Vec::new();
+                    push_children(&node, &mut children);
 
-                match &node {
-                    #(NodeRef::#node_variant_names(n) => {#node_property_handlers}),*,
-                    _ => {
-                        // Some node types don't have any child nodes to traverse
+                    match self.order {
+                        TraversalOrder::BreadthFirst => {
+                            self.stack.extend(children.into_iter().map(|c| (c, false)));
+                            return Some(node);
+                        }
+                        TraversalOrder::PreOrder => {
+                            self.stack.extend(children.into_iter().rev().map(|c| (c, false)));
+                            return Some(node);
+                        }
+                        TraversalOrder::PostOrder | TraversalOrder::SourceOrder => {
+                            self.stack.push_back((node, true));
+                            self.stack.extend(children.into_iter().rev().map(|c| (c, false)));
+                        }
                     }
-                };
+                }
+            }
+        }
+
+        /// Collects all nodes below and including `root`, sorted by their location.
+        ///
+        /// Nodes without a location inherit the location of their closest ancestor that
+        /// has one. The sort is stable, so ties keep their pre-order position.
+        fn source_order(root: NodeRef<'_>) -> Vec<NodeRef<'_>> {
+            let mut stack = vec![(root, -1)];
+            let mut nodes = Vec::new();
 
-                Some(node)
+            while let Some((node, inherited)) = stack.pop() {
+                let location = node.location().unwrap_or(inherited);
+
+                let mut children = Vec::new();
+                push_children(&node, &mut children);
+                stack.extend(children.into_iter().rev().map(|c| (c, location)));
+
+                nodes.push((location, node));
             }
+
+            nodes.sort_by_key(|(location, _)| *location);
+            nodes.into_iter().map(|(_, node)| node).collect()
+        }
+
+        /// Pushes the direct children of `node` in field declaration order.
+        pub(crate) fn push_children<'a>(node: &NodeRef<'a>, children: &mut Vec<NodeRef<'a>>) {
+            match node {
+                #(NodeRef::#node_variant_names(n) => {#node_property_handlers}),*,
+                _ => {
+                    // Some node types don't have any child nodes to traverse
+                }
+            };
         }
     }
 }
@@ -71,29 +141,56 @@ fn property_handlers(node: &Node) -> Vec<TokenStream> {
         .iter()
         .filter_map(|field| {
             let field_name = format_ident!("{}", field.name.as_str());
-            if matches!(field.r#type, FieldType::Node(_)) && field.repeated {
+            if let (FieldType::Node(type_name), Some(one_of)) = (&field.r#type, &field.one_of) {
+                let oneof_field = format_ident!("{}", one_of.field_name);
+                let module_name = format_ident!("{}", one_of.module_name);
+                let enum_name = format_ident!("{}", one_of.enum_name);
+                let variant_name = format_ident!("{}", one_of.variant_name);
+                let push = if type_name.is_none() {
+                    quote! {
+                        if let Some(n) = x.node.as_ref() {
+                            children.push(n.to_ref());
+                        }
+                    }
+                } else {
+                    quote! {
+                        children.push(x.to_ref());
+                    }
+                };
+                Some(quote! {
+                    if let Some(protobuf::#module_name::#enum_name::#variant_name(x)) = &n.#oneof_field {
+                        #push
+                    }
+                })
+            } else if field.r#type == FieldType::Node(None) && field.repeated {
                 Some(quote! {
                     n.#field_name
                         .iter()
                         .for_each(|x| {
                             if let Some(n) = x.node.as_ref() {
-                                self.stack.push_back(n.to_ref());
+                                children.push(n.to_ref());
                             }
                         });
                 })
-            } else if matches!(field.r#type, FieldType::Node(_)) && !field.is_one_of {
+            } else if matches!(field.r#type, FieldType::Node(_)) && field.repeated {
+                Some(quote! {
+                    n.#field_name
+                        .iter()
+                        .for_each(|x| children.push(x.to_ref()));
+                })
+            } else if matches!(field.r#type, FieldType::Node(_)) {
                 if field.r#type == FieldType::Node(None) {
                     Some(quote! {
                         if let Some(n) = &n.#field_name {
                             if let Some(n) = n.node.as_ref() {
-                                self.stack.push_back(n.to_ref());
+                                children.push(n.to_ref());
                             }
                         }
                     })
                 } else {
                     Some(quote! {
                         if let Some(field_node) = &n.#field_name {
-                            self.stack.push_back(field_node.to_ref());
+                            children.push(field_node.to_ref());
                         }
                     })
                 }
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::proto_analyser::{Field, FieldType, Node, ProtoAnalyzer};
+
+/// Messages whose `Serialize` impl is written by hand because libpg_query special-cases them
+const CUSTOM_SERIALIZE: &[&str] = &["A_Const", "IntList", "OidList"];
+
+/// Messages whose `Deserialize` impl is written by hand
+const CUSTOM_DESERIALIZE: &[&str] = &["IntList", "OidList"];
+
+/// Fields that libpg_query writes even when they hold the default value
+const ALWAYS_WRITTEN: &[(&str, &str)] = &[
+    ("Boolean", "boolval"),
+    ("Float", "fval"),
+    ("String", "sval"),
+    ("BitString", "bsval"),
+    ("List", "items"),
+];
+
+pub fn json_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
+    let enum_variants = analyser.enum_variants();
+    let nodes = analyser.nodes();
+
+    let names_by_type: HashMap<&str, &str> = nodes
+        .iter()
+        .map(|n| (n.enum_variant_name.as_str(), n.name.as_str()))
+        .collect();
+
+    let variant_idents: Vec<_> = enum_variants
+        .iter()
+        .map(|v| format_ident!("{}", v.name))
+        .collect();
+    let type_idents: Vec<_> = enum_variants
+        .iter()
+        .map(|v| format_ident!("{}", v.type_name))
+        .collect();
+    let labels: Vec<_> = enum_variants
+        .iter()
+        .map(|v| names_by_type[v.type_name.as_str()])
+        .collect();
+
+    let impls: Vec<_> = nodes.iter().map(message_impls).collect();
+
+    quote! {
+        /// The names libpg_query uses for node types in JSON, e.g. `A_Expr`
+        const NODE_NAMES: &[&str] = &[#(#labels),*];
+
+        fn serialize_node_entry<M: SerializeMap>(node: &NodeEnum, map: &mut M) -> ::core::result::Result<(), M::Error> {
+            match node {
+                #(NodeEnum::#variant_idents(n) => map.serialize_entry(#labels, n),)*
+            }
+        }
+
+        fn deserialize_node_entry<'de, A: MapAccess<'de>>(label: &str, map: &mut A) -> ::core::result::Result<NodeEnum, A::Error> {
+            match label {
+                #(#labels => next_node::<_, protobuf::#type_idents>(map),)*
+                _ => Err(de::Error::unknown_variant(label, NODE_NAMES)),
+            }
+        }
+
+        #(#impls)*
+    }
+}
+
+fn message_impls(node: &Node) -> TokenStream {
+    let type_ident = format_ident!("{}", node.enum_variant_name);
+
+    let serialize = if CUSTOM_SERIALIZE.contains(&node.name.as_str()) {
+        quote! {}
+    } else {
+        let entries: Vec<_> = node
+            .stored_fields()
+            .into_iter()
+            .map(|(ident, field)| serialize_field(node, &ident, field))
+            .collect();
+
+        quote! {
+            impl Serialize for protobuf::#type_ident {
+                fn serialize<S: Serializer>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error> {
+                    let mut map = serializer.serialize_map(None)?;
+                    #(#entries)*
+                    map.end()
+                }
+            }
+        }
+    };
+
+    let deserialize = if CUSTOM_DESERIALIZE.contains(&node.name.as_str()) {
+        quote! {}
+    } else {
+        let name = &node.name;
+        let arms: Vec<_> = node.fields.iter().map(deserialize_field).collect();
+        let body = if arms.is_empty() {
+            quote! {
+                let _ = (key, map);
+                Ok(false)
+            }
+        } else {
+            quote! {
+                match key {
+                    #(#arms,)*
+                    _ => return Ok(false),
+                }
+                Ok(true)
+            }
+        };
+
+        quote! {
+            impl DeserializeFields for protobuf::#type_ident {
+                const NAME: &'static str = #name;
+
+                fn deserialize_field<'de, A: MapAccess<'de>>(&mut self, key: &str, map: &mut A) -> ::core::result::Result<bool, A::Error> {
+                    #body
+                }
+            }
+
+            impl<'de> Deserialize<'de> for protobuf::#type_ident {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::core::result::Result<Self, D::Error> {
+                    deserializer.deserialize_map(MessageVisitor::<Self>::new())
+                }
+            }
+        }
+    };
+
+    quote! {
+        #serialize
+        #deserialize
+    }
+}
+
+fn serialize_field(node: &Node, ident: &proc_macro2::Ident, field: &Field) -> TokenStream {
+    if let Some(one_of) = &field.one_of {
+        let module_name = format_ident!("{}", one_of.module_name);
+        let enum_name = format_ident!("{}", one_of.enum_name);
+        let arms: Vec<_> = node
+            .one_of_members(&one_of.field_name)
+            .into_iter()
+            .map(|f| {
+                let variant_name = format_ident!("{}", f.one_of.as_ref().unwrap().variant_name);
+                let json_name = &f.json_name;
+                quote! {
+                    Some(protobuf::#module_name::#enum_name::#variant_name(x)) =>
+                        map.serialize_entry(#json_name, x)?
+                }
+            })
+            .collect();
+        return quote! {
+            match &self.#ident {
+                #(#arms,)*
+                None => {}
+            }
+        };
+    }
+
+    let json_name = &field.json_name;
+    let always =
+        field.is_float || ALWAYS_WRITTEN.contains(&(node.name.as_str(), field.name.as_str()));
+
+    match (&field.r#type, field.repeated) {
+        (FieldType::Enum(type_name), false) => {
+            let type_ident = format_ident!("{}", type_name.to_case(Case::Pascal));
+            quote! {
+                match protobuf::#type_ident::try_from(self.#ident) {
+                    Ok(e) => map.serialize_entry(#json_name, e.as_str_name())?,
+                    Err(_) => map.serialize_entry(#json_name, &self.#ident)?,
+                }
+            }
+        }
+        _ if always => quote! {
+            map.serialize_entry(#json_name, &self.#ident)?;
+        },
+        (_, true) => quote! {
+            if !self.#ident.is_empty() {
+                map.serialize_entry(#json_name, &self.#ident)?;
+            }
+        },
+        (FieldType::Node(_), false) => quote! {
+            if let Some(x) = &self.#ident {
+                map.serialize_entry(#json_name, x)?;
+            }
+        },
+        _ => quote! {
+            if !is_default(&self.#ident) {
+                map.serialize_entry(#json_name, &self.#ident)?;
+            }
+        },
+    }
+}
+
+fn deserialize_field(field: &Field) -> TokenStream {
+    let keys = if field.json_name == field.name {
+        vec![field.name.as_str()]
+    } else {
+        vec![field.json_name.as_str(), field.name.as_str()]
+    };
+
+    let value = match (&field.r#type, field.repeated) {
+        (FieldType::Enum(type_name), false) => {
+            let type_ident = format_ident!("{}", type_name.to_case(Case::Pascal));
+            quote! {
+                map.next_value_seed(EnumSeed(|s| protobuf::#type_ident::from_str_name(s).map(|e| e as i32)))?
+            }
+        }
+        _ => quote! { map.next_value()? },
+    };
+
+    let assignment = match &field.one_of {
+        Some(one_of) => {
+            let ident = format_ident!("{}", one_of.field_name);
+            let module_name = format_ident!("{}", one_of.module_name);
+            let enum_name = format_ident!("{}", one_of.enum_name);
+            let variant_name = format_ident!("{}", one_of.variant_name);
+            quote! { self.#ident = Some(protobuf::#module_name::#enum_name::#variant_name(#value)) }
+        }
+        None => {
+            let ident = field.ident();
+            quote! { self.#ident = #value }
+        }
+    };
+
+    quote! {
+        #(#keys)|* => #assignment
+    }
+}
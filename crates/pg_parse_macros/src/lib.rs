@@ -1,20 +1,38 @@
+use checked_sql::checked_sql_mod;
+use convert::convert_mod;
+use diff::diff_mod;
 use iter_mut::iter_mut_mod;
 use iter_ref::iter_ref_mod;
+use json::json_mod;
 use node_enum::node_enum_mod;
 use node_mut::node_mut_mod;
 use node_ref::node_ref_mod;
 use node_structs::node_structs_mod;
 use proto_analyser::ProtoAnalyzer;
 use quote::quote;
+use reflect::reflect_mod;
+use sql::sql_mod;
 use std::path;
+use std::sync::OnceLock;
+use structural::structural_mod;
+use walk_mut::walk_mut_mod;
 
+mod checked_sql;
+mod convert;
+mod diff;
 mod iter_mut;
 mod iter_ref;
+mod json;
 mod node_enum;
 mod node_mut;
 mod node_ref;
 mod node_structs;
+mod parser;
 mod proto_analyser;
+mod reflect;
+mod sql;
+mod structural;
+mod walk_mut;
 
 #[proc_macro]
 pub fn node_ref_codegen(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -100,6 +118,153 @@ pub fn iter_mut_codegen(_input: proc_macro::TokenStream) -> proc_macro::TokenStr
     .into()
 }
 
+#[proc_macro]
+pub fn walk_mut_codegen(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let analyser = ProtoAnalyzer::from(&proto_file_path()).unwrap();
+
+    let walker = walk_mut_mod(analyser);
+
+    quote! {
+        use crate::*;
+
+        #walker
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn structural_codegen(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let analyser = ProtoAnalyzer::from(&proto_file_path()).unwrap();
+
+    let structural = structural_mod(analyser);
+
+    quote! {
+        use crate::*;
+
+        #structural
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn convert_codegen(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let analyser = ProtoAnalyzer::from(&proto_file_path()).unwrap();
+
+    let convert = convert_mod(analyser);
+
+    quote! {
+        use crate::*;
+
+        #convert
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn reflect_codegen(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let analyser = ProtoAnalyzer::from(&proto_file_path()).unwrap();
+
+    let reflect = reflect_mod(analyser);
+
+    quote! {
+        use crate::*;
+
+        #reflect
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn diff_codegen(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let analyser = ProtoAnalyzer::from(&proto_file_path()).unwrap();
+
+    let diff = diff_mod(analyser);
+
+    quote! {
+        use crate::*;
+
+        #diff
+    }
+    .into()
+}
+
+#[proc_macro]
+pub fn json_codegen(_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let analyser = ProtoAnalyzer::from(&proto_file_path()).unwrap();
+
+    let json = json_mod(analyser);
+
+    quote! {
+        use crate::*;
+
+        #json
+    }
+    .into()
+}
+
+/// Parses a SQL statement at compile time and expands to an expression that builds its
+/// `NodeEnum`.
+///
+/// `#name` interpolates the variable `name` where the statement expects an expression or a
+/// table. Variables can be of any type that implements `Clone` and `Into<NodeEnum>`, such as
+/// nodes or the builders from `pg_parse::builder`. Syntax errors are reported as compile errors.
+///
+/// The statement is parsed with the grammar of the Postgres version selected through the
+/// `postgres-*` features of `pg_parse`, the same one that is used at runtime.
+///
+/// ```rust,ignore
+/// use pg_parse::{builder::*, sql};
+///
+/// let table = table("public", "users");
+/// let id = param(1);
+/// let stmt = sql!("SELECT * FROM #table WHERE id = #id");
+/// ```
+#[proc_macro]
+pub fn sql(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    sql_mod(analyser(), input.into())
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Checks SQL with the Postgres parser at compile time.
+///
+/// Given a string literal, expands to that literal as a `&'static str`. Syntax errors become
+/// compile errors pointing at the position the parser reports. Any number of statements can be
+/// checked this way.
+///
+/// Given `const` items instead, each item defines the constant together with `<NAME>_KIND`,
+/// the name of the statement's node type, and `<NAME>_PARAMS`, the number of `$n` parameters
+/// the statement expects. Each item must hold exactly one statement.
+///
+/// Like `sql!`, the macro uses the grammar of the Postgres version selected through the
+/// `postgres-*` features of `pg_parse`.
+///
+/// ```rust,ignore
+/// use pg_parse::checked_sql;
+///
+/// const LIST_USERS: &str = checked_sql!("SELECT * FROM users");
+///
+/// checked_sql! {
+///     pub const FIND_USER = "SELECT * FROM users WHERE id = $1";
+/// }
+///
+/// assert_eq!(FIND_USER_KIND, "SelectStmt");
+/// assert_eq!(FIND_USER_PARAMS, 1);
+/// ```
+#[proc_macro]
+pub fn checked_sql(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    checked_sql_mod(analyser(), input.into())
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Returns the analyser shared by the macros that are invoked once per query. Compiling the
+/// proto file takes a while, so it is done once per compiler process.
+fn analyser() -> &'static ProtoAnalyzer {
+    static ANALYSER: OnceLock<ProtoAnalyzer> = OnceLock::new();
+    ANALYSER.get_or_init(|| ProtoAnalyzer::from(&proto_file_path()).unwrap())
+}
+
 fn proto_file_path() -> path::PathBuf {
     // Use the path set by the build script
     path::PathBuf::from(env!("PG_QUERY_PROTO_PATH"))
@@ -4,9 +4,11 @@ use crate::proto_analyser::ProtoAnalyzer;
 
 pub fn node_ref_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
     let node_variants = analyser.enum_variants();
+    let nodes = analyser.nodes();
 
     let mut to_enum_matches = Vec::new();
     let mut node_enum_variants = Vec::new();
+    let mut location_matches = Vec::new();
 
     for variant in &node_variants {
         let variant_ident = format_ident!("{}", &variant.name);
@@ -27,6 +29,16 @@ pub fn node_ref_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
         node_enum_variants.push(quote! {
             #variant_ident(&'a protobuf::#type_ident)
         });
+
+        let has_location = nodes
+            .iter()
+            .find(|n| n.enum_variant_name == variant.type_name)
+            .is_some_and(|n| n.has_location);
+        if has_location {
+            location_matches.push(quote! {
+                NodeRef::#variant_ident(n) => Some(n.location).filter(|l| *l >= 0)
+            });
+        }
     }
 
     quote! {
@@ -41,6 +53,16 @@ pub fn node_ref_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
                     #(#to_enum_matches,)*
                 }
             }
+
+            /// Returns the byte offset of this node in the source text.
+            ///
+            /// Returns None if the node type has no location or if the location is unknown.
+            pub fn location(&self) -> Option<i32> {
+                match self {
+                    #(#location_matches,)*
+                    _ => None,
+                }
+            }
         }
     }
 }
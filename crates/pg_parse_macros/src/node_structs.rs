@@ -21,6 +21,21 @@ pub fn node_structs_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
                     NodeMut::#node_ident(self)
                 }
             }
+
+            impl AstNode for protobuf::#type_ident {
+                const KIND: NodeKind = NodeKind::#node_ident;
+
+                fn cast(node: NodeRef<'_>) -> Option<&Self> {
+                    match node {
+                        NodeRef::#node_ident(n) => Some(n),
+                        _ => None,
+                    }
+                }
+
+                fn to_ref(&self) -> NodeRef<'_> {
+                    NodeRef::#node_ident(self)
+                }
+            }
         });
     }
 
@@ -0,0 +1,196 @@
+//! Runs the Postgres parser and scanner at compile time.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+
+use prost_reflect::{DynamicMessage, ReflectMessage, Value};
+
+use crate::proto_analyser::ProtoAnalyzer;
+
+#[repr(C)]
+struct PgQueryError {
+    message: *mut c_char,
+    funcname: *mut c_char,
+    filename: *mut c_char,
+    lineno: c_int,
+    cursorpos: c_int,
+    context: *mut c_char,
+}
+
+#[repr(C)]
+struct PgQueryProtobuf {
+    len: usize,
+    data: *mut c_char,
+}
+
+#[repr(C)]
+struct PgQueryProtobufParseResult {
+    parse_tree: PgQueryProtobuf,
+    stderr_buffer: *mut c_char,
+    error: *mut PgQueryError,
+}
+
+#[repr(C)]
+struct PgQueryScanResult {
+    pbuf: PgQueryProtobuf,
+    stderr_buffer: *mut c_char,
+    error: *mut PgQueryError,
+}
+
+extern "C" {
+    fn pg_query_parse_protobuf(input: *const c_char) -> PgQueryProtobufParseResult;
+    fn pg_query_free_protobuf_parse_result(result: PgQueryProtobufParseResult);
+    fn pg_query_scan(input: *const c_char) -> PgQueryScanResult;
+    fn pg_query_free_scan_result(result: PgQueryScanResult);
+}
+
+/// A syntax error reported by the parser
+pub(crate) struct SyntaxError {
+    pub message: String,
+    /// The 1-based character position of the error in the input, or 0 if unknown
+    pub cursorpos: usize,
+}
+
+impl SyntaxError {
+    /// Formats the error together with the offending line of `sql` and a caret pointing at the
+    /// error position.
+    pub fn render(&self, sql: &str) -> String {
+        if self.cursorpos == 0 {
+            return self.message.clone();
+        }
+
+        let offset = sql
+            .char_indices()
+            .nth(self.cursorpos - 1)
+            .map_or(sql.len(), |(i, _)| i);
+        let line_start = sql[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = sql[offset..].find('\n').map_or(sql.len(), |i| offset + i);
+        let line_number = sql[..offset].matches('\n').count() + 1;
+        let column = sql[line_start..offset].chars().count();
+
+        format!(
+            "{} (line {}, column {})\n  | {}\n  | {}^",
+            self.message,
+            line_number,
+            column + 1,
+            &sql[line_start..line_end],
+            " ".repeat(column)
+        )
+    }
+}
+
+/// A token found by the scanner
+pub(crate) struct Token {
+    /// The byte offset of the token in the input
+    pub start: usize,
+    /// The byte offset after the end of the token
+    pub end: usize,
+    /// The name of the token type, e.g. `Op`, `SCONST` or `C_COMMENT`
+    pub kind: String,
+}
+
+fn c_string(sql: &str) -> Result<CString, SyntaxError> {
+    CString::new(sql).map_err(|_| SyntaxError {
+        message: "SQL must not contain NUL bytes".to_string(),
+        cursorpos: 0,
+    })
+}
+
+fn syntax_error(error: &PgQueryError) -> SyntaxError {
+    SyntaxError {
+        message: unsafe { CStr::from_ptr(error.message) }
+            .to_string_lossy()
+            .into_owned(),
+        cursorpos: error.cursorpos.max(0) as usize,
+    }
+}
+
+/// Splits `sql` into tokens, including comments.
+pub(crate) fn scan(analyser: &ProtoAnalyzer, sql: &str) -> Result<Vec<Token>, SyntaxError> {
+    let input = c_string(sql)?;
+
+    let result = unsafe { pg_query_scan(input.as_ptr()) };
+    let scanned = if !result.error.is_null() {
+        Err(syntax_error(unsafe { &*result.error }))
+    } else {
+        let data =
+            unsafe { std::slice::from_raw_parts(result.pbuf.data as *const u8, result.pbuf.len) };
+        analyser
+            .decode_scan_result(data)
+            .map(|scan_result| tokens(&scan_result))
+            .map_err(|e| SyntaxError {
+                message: format!("unable to decode scan result: {e}"),
+                cursorpos: 0,
+            })
+    };
+
+    unsafe { pg_query_free_scan_result(result) };
+    scanned
+}
+
+fn tokens(scan_result: &DynamicMessage) -> Vec<Token> {
+    let Some(Value::List(tokens)) = scan_result.get_field_by_name("tokens").as_deref().cloned()
+    else {
+        return Vec::new();
+    };
+    tokens
+        .iter()
+        .filter_map(Value::as_message)
+        .map(|token| {
+            let offset = |field: &str| match token.get_field_by_name(field).as_deref() {
+                Some(Value::I32(n)) => (*n).max(0) as usize,
+                _ => 0,
+            };
+            let field = token.descriptor().get_field_by_name("token");
+            let kind = match (field, token.get_field_by_name("token").as_deref()) {
+                (Some(field), Some(Value::EnumNumber(n))) => field
+                    .kind()
+                    .as_enum()
+                    .and_then(|e| e.get_value(*n))
+                    .map(|v| v.name().to_string()),
+                _ => None,
+            };
+            Token {
+                start: offset("start"),
+                end: offset("end"),
+                kind: kind.unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+/// Parses `sql` and decodes the result as a dynamic `pg_query.ParseResult` message.
+pub(crate) fn parse(analyser: &ProtoAnalyzer, sql: &str) -> Result<DynamicMessage, SyntaxError> {
+    parse_with(sql, |data| {
+        analyser.decode_parse_result(data).map_err(|e| SyntaxError {
+            message: format!("unable to decode parse result: {e}"),
+            cursorpos: 0,
+        })
+    })
+}
+
+/// Checks that `sql` parses, without decoding the parse tree.
+pub(crate) fn check(sql: &str) -> Result<(), SyntaxError> {
+    parse_with(sql, |_| Ok(()))
+}
+
+/// Parses `sql` and hands the encoded parse tree to `decode`.
+fn parse_with<T>(
+    sql: &str,
+    decode: impl FnOnce(&[u8]) -> Result<T, SyntaxError>,
+) -> Result<T, SyntaxError> {
+    let input = c_string(sql)?;
+
+    let result = unsafe { pg_query_parse_protobuf(input.as_ptr()) };
+    let parsed = if !result.error.is_null() {
+        Err(syntax_error(unsafe { &*result.error }))
+    } else {
+        let data = unsafe {
+            std::slice::from_raw_parts(result.parse_tree.data as *const u8, result.parse_tree.len)
+        };
+        decode(data)
+    };
+
+    unsafe { pg_query_free_protobuf_parse_result(result) };
+    parsed
+}
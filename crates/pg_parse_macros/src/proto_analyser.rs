@@ -9,8 +9,9 @@ use prost_reflect::{
         field_descriptor_proto::{Label, Type},
         FieldDescriptorProto,
     },
-    DescriptorError, DescriptorPool, FieldDescriptor, MessageDescriptor,
+    DescriptorError, DescriptorPool, DynamicMessage, FieldDescriptor, MessageDescriptor,
 };
+use quote::format_ident;
 
 pub(crate) struct ProtoAnalyzer {
     pool: DescriptorPool,
@@ -32,16 +33,105 @@ pub(crate) enum FieldType {
 
 pub(crate) struct Field {
     pub name: String,
+    /// The name libpg_query uses for the field in its JSON output, e.g. `targetList`
+    pub json_name: String,
     pub r#type: FieldType,
     pub repeated: bool,
-    pub is_one_of: bool,
+    pub one_of: Option<OneOf>,
+    pub location: Option<LocationKind>,
+    /// Whether the field is a `double`
+    pub is_float: bool,
+}
+
+/// Kinds of fields that record where a node was found in the source text
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum LocationKind {
+    /// A byte offset, or -1 if unknown
+    Offset,
+    /// The length of a statement in bytes, or 0 for the rest of the input
+    Length,
+}
+
+impl Field {
+    /// The identifier prost uses for this field, escaping Rust keywords
+    pub fn ident(&self) -> proc_macro2::Ident {
+        field_ident(&self.name)
+    }
+}
+
+/// Returns the identifier prost uses for the field `name`, escaping Rust keywords
+pub(crate) fn field_ident(name: &str) -> proc_macro2::Ident {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+        "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+        "unsized", "use", "virtual", "where", "while", "yield",
+    ];
+
+    if KEYWORDS.contains(&name) {
+        format_ident!("r#{}", name)
+    } else {
+        format_ident!("{}", name)
+    }
+}
+
+/// Returns the kind of location stored in a field, if any
+pub(crate) fn location_kind(field: &FieldDescriptor) -> Option<LocationKind> {
+    match (field.field_descriptor_proto().r#type(), field.name()) {
+        (Type::Int32, "location" | "name_location" | "stmt_location") => Some(LocationKind::Offset),
+        (Type::Int32, "stmt_len") => Some(LocationKind::Length),
+        _ => None,
+    }
+}
+
+/// Describes where prost puts a field that is a member of a `oneof`
+pub(crate) struct OneOf {
+    /// The Rust field holding the oneof, e.g. `val` in `A_Const`
+    pub field_name: String,
+    /// The module prost generates for the nested types of the message, e.g. `a_const`
+    pub module_name: String,
+    /// The enum prost generates for the oneof, e.g. `Val`
+    pub enum_name: String,
+    /// The enum variant for this member, e.g. `Ival`
+    pub variant_name: String,
 }
 
 pub(crate) struct Node {
     pub name: String,
-    #[allow(dead_code)]
     pub enum_variant_name: String,
     pub fields: Vec<Field>,
+    /// Whether the message has an `int32 location` field with its offset in the source text
+    pub has_location: bool,
+}
+
+impl Node {
+    /// Returns the fields of the message as prost stores them, with each oneof appearing once
+    /// under the identifier of its Rust field
+    pub fn stored_fields(&self) -> Vec<(proc_macro2::Ident, &Field)> {
+        let mut seen_oneofs = HashSet::new();
+        self.fields
+            .iter()
+            .filter_map(|field| match &field.one_of {
+                Some(one_of) => seen_oneofs
+                    .insert(one_of.field_name.clone())
+                    .then(|| (format_ident!("{}", one_of.field_name), field)),
+                None => Some((field.ident(), field)),
+            })
+            .collect()
+    }
+
+    /// Returns the members of the oneof stored in the Rust field `field_name`
+    pub fn one_of_members(&self, field_name: &str) -> Vec<&Field> {
+        self.fields
+            .iter()
+            .filter(|f| {
+                f.one_of
+                    .as_ref()
+                    .is_some_and(|o| o.field_name == field_name)
+            })
+            .collect()
+    }
 }
 
 impl ProtoAnalyzer {
@@ -96,25 +186,57 @@ impl ProtoAnalyzer {
                         _ => FieldType::Literal,
                     };
 
+                    let location = location_kind(&f);
+
                     Field {
                         name: f.name().to_string(),
+                        json_name: f.json_name().to_string(),
                         r#type: field_type,
+                        location,
+                        is_float: f.field_descriptor_proto().r#type() == Type::Double,
                         repeated: f.is_list(),
-                        is_one_of: f.containing_oneof().is_some(),
+                        one_of: f.containing_oneof().map(|o| OneOf {
+                            field_name: o.name().to_string(),
+                            module_name: msg.name().to_case(Case::Snake),
+                            enum_name: o.name().to_case(Case::Pascal),
+                            variant_name: f.name().to_case(Case::Pascal),
+                        }),
                     }
                 })
                 .collect();
 
+            let has_location = msg.get_field_by_name("location").is_some_and(|f| {
+                f.field_descriptor_proto().r#type() == Type::Int32 && !f.is_list()
+            });
+
             nodes.push(Node {
                 name: msg.name().to_string(),
                 enum_variant_name: msg.name().to_case(Case::Pascal), // Convert to PascalCase for enum variant name
                 fields,
+                has_location,
             });
         }
 
         nodes
     }
 
+    /// Decodes the protobuf encoded output of the parser
+    pub fn decode_parse_result(&self, bytes: &[u8]) -> Result<DynamicMessage, String> {
+        let descriptor = self
+            .pool
+            .get_message_by_name("pg_query.ParseResult")
+            .expect("ParseResult message not found");
+        DynamicMessage::decode(descriptor, bytes).map_err(|e| e.to_string())
+    }
+
+    pub fn decode_scan_result(&self, bytes: &[u8]) -> Result<DynamicMessage, String> {
+        let descriptor = self
+            .pool
+            .get_message_by_name("pg_query.ScanResult")
+            .expect("ScanResult message not found");
+        DynamicMessage::decode(descriptor, bytes).map_err(|e| e.to_string())
+    }
+
     pub fn enum_variants(&self) -> Vec<EnumVariant> {
         let node = self
             .pool
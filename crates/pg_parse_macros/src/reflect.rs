@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::proto_analyser::{FieldType, Node, ProtoAnalyzer};
+
+pub fn reflect_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
+    let enum_variants = analyser.enum_variants();
+    let nodes = analyser.nodes();
+
+    let variant_idents: Vec<_> = enum_variants
+        .iter()
+        .map(|v| format_ident!("{}", v.name))
+        .collect();
+    let variant_names: Vec<_> = enum_variants.iter().map(|v| v.name.clone()).collect();
+
+    let type_to_variant: HashMap<String, String> = enum_variants
+        .iter()
+        .map(|v| (v.type_name.clone(), v.name.clone()))
+        .collect();
+
+    let mut node_variant_names = Vec::new();
+    let mut node_fields = Vec::new();
+    for node in &nodes {
+        if let Some(variant_name) = type_to_variant.get(&node.enum_variant_name) {
+            node_variant_names.push(format_ident!("{}", variant_name));
+            node_fields.push(fields(node));
+        }
+    }
+
+    quote! {
+        /// The type of a node, without its contents.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum NodeKind {
+            #(#variant_idents,)*
+        }
+
+        impl NodeKind {
+            /// All node kinds, in the order of the `Node` message in `pg_query.proto`.
+            pub const ALL: &'static [NodeKind] = &[#(NodeKind::#variant_idents,)*];
+
+            /// Returns the name of the node type, e.g. `SelectStmt`.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(NodeKind::#variant_idents => #variant_names,)*
+                }
+            }
+        }
+
+        impl<'a> NodeRef<'a> {
+            /// Returns the type of this node.
+            pub fn kind(&self) -> NodeKind {
+                match self {
+                    #(NodeRef::#variant_idents(_) => NodeKind::#variant_idents,)*
+                }
+            }
+
+            /// Returns the name and value of every field of this node, in declaration order.
+            pub fn fields(&self) -> Vec<(&'static str, FieldValue<'a>)> {
+                match *self {
+                    #(NodeRef::#node_variant_names(n) => #node_fields,)*
+                }
+            }
+        }
+    }
+}
+
+fn fields(node: &Node) -> TokenStream {
+    let values: Vec<TokenStream> = node
+        .stored_fields()
+        .into_iter()
+        .map(|(ident, field)| {
+            let label = ident.to_string().trim_start_matches("r#").to_string();
+
+            let value = if let Some(one_of) = &field.one_of {
+                let members = node.one_of_members(&one_of.field_name);
+                if members
+                    .iter()
+                    .all(|f| matches!(f.r#type, FieldType::Node(_)))
+                {
+                    let module_name = format_ident!("{}", one_of.module_name);
+                    let enum_name = format_ident!("{}", one_of.enum_name);
+                    let arms: Vec<_> = members
+                        .iter()
+                        .map(|f| {
+                            let variant_name =
+                                format_ident!("{}", f.one_of.as_ref().unwrap().variant_name);
+                            quote! {
+                                Some(protobuf::#module_name::#enum_name::#variant_name(x)) =>
+                                    FieldValue::Node(x.to_ref())
+                            }
+                        })
+                        .collect();
+                    quote! {
+                        match &n.#ident {
+                            #(#arms,)*
+                            None => FieldValue::Null,
+                        }
+                    }
+                } else {
+                    quote! { FieldValue::Null }
+                }
+            } else {
+                match (&field.r#type, field.repeated) {
+                    (FieldType::Node(None), true) => quote! {
+                        FieldValue::List(n.#ident.iter().map(FieldValue::from).collect())
+                    },
+                    (FieldType::Node(Some(_)), true) => quote! {
+                        FieldValue::List(n.#ident.iter().map(|x| FieldValue::Node(x.to_ref())).collect())
+                    },
+                    (FieldType::Node(None), false) => quote! {
+                        n.#ident.as_deref().map_or(FieldValue::Null, FieldValue::from)
+                    },
+                    (FieldType::Node(Some(_)), false) => quote! {
+                        n.#ident.as_ref().map_or(FieldValue::Null, |x| FieldValue::Node(x.to_ref()))
+                    },
+                    (FieldType::Enum(type_name), false) => {
+                        let type_ident = format_ident!("{}", type_name.to_case(Case::Pascal));
+                        quote! {
+                            match protobuf::#type_ident::try_from(n.#ident) {
+                                Ok(e) => FieldValue::Enum(e.as_str_name()),
+                                Err(_) => FieldValue::Int(n.#ident.into()),
+                            }
+                        }
+                    }
+                    _ => quote! { FieldValue::from(&n.#ident) },
+                }
+            };
+
+            quote! { (#label, #value) }
+        })
+        .collect();
+
+    quote! {
+        vec![#(#values),*]
+    }
+}
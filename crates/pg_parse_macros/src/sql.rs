@@ -0,0 +1,255 @@
+use std::ops::Range;
+
+use convert_case::{Case, Casing};
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use prost_reflect::{DynamicMessage, ReflectMessage, Value};
+use quote::{format_ident, quote, ToTokens};
+use syn::LitStr;
+
+use crate::parser;
+use crate::proto_analyser::{field_ident, location_kind, LocationKind, ProtoAnalyzer};
+
+const SPLICE_PREFIX: &str = "__pg_parse_splice_";
+
+pub fn sql_mod(analyser: &ProtoAnalyzer, input: TokenStream) -> syn::Result<TokenStream> {
+    let literal: LitStr = syn::parse2(input)?;
+    let (template, splices) = extract_splices(analyser, &literal.value(), literal.span())?;
+
+    let result = parser::parse(analyser, &template)
+        .map_err(|e| syn::Error::new(literal.span(), e.render(&template)))?;
+
+    let stmts = match result.get_field_by_name("stmts").as_deref() {
+        Some(Value::List(stmts)) => stmts.clone(),
+        _ => Vec::new(),
+    };
+    let [stmt] = stmts.as_slice() else {
+        return Err(syn::Error::new(
+            literal.span(),
+            format!("expected exactly one statement, found {}", stmts.len()),
+        ));
+    };
+    let stmt = match stmt.as_message().and_then(|m| m.get_field_by_name("stmt")) {
+        Some(stmt) => stmt.as_message().cloned().unwrap(),
+        None => unreachable!("raw statements always contain a statement"),
+    };
+
+    let mut generator = Generator {
+        splices: splices.iter().map(|s| (s.clone(), false)).collect(),
+    };
+    let node = generator.node(&stmt);
+
+    if let Some((ident, _)) = generator.splices.iter().find(|(_, used)| !used) {
+        return Err(syn::Error::new(
+            ident.span(),
+            format!(
+                "`#{ident}` can only be interpolated where an expression or a table is expected"
+            ),
+        ));
+    }
+
+    Ok(quote! {
+        {
+            let node: ::pg_parse::ast::Node = #node;
+            node.node.unwrap()
+        }
+    })
+}
+
+/// Replaces every `#ident` outside of strings, quoted identifiers and comments with a
+/// placeholder identifier the parser accepts wherever an expression or a table name is
+/// expected.
+fn extract_splices(
+    analyser: &ProtoAnalyzer,
+    sql: &str,
+    span: Span,
+) -> syn::Result<(String, Vec<Ident>)> {
+    // `#` is an operator character, so a `#` in any other token is quoted or commented out. If
+    // the query cannot be scanned, the parser reports the error.
+    let quoted: Vec<Range<usize>> = parser::scan(analyser, sql)
+        .map(|tokens| {
+            tokens
+                .into_iter()
+                .filter(|t| t.kind != "Op")
+                .map(|t| t.start..t.end)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut template = String::with_capacity(sql.len());
+    let mut splices = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let is_splice = c == '#'
+            && !quoted.iter().any(|r| r.contains(&i))
+            && chars
+                .peek()
+                .is_some_and(|(_, d)| d.is_ascii_alphabetic() || *d == '_');
+        if !is_splice {
+            template.push(c);
+            continue;
+        }
+
+        let mut name = String::new();
+        while let Some((_, d)) = chars.next_if(|(_, d)| d.is_ascii_alphanumeric() || *d == '_') {
+            name.push(d);
+        }
+        let ident = syn::parse_str::<Ident>(&name)
+            .map_err(|_| syn::Error::new(span, format!("`#{name}` is not a valid identifier")))?;
+        template.push_str(&format!("{SPLICE_PREFIX}{}", splices.len()));
+        splices.push(Ident::new(&ident.to_string(), span));
+    }
+
+    Ok((template, splices))
+}
+
+/// Turns dynamic protobuf messages into expressions constructing the prost structs
+struct Generator {
+    // The interpolated variables and whether they have been spliced in
+    splices: Vec<(Ident, bool)>,
+}
+
+impl Generator {
+    /// Returns the interpolated variable for a placeholder identifier
+    fn splice(&mut self, name: &str) -> Option<TokenStream> {
+        let index: usize = name.strip_prefix(SPLICE_PREFIX)?.parse().ok()?;
+        let (ident, used) = self.splices.get_mut(index)?;
+        *used = true;
+        Some(quote! {
+            ::core::convert::Into::<::pg_parse::NodeEnum>::into(::core::clone::Clone::clone(&#ident))
+        })
+    }
+
+    /// Checks whether `message` is a column reference or table consisting only of a placeholder
+    fn placeholder(&mut self, message: &DynamicMessage) -> Option<TokenStream> {
+        let string = |m: &DynamicMessage, field: &str| match m.get_field_by_name(field).as_deref() {
+            Some(Value::String(s)) => s.clone(),
+            _ => String::new(),
+        };
+
+        match message.descriptor().name() {
+            "ColumnRef" => {
+                let Some(Value::List(fields)) =
+                    message.get_field_by_name("fields").as_deref().cloned()
+                else {
+                    return None;
+                };
+                let [field] = fields.as_slice() else {
+                    return None;
+                };
+                let node = field.as_message()?;
+                let (_, value) = node.fields().next()?;
+                self.splice(&string(value.as_message()?, "sval"))
+            }
+            "RangeVar" if string(message, "schemaname").is_empty() => {
+                self.splice(&string(message, "relname"))
+            }
+            _ => None,
+        }
+    }
+
+    /// Generates a `protobuf::Node`
+    fn node(&mut self, node: &DynamicMessage) -> TokenStream {
+        let Some((field, value)) = node.fields().next() else {
+            return quote! { ::pg_parse::ast::Node { node: None } };
+        };
+        let inner = value.as_message().unwrap();
+
+        if let Some(splice) = self.placeholder(inner) {
+            return quote! { ::pg_parse::ast::Node { node: Some(#splice) } };
+        }
+
+        let variant = format_ident!("{}", field.name().to_case(Case::Pascal));
+        let message = self.message(inner);
+        quote! {
+            ::pg_parse::ast::Node {
+                node: Some(::pg_parse::ast::node::Node::#variant(
+                    ::core::convert::From::from(#message)
+                )),
+            }
+        }
+    }
+
+    /// Generates a struct literal for a message other than `Node`
+    fn message(&mut self, message: &DynamicMessage) -> TokenStream {
+        let descriptor = message.descriptor();
+
+        // A table in a position that requires a `RangeVar` rather than any node
+        if descriptor.name() == "RangeVar" {
+            if let Some(splice) = self.placeholder(message) {
+                return quote! { ::pg_parse::__private::into_range_var(#splice) };
+            }
+        }
+
+        let type_ident = format_ident!("{}", descriptor.name().to_case(Case::Pascal));
+        let module = format_ident!("{}", descriptor.name().to_case(Case::Snake));
+
+        let mut fields = Vec::new();
+        let mut complete = true;
+        for field in descriptor.fields() {
+            let ident = field_ident(field.name());
+
+            // Locations refer to the template rather than the query the node ends up in
+            if location_kind(&field) == Some(LocationKind::Offset) {
+                fields.push(quote! { #ident: -1 });
+                continue;
+            }
+            if !message.has_field(&field) || location_kind(&field).is_some() {
+                complete = false;
+                continue;
+            }
+
+            let value = self.value(&message.get_field(&field));
+            match field.containing_oneof() {
+                Some(oneof) => {
+                    let oneof_ident = field_ident(oneof.name());
+                    let enum_ident = format_ident!("{}", oneof.name().to_case(Case::Pascal));
+                    let variant = format_ident!("{}", field.name().to_case(Case::Pascal));
+                    fields.push(quote! {
+                        #oneof_ident: Some(::pg_parse::ast::#module::#enum_ident::#variant(
+                            ::core::convert::From::from(#value)
+                        ))
+                    });
+                }
+                None if field.is_list() => fields.push(quote! { #ident: #value }),
+                None if field.kind().as_message().is_some() => fields.push(quote! {
+                    #ident: Some(::core::convert::From::from(#value))
+                }),
+                None => fields.push(quote! { #ident: #value }),
+            }
+        }
+
+        // Spelling out the remaining fields keeps the expansion free of `needless_update` lints
+        let rest = (!complete).then(|| quote! { ..::core::default::Default::default() });
+        quote! {
+            ::pg_parse::ast::#type_ident {
+                #(#fields,)*
+                #rest
+            }
+        }
+    }
+
+    fn value(&mut self, value: &Value) -> TokenStream {
+        match value {
+            Value::Bool(b) => quote! { #b },
+            Value::I32(v) | Value::EnumNumber(v) => Literal::i32_unsuffixed(*v).to_token_stream(),
+            Value::I64(v) => Literal::i64_unsuffixed(*v).to_token_stream(),
+            Value::U32(v) => Literal::u32_unsuffixed(*v).to_token_stream(),
+            Value::U64(v) => Literal::u64_unsuffixed(*v).to_token_stream(),
+            Value::F32(v) => Literal::f32_unsuffixed(*v).to_token_stream(),
+            Value::F64(v) => Literal::f64_unsuffixed(*v).to_token_stream(),
+            Value::String(s) => quote! { ::std::string::String::from(#s) },
+            Value::Bytes(b) => {
+                let bytes = b.iter();
+                quote! { ::std::vec![#(#bytes),*] }
+            }
+            Value::Message(m) if m.descriptor().full_name() == "pg_query.Node" => self.node(m),
+            Value::Message(m) => self.message(m),
+            Value::List(values) => {
+                let values: Vec<_> = values.iter().map(|v| self.value(v)).collect();
+                quote! { ::std::vec![#(#values),*] }
+            }
+            Value::Map(_) => unreachable!("pg_query.proto does not contain maps"),
+        }
+    }
+}
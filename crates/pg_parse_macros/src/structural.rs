@@ -0,0 +1,198 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::proto_analyser::{FieldType, LocationKind, Node, ProtoAnalyzer};
+
+pub fn structural_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
+    let enum_variants = analyser.enum_variants();
+    let nodes = analyser.nodes();
+
+    let mut impls = Vec::new();
+    for node in &nodes {
+        impls.push(message_impls(node));
+        impls.extend(oneof_impls(node));
+    }
+
+    let variant_idents: Vec<_> = enum_variants
+        .iter()
+        .map(|v| format_ident!("{}", v.name))
+        .collect();
+
+    quote! {
+        #(#impls)*
+
+        impl StructuralEq for NodeEnum {
+            fn structural_eq(&self, other: &Self) -> bool {
+                match (self, other) {
+                    #((NodeEnum::#variant_idents(a), NodeEnum::#variant_idents(b)) => a.structural_eq(b),)*
+                    _ => false,
+                }
+            }
+        }
+
+        impl StructuralHash for NodeEnum {
+            fn structural_hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+                ::std::hash::Hash::hash(&::std::mem::discriminant(self), state);
+                match self {
+                    #(NodeEnum::#variant_idents(n) => n.structural_hash(state),)*
+                }
+            }
+        }
+
+        impl StructuralEq for NodeRef<'_> {
+            fn structural_eq(&self, other: &Self) -> bool {
+                match (self, other) {
+                    #((NodeRef::#variant_idents(a), NodeRef::#variant_idents(b)) => a.structural_eq(b),)*
+                    _ => false,
+                }
+            }
+        }
+
+        impl StructuralHash for NodeRef<'_> {
+            fn structural_hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+                ::std::hash::Hash::hash(&::std::mem::discriminant(self), state);
+                match self {
+                    #(NodeRef::#variant_idents(n) => n.structural_hash(state),)*
+                }
+            }
+        }
+
+        impl Locations for NodeEnum {
+            fn strip_locations(&mut self) {
+                match self {
+                    #(NodeEnum::#variant_idents(n) => n.strip_locations(),)*
+                }
+            }
+
+            fn shift_locations(&mut self, delta: i32) {
+                match self {
+                    #(NodeEnum::#variant_idents(n) => n.shift_locations(delta),)*
+                }
+            }
+        }
+    }
+}
+
+fn message_impls(node: &Node) -> TokenStream {
+    let type_ident = format_ident!("{}", node.enum_variant_name);
+    let fields = node.stored_fields();
+
+    let compared: Vec<_> = fields
+        .iter()
+        .filter(|(_, f)| f.location.is_none())
+        .map(|(ident, _)| ident)
+        .collect();
+
+    // Only nodes and oneofs can contain locations
+    let nested: Vec<_> = fields
+        .iter()
+        .filter(|(_, f)| f.one_of.is_some() || matches!(f.r#type, FieldType::Node(_)))
+        .map(|(ident, _)| ident)
+        .collect();
+
+    let mut strip = Vec::new();
+    let mut shift = Vec::new();
+    for (ident, field) in &fields {
+        match field.location {
+            Some(LocationKind::Offset) => {
+                strip.push(quote! { self.#ident = -1; });
+                shift.push(quote! {
+                    if self.#ident >= 0 {
+                        self.#ident = self.#ident.saturating_add(delta);
+                    }
+                });
+            }
+            Some(LocationKind::Length) => strip.push(quote! { self.#ident = 0; }),
+            None => {}
+        }
+    }
+
+    quote! {
+        impl StructuralEq for protobuf::#type_ident {
+            #[allow(unused_variables)]
+            fn structural_eq(&self, other: &Self) -> bool {
+                true #(&& self.#compared.structural_eq(&other.#compared))*
+            }
+        }
+
+        impl StructuralHash for protobuf::#type_ident {
+            #[allow(unused_variables)]
+            fn structural_hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+                #(self.#compared.structural_hash(state);)*
+            }
+        }
+
+        impl Locations for protobuf::#type_ident {
+            fn strip_locations(&mut self) {
+                #(#strip)*
+                #(self.#nested.strip_locations();)*
+            }
+
+            #[allow(unused_variables)]
+            fn shift_locations(&mut self, delta: i32) {
+                #(#shift)*
+                #(self.#nested.shift_locations(delta);)*
+            }
+        }
+    }
+}
+
+fn oneof_impls(node: &Node) -> Vec<TokenStream> {
+    node.stored_fields()
+        .into_iter()
+        .filter_map(|(_, field)| field.one_of.as_ref())
+        .map(|one_of| {
+            let module_name = format_ident!("{}", one_of.module_name);
+            let enum_name = format_ident!("{}", one_of.enum_name);
+
+            let members = node.one_of_members(&one_of.field_name);
+            let variants: Vec<_> = members
+                .iter()
+                .map(|f| format_ident!("{}", f.one_of.as_ref().unwrap().variant_name))
+                .collect();
+            let nested: Vec<_> = members
+                .iter()
+                .filter(|f| matches!(f.r#type, FieldType::Node(_)))
+                .map(|f| format_ident!("{}", f.one_of.as_ref().unwrap().variant_name))
+                .collect();
+
+            quote! {
+                impl StructuralEq for protobuf::#module_name::#enum_name {
+                    fn structural_eq(&self, other: &Self) -> bool {
+                        match (self, other) {
+                            #((Self::#variants(a), Self::#variants(b)) => a.structural_eq(b),)*
+                            _ => false,
+                        }
+                    }
+                }
+
+                impl StructuralHash for protobuf::#module_name::#enum_name {
+                    fn structural_hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+                        ::std::hash::Hash::hash(&::std::mem::discriminant(self), state);
+                        match self {
+                            #(Self::#variants(n) => n.structural_hash(state),)*
+                        }
+                    }
+                }
+
+                impl Locations for protobuf::#module_name::#enum_name {
+                    fn strip_locations(&mut self) {
+                        match self {
+                            #(Self::#nested(n) => n.strip_locations(),)*
+                            #[allow(unreachable_patterns)]
+                            _ => {}
+                        }
+                    }
+
+                    fn shift_locations(&mut self, delta: i32) {
+                        match self {
+                            #(Self::#nested(n) => n.shift_locations(delta),)*
+                            #[allow(unreachable_patterns)]
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        })
+        .collect()
+}
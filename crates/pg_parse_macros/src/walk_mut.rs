@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::proto_analyser::{FieldType, Node, ProtoAnalyzer};
+
+pub fn walk_mut_mod(analyser: ProtoAnalyzer) -> proc_macro2::TokenStream {
+    let enum_variants = analyser.enum_variants();
+    let nodes = analyser.nodes();
+
+    // Map from type name to enum variant name and whether the variant is boxed
+    let type_to_variant: HashMap<String, (String, bool)> = enum_variants
+        .iter()
+        .map(|v| (v.type_name.clone(), (v.name.clone(), v.boxed)))
+        .collect();
+
+    let mut node_variant_names = Vec::new();
+    let mut node_field_handlers = Vec::new();
+
+    for node in &nodes {
+        if let Some((variant_name, _)) = type_to_variant.get(&node.enum_variant_name) {
+            node_variant_names.push(format_ident!("{}", variant_name));
+            node_field_handlers.push(field_handlers(node, &type_to_variant));
+        }
+    }
+
+    quote! {
+        /// Visits the direct children of `node` in field declaration order.
+        fn walk_children(node: &mut NodeEnum, walker: &mut Walker<'_>) {
+            match node {
+                #(NodeEnum::#node_variant_names(n) => {#node_field_handlers}),*,
+                _ => {
+                    // Some node types don't have any child nodes to traverse
+                }
+            };
+        }
+    }
+}
+
+fn field_handlers(node: &Node, type_to_variant: &HashMap<String, (String, bool)>) -> TokenStream {
+    let handlers: Vec<TokenStream> = node
+        .fields
+        .iter()
+        .filter_map(|field| {
+            let FieldType::Node(type_name) = &field.r#type else {
+                return None; // Filter out non-node fields
+            };
+
+            let field_name = format_ident!("{}", field.name.as_str());
+            let field_label = field.name.as_str();
+
+            // Nodes stored in a typed field are wrapped into a `NodeEnum` while they are visited
+            let typed = type_name.as_ref().map(|type_name| {
+                let type_name = type_name.to_case(Case::Pascal);
+                let type_ident = format_ident!("{}", type_name);
+                let (variant_name, boxed) = type_to_variant
+                    .get(&type_name)
+                    .unwrap_or_else(|| panic!("{type_name} is not a node type"));
+                let variant_ident = format_ident!("{}", variant_name);
+                let (wrap, unwrap) = if *boxed {
+                    (quote! { Box::new(x) }, quote! { *x })
+                } else {
+                    (quote! { x }, quote! { x })
+                };
+                let conversions = quote! {
+                    |x| NodeEnum::#variant_ident(#wrap),
+                    |n| match n {
+                        NodeEnum::#variant_ident(x) => Some(#unwrap),
+                        _ => None,
+                    }
+                };
+                (type_ident, conversions)
+            });
+
+            if let Some(one_of) = &field.one_of {
+                let oneof_field = format_ident!("{}", one_of.field_name);
+                let oneof_label = one_of.field_name.as_str();
+                let module_name = format_ident!("{}", one_of.module_name);
+                let enum_name = format_ident!("{}", one_of.enum_name);
+                let variant_name = format_ident!("{}", one_of.variant_name);
+                let visit = match typed {
+                    Some((type_ident, conversions)) => quote! {
+                        walker.visit_typed(
+                            #oneof_label,
+                            ::std::borrow::BorrowMut::<protobuf::#type_ident>::borrow_mut(x),
+                            #conversions
+                        )
+                    },
+                    None => quote! { walker.visit_node(x, #oneof_label) },
+                };
+                Some(quote! {
+                    if let Some(protobuf::#module_name::#enum_name::#variant_name(x)) = n.#oneof_field.as_mut() {
+                        if #visit {
+                            n.#oneof_field = None;
+                        }
+                    }
+                })
+            } else if field.repeated {
+                match typed {
+                    Some((_, conversions)) => Some(quote! {
+                        walker.visit_typed_list(#field_label, &mut n.#field_name, #conversions);
+                    }),
+                    None => Some(quote! {
+                        walker.visit_list(&mut n.#field_name, #field_label);
+                    }),
+                }
+            } else {
+                let visit = match typed {
+                    Some((type_ident, conversions)) => quote! {
+                        walker.visit_typed(
+                            #field_label,
+                            ::std::borrow::BorrowMut::<protobuf::#type_ident>::borrow_mut(x),
+                            #conversions
+                        )
+                    },
+                    None => quote! { walker.visit_node(x, #field_label) },
+                };
+                Some(quote! {
+                    if let Some(x) = n.#field_name.as_mut() {
+                        if #visit {
+                            n.#field_name = None;
+                        }
+                    }
+                })
+            }
+        })
+        .collect();
+
+    quote! {
+        #(#handlers)*
+    }
+}